@@ -0,0 +1,115 @@
+//! Entry point macros for `egui-opengl-internal`, use them through its `macros` feature.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::Parser, punctuated::Punctuated, FnArg, ItemFn, MetaNameValue, Token, Type, TypeReference,
+};
+
+/// Turns a UI function into an injectable DLL: generates the `DllMain` spawning an `Overlay` with it,
+/// which hooks the game, shows the UI and unloads the DLL on eject. The state is the type behind the
+/// second argument and must implement `Default`. Fields of `OverlayConfig` can be set as arguments.
+/// ```ignore
+/// #[overlay_main(console = true, menu_key = Some(VK_F1))]
+/// fn ui(ctx: &egui::Context, clicks: &mut i32) {
+///     egui::Window::new("Hello").show(ctx, |ui| {
+///         if ui.button(format!("Clicked {clicks} times")).clicked() {
+///             *clicks += 1;
+///         }
+///     });
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn overlay_main(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    expand(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let config = Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(attr)?;
+    let ui: ItemFn = syn::parse2(item)?;
+    let state = state_type(&ui)?;
+
+    let name = &ui.sig.ident;
+    let fields = config
+        .iter()
+        .map(|MetaNameValue { path, value, .. }| quote!(#path: #value,));
+
+    Ok(quote! {
+        #ui
+
+        #[no_mangle]
+        extern "system" fn DllMain(
+            module: ::egui_opengl_internal::__private::HMODULE,
+            reason: u32,
+            _: *mut ::core::ffi::c_void,
+        ) -> ::egui_opengl_internal::__private::BOOL {
+            if reason == ::egui_opengl_internal::__private::DLL_PROCESS_ATTACH {
+                ::egui_opengl_internal::Overlay::<#state>::spawn(
+                    ::egui_opengl_internal::OverlayConfig {
+                        #(#fields)*
+                        ..::egui_opengl_internal::OverlayConfig::new(module)
+                    },
+                    #name,
+                );
+            }
+            ::egui_opengl_internal::__private::BOOL(1)
+        }
+    })
+}
+
+/// Type of the state behind the `&mut` second argument of the UI function.
+fn state_type(ui: &ItemFn) -> syn::Result<&Type> {
+    match ui.sig.inputs.iter().nth(1) {
+        Some(FnArg::Typed(arg)) => match &*arg.ty {
+            Type::Reference(TypeReference {
+                mutability: Some(_),
+                elem,
+                ..
+            }) => Ok(elem),
+            ty => Err(syn::Error::new_spanned(ty, "expected `&mut State`")),
+        },
+        _ => Err(syn::Error::new_spanned(
+            &ui.sig,
+            "expected `fn(&egui::Context, &mut State)`",
+        )),
+    }
+}
+
+#[test]
+fn test_expand() {
+    let expanded = expand(
+        quote!(console = true),
+        quote!(
+            fn ui(ctx: &Context, state: &mut Vec<u8>) {}
+        ),
+    )
+    .unwrap()
+    .to_string();
+    assert!(expanded.contains("fn DllMain"));
+    assert!(expanded.contains("Overlay :: < Vec < u8 > > :: spawn"));
+    assert!(expanded.contains("console : true ,"));
+
+    let error = expand(
+        quote!(),
+        quote!(
+            fn ui(ctx: &Context) {}
+        ),
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "expected `fn(&egui::Context, &mut State)`"
+    );
+    assert!(expand(
+        quote!(console),
+        quote!(
+            fn ui(_: &Context, _: &mut i32) {}
+        )
+    )
+    .is_err());
+}
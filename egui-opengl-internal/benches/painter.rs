@@ -0,0 +1,72 @@
+//! Painter throughput on synthetic workloads. Run with `cargo bench --features golden`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use egui_opengl_internal::{
+    golden::GoldenRenderer,
+    workload::{Workload, WORKLOADS},
+    PreparedFrame, SurfaceConfig,
+};
+
+const SIZE: (u32, u32) = (1920, 1080);
+
+fn prepare(renderer: &GoldenRenderer, workload: &Workload) -> PreparedFrame {
+    PreparedFrame::run(renderer.context(), SIZE, 1.0, |ctx| workload.ui(ctx))
+}
+
+fn bench_prepare(c: &mut Criterion) {
+    let renderer = renderer();
+    let mut group = c.benchmark_group("prepare");
+
+    for workload in WORKLOADS {
+        group.bench_function(workload.name(), |b| b.iter(|| prepare(&renderer, workload)));
+    }
+}
+
+/// Draw cost only, textures are uploaded once before measuring.
+fn bench_paint(c: &mut Criterion) {
+    let mut renderer = renderer();
+    let mut group = c.benchmark_group("paint");
+
+    for workload in WORKLOADS {
+        let mut frame = prepare(&renderer, workload);
+        renderer.paint(&frame);
+        frame.textures_delta.clear();
+
+        group.bench_function(workload.name(), |b| {
+            b.iter(|| {
+                renderer.paint(&frame);
+                renderer.finish();
+            })
+        });
+    }
+}
+
+/// Upload and draw cost of a first frame, including the font atlas.
+fn bench_upload(c: &mut Criterion) {
+    let mut group = c.benchmark_group("upload");
+    let workload = WORKLOADS[0];
+
+    group.bench_function(workload.name(), |b| {
+        b.iter_batched(
+            || {
+                let renderer = renderer();
+                let frame = prepare(&renderer, &workload);
+                (renderer, frame)
+            },
+            |(mut renderer, frame)| {
+                renderer.paint(&frame);
+                renderer.finish();
+                renderer
+            },
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+fn renderer() -> GoldenRenderer {
+    GoldenRenderer::new(SurfaceConfig::from_pixel_format(None))
+        .expect("failed to create hidden OpenGL context")
+}
+
+criterion_group!(benches, bench_prepare, bench_paint, bench_upload);
+criterion_main!(benches);
@@ -1,27 +1,525 @@
-use crate::{input::InputCollector, painter, utils};
-use clipboard::{windows_clipboard::WindowsClipboardContext, ClipboardProvider};
-use egui::Context;
-use once_cell::sync::OnceCell;
-use std::ops::DerefMut;
-use windows::Win32::{
-    Foundation::{HWND, LPARAM, RECT, WPARAM},
-    Graphics::{
-        Gdi::{WindowFromDC, HDC},
-        OpenGL::{wglCreateContext, wglGetCurrentContext, wglMakeCurrent, HGLRC},
+use crate::{
+    backoff::Backoff,
+    clipboard,
+    config::{Config, Settings},
+    gl_state::GlStateGuard,
+    input::{
+        self, InputCollector, KeyCode, MouseMotion, MouseTracker, ScrollConfig, TextInputSource,
+        ZoomConfig,
     },
-    UI::WindowsAndMessaging::{GetClientRect, WM_SIZE},
+    layers::Layers,
+    notifications::{Notification, Notifications, Notifier},
+    painter,
+    panic_guard::{self, UiPanic},
+    stats::{FrameClock, FrameTiming},
+    subclass,
+    tasks::{TaskSender, Tasks},
+    utils, AppEvent, CustomShader, Error, OpenGLAppBuilder, RenderStats, SurfaceConfig,
 };
+use egui::{Color32, ColorImage, Context, TextureOptions};
+use std::{
+    ops::DerefMut,
+    sync::atomic::{AtomicBool, AtomicIsize, AtomicU32, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use windows::{
+    core::{HSTRING, PCWSTR},
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM},
+        Graphics::{
+            Gdi::{
+                GetMonitorInfoW, MonitorFromWindow, WindowFromDC, HDC, MONITORINFO,
+                MONITOR_DEFAULTTONEAREST,
+            },
+            OpenGL::{
+                wglCreateContext, wglDeleteContext, wglGetCurrentContext, wglMakeCurrent,
+                wglShareLists, GetPixelFormat, HGLRC,
+            },
+        },
+        System::{
+            SystemServices::{MK_LBUTTON, MK_MBUTTON, MK_RBUTTON, MK_XBUTTON1, MK_XBUTTON2},
+            Threading::GetCurrentThreadId,
+        },
+        UI::{
+            HiDpi::GetDpiForWindow,
+            Input::KeyboardAndMouse::{GetCapture, ReleaseCapture, SetCapture, VIRTUAL_KEY},
+            Shell::{DragAcceptFiles, DragFinish, ShellExecuteW, HDROP},
+            WindowsAndMessaging::{
+                CallWindowProcW, ChangeWindowMessageFilterEx, DefWindowProcW, GetClientRect,
+                GetForegroundWindow, GetWindowLongW, IsIconic, IsWindow, LoadCursorW, SetCursor,
+                DLGC_WANTALLKEYS, DLGC_WANTARROWS, DLGC_WANTCHARS, DLGC_WANTTAB, GWL_EXSTYLE,
+                HCURSOR, HTCLIENT, IDC_APPSTARTING, IDC_ARROW, IDC_CROSS, IDC_HAND, IDC_HELP,
+                IDC_IBEAM, IDC_NO, IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE,
+                IDC_WAIT, MSGFLT_ALLOW, SW_SHOWNORMAL, USER_DEFAULT_SCREEN_DPI, WM_CAPTURECHANGED,
+                WM_COPYDATA, WM_DISPLAYCHANGE, WM_DPICHANGED, WM_DROPFILES, WM_GETDLGCODE,
+                WM_INPUT, WM_KEYDOWN, WM_KEYFIRST, WM_KEYLAST, WM_KILLFOCUS, WM_LBUTTONDBLCLK,
+                WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN, WM_MBUTTONUP,
+                WM_MOUSEFIRST, WM_MOUSELAST, WM_MOUSEMOVE, WM_NCDESTROY, WM_PASTE, WM_POINTERDOWN,
+                WM_POINTERUP, WM_POINTERUPDATE, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN, WM_RBUTTONUP,
+                WM_SETCURSOR, WM_SIZE, WM_SYSKEYDOWN, WM_TOUCH, WM_XBUTTONDBLCLK, WM_XBUTTONDOWN,
+                WM_XBUTTONUP, WNDPROC, WS_EX_ACCEPTFILES,
+            },
+        },
+    },
+};
+
+/// Screenshot requested with [`OpenGLApp::capture_frame`].
+#[allow(clippy::type_complexity)]
+struct Capture<T> {
+    include_ui: bool,
+    apply: Box<dyn FnOnce(&mut T, ColorImage) + Send + 'static>,
+}
+
+/// Primitives of the last UI run, painted again between UI ticks and while nothing changed,
+/// see [`OpenGLApp::set_ui_rate`] and [`OpenGLApp::set_damage_tracking`].
+struct CachedFrame {
+    primitives: Vec<egui::ClippedPrimitive>,
+    pixels_per_point: f32,
+    client_rect: (u32, u32),
+}
 
 #[allow(clippy::type_complexity)]
 struct AppData<T> {
     ui: Box<dyn FnMut(&Context, &mut T) + 'static>,
-    gl_context: HGLRC,
+    gl_context: Option<HGLRC>,
     window: HWND,
-    painter: painter::Painter,
+    painter: Option<painter::Painter>,
     input_collector: InputCollector,
     ctx: Context,
     client_rect: (u32, u32),
     state: T,
+    window_lost: bool,
+    on_event: Option<Box<dyn FnMut(AppEvent) + 'static>>,
+    on_error: Option<Box<dyn FnMut(&Error) + 'static>>,
+    on_resize: Option<Box<dyn FnMut(u32, u32) + 'static>>,
+    context_backoff: Backoff,
+    surface: Option<SurfaceConfig>,
+    /// Replaces the detected [`SurfaceConfig::framebuffer_srgb`].
+    framebuffer_srgb: Option<bool>,
+    thread_policy: ThreadPolicy,
+    window_policy: WindowPolicy,
+    gl_state_check: bool,
+    /// Set while the mouse is captured for an egui drag.
+    pointer_captured: bool,
+    /// `GL_MAX_TEXTURE_SIZE` of the overlay context, known once the painter exists.
+    max_texture_side: Option<usize>,
+    /// Flipped to force a font atlas rebuild, see [`OpenGLApp::rebuild_font_atlas`].
+    atlas_generation: bool,
+    /// Scale of the window's monitor, `1.0` at 96 DPI.
+    dpi_scale: f32,
+    /// Set with [`OpenGLApp::set_pixels_per_point`], replaces `dpi_scale`.
+    pixels_per_point: Option<f32>,
+    compositing: bool,
+    debug_overlay: bool,
+    /// Minimum time between UI runs, `None` runs the UI every frame.
+    ui_interval: Option<Duration>,
+    last_ui_run: Option<Instant>,
+    /// When egui asked to run again after the last run, `None` if it didn't.
+    repaint_at: Option<Instant>,
+    damage_tracking: bool,
+    cached_frame: Option<CachedFrame>,
+    ui_runs: u64,
+    cached_frames: u64,
+    /// Set when the UI closure panicked, the UI doesn't run until the user resumes it.
+    ui_panic: Option<UiPanic>,
+    #[cfg(feature = "persistence")]
+    memory_autosave: crate::memory::Autosave,
+    /// Set by [`OpenGLApp::init_with_persisted_state`].
+    #[cfg(feature = "persistence")]
+    state_saver: Option<crate::state::StateSaver<T>>,
+    render_resolution: Option<(u32, u32)>,
+    /// Set with [`OpenGLApp::set_custom_shader`], applied once the context is current.
+    pending_shader: Option<Option<CustomShader>>,
+    /// Textures unregistered with [`OpenGLApp::unregister_texture`], deleted once the context is current.
+    unregistered_textures: Vec<egui::TextureId>,
+    /// Context the game had current when presenting, a new one means it recreated its context.
+    game_context: Option<HGLRC>,
+    /// Pixel format of the device context presented to, a new one can't take the overlay's context.
+    pixel_format: Option<i32>,
+    context_mode: ContextMode,
+    /// Set when the context mode changed, the context is rebuilt on the next frame.
+    rebuild_context: bool,
+    /// Cursor egui asked for in the last frame, set on `WM_SETCURSOR`.
+    cursor_icon: egui::CursorIcon,
+    input_blocking: InputBlocking,
+    /// Whether the menu was open last frame, closing it releases held input.
+    menu_was_open: bool,
+    /// Whether the window accepted dropped files before the overlay enabled them.
+    game_accepts_files: bool,
+    /// Window the overlay enabled dropped files on, see [`Self::restore_files`].
+    files_window: Option<HWND>,
+    /// Next user texture ID of the destroyed painter, the next one continues from it.
+    next_user_texture: u64,
+    /// Set with [`OpenGLApp::set_gamepad_navigation`].
+    #[cfg(feature = "gamepad")]
+    gamepad: Option<crate::gamepad::GamepadNavigation>,
+}
+
+/// Decides which threads draw the overlay when the game presents from more than one thread.
+/// Only one thread draws at a time, presents that would have to wait for another thread are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThreadPolicy {
+    /// The first thread that presents owns the overlay, presents from other threads are ignored.
+    #[default]
+    FirstThread,
+    /// Any presenting thread may draw the overlay.
+    AnyThread,
+}
+
+/// Decides which window the overlay draws on when the game presents to more than one, e.g. editors,
+/// launchers or split-screen tools. To draw on several windows, use one app per window with [`WindowPolicy::Bound`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowPolicy {
+    /// The overlay moves to the window of each present, following games that recreate their window.
+    #[default]
+    Follow,
+    /// Only presents to the window the app was initialized with are drawn, presents to other windows are skipped.
+    /// Once that window is destroyed the app stays lost until it's initialized again, see [`OpenGLApp::is_window_lost`].
+    Bound,
+}
+
+/// Which OpenGL context the overlay paints with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContextMode {
+    /// A context of its own, isolated from the game's state and objects.
+    #[default]
+    Separate,
+    /// A context of its own sharing objects with the game's context through `wglShareLists`,
+    /// so textures of the game can be registered with [`OpenGLApp::register_gl_texture`].
+    Shared,
+    /// The context the game has current when presenting, for drivers that fail to create another one.
+    /// The painter saves and restores the state it changes.
+    Game,
+}
+
+/// What [`OpenGLApp::init_data`] sets up before the context is made current the first time,
+/// so a failing context is reported to `on_error` and never created in the wrong mode.
+#[allow(clippy::type_complexity)]
+pub(crate) struct InitOptions<T> {
+    pub ui: Box<dyn FnMut(&Context, &mut T) + 'static>,
+    pub state: T,
+    pub context: Context,
+    pub context_mode: ContextMode,
+    pub on_event: Option<Box<dyn FnMut(AppEvent) + 'static>>,
+    pub on_error: Option<Box<dyn FnMut(&Error) + 'static>>,
+}
+
+/// Which input [`OpenGLApp::wnd_proc`] keeps from the game while the menu is open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputBlocking {
+    /// Only input egui wants, e.g. clicks on a window or typing into a focused text field.
+    #[default]
+    WhenWanted,
+    /// All mouse, keyboard and raw input, so the game doesn't react to clicks on empty space between windows.
+    All,
+}
+
+/// How [`OpenGLApp::attach_wnd_proc`] receives the messages of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageCapture {
+    /// Subclasses the window with `SetWindowSubclass`. Sees every message and can answer it.
+    #[default]
+    Subclass,
+    /// Hooks the window's thread with `WH_GETMESSAGE` and `WH_CALLWNDPROC`, for games that set their
+    /// window procedure again and drop the subclass. Consumed keyboard and mouse input is still kept
+    /// from the game, but sent messages can only be observed, e.g. the game may override egui's cursor.
+    Hook,
+}
+
+/// Sent with the data of drag and drop operations, has no constant in `windows`.
+const WM_COPYGLOBALDATA: u32 = 0x0049;
+
+/// What the window procedure should do with a message, see [`OpenGLApp::wnd_proc_result`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WndProcResult {
+    /// egui didn't use the message, pass it on to the game.
+    PassThrough,
+    /// egui used the message, return the value without passing the message on.
+    Consumed(LRESULT),
+}
+
+impl WndProcResult {
+    pub fn is_consumed(self) -> bool {
+        matches!(self, Self::Consumed(_))
+    }
+}
+
+impl From<WndProcResult> for Option<LRESULT> {
+    fn from(result: WndProcResult) -> Self {
+        match result {
+            WndProcResult::Consumed(result) => Some(result),
+            WndProcResult::PassThrough => None,
+        }
+    }
+}
+
+/// Number of consecutive context failures after which they are reported to the error handler.
+const PERSISTENT_FAILURES: u32 = 3;
+
+impl<T> AppData<T> {
+    fn emit(&mut self, event: AppEvent) {
+        if let Some(handler) = self.on_event.as_mut() {
+            handler(event);
+        }
+    }
+
+    fn report(&mut self, error: &Error) {
+        if let Some(handler) = self.on_error.as_mut() {
+            handler(error);
+        }
+    }
+
+    /// Takes the last frame if it may be painted again instead of running the UI: before the next UI tick,
+    /// or with damage tracking while there is no new input and egui didn't ask to run again.
+    fn take_cached_frame(&mut self, input_pending: bool) -> Option<CachedFrame> {
+        let frame = self.cached_frame.take()?;
+        if frame.client_rect != self.client_rect {
+            return None;
+        }
+
+        let before_tick = self
+            .ui_interval
+            .zip(self.last_ui_run)
+            .is_some_and(|(interval, run)| run.elapsed() < interval);
+        let unchanged = self.damage_tracking && !input_pending && !self.repaint_due();
+        (before_tick || unchanged).then_some(frame)
+    }
+
+    fn repaint_due(&self) -> bool {
+        match self.repaint_at {
+            Some(at) => Instant::now() >= at,
+            // Requested from outside of the UI since, e.g. by a finished task.
+            None => self.ctx.has_requested_repaint(),
+        }
+    }
+
+    fn caches_frames(&self) -> bool {
+        self.ui_interval.is_some() || self.damage_tracking
+    }
+
+    /// The detected `surface` with the sRGB override of [`OpenGLApp::set_framebuffer_srgb`] applied.
+    fn painted_surface(&self, mut surface: SurfaceConfig) -> SurfaceConfig {
+        if let Some(srgb) = self.framebuffer_srgb {
+            surface.framebuffer_srgb = srgb;
+        }
+        surface
+    }
+
+    /// Makes the overlay context current on `hdc`, lazily creating the context and painter.
+    /// `game_context` is the context the game had current when presenting.
+    unsafe fn make_current(&mut self, hdc: HDC, game_context: HGLRC) -> Result<(), Error> {
+        if self.context_mode == ContextMode::Game {
+            if game_context.0 == 0 {
+                return Err(Error::NoGameContext);
+            }
+        } else {
+            #[cfg(feature = "gl-debug")]
+            let created = self.gl_context.is_none();
+            let gl_context = match self.gl_context {
+                Some(gl_context) => gl_context,
+                None => {
+                    let gl_context = wglCreateContext(hdc).map_err(Error::CreateContext)?;
+                    // Sharing has to be set up before the new context owns any objects.
+                    if self.context_mode == ContextMode::Shared
+                        && wglShareLists(game_context, gl_context).is_err()
+                    {
+                        log!("Failed to share objects with the game's context, textures of the game won't be visible");
+                    }
+                    self.gl_context = Some(gl_context);
+                    gl_context
+                }
+            };
+
+            wglMakeCurrent(hdc, gl_context).map_err(Error::MakeCurrent)?;
+            // Only on contexts of our own, the game's callback and debug output stay untouched.
+            #[cfg(feature = "gl-debug")]
+            if created {
+                crate::debug::install();
+            }
+        }
+
+        if self.painter.is_none() {
+            let surface = SurfaceConfig::detect(hdc);
+            let mut painter = painter::Painter::new().map_err(Error::Shader)?;
+            painter.set_surface(self.painted_surface(surface));
+            painter.set_state_check(self.gl_state_check);
+            painter.set_compositing(self.compositing);
+            painter.set_debug_overlay(self.debug_overlay);
+            painter.set_render_resolution(self.render_resolution);
+            painter.set_next_user_texture(self.next_user_texture);
+
+            self.surface = Some(surface);
+            self.max_texture_side = Some(painter.max_texture_side()).filter(|&side| side > 1);
+            self.painter = Some(painter);
+        }
+
+        if let (Some(shader), Some(painter)) = (self.pending_shader.take(), self.painter.as_mut()) {
+            if let Err(e) = painter.set_custom_shader(shader.as_ref()) {
+                log!("Keeping the current shaders: {e}");
+                self.report(&Error::Shader(e));
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn restore_context(&mut self, hdc: HDC, o_context: HGLRC) {
+        if self.context_mode == ContextMode::Game {
+            return;
+        }
+
+        if let Err(e) = wglMakeCurrent(hdc, o_context) {
+            self.report(&Error::MakeCurrent(e));
+        }
+    }
+
+    /// Deletes the painter's objects and the overlay context, which are recreated on the next frame.
+    unsafe fn destroy_gl(&mut self, hdc: HDC) {
+        if let Some(painter) = self.painter.as_ref() {
+            self.next_user_texture = painter.next_user_texture();
+        }
+        let o_context = wglGetCurrentContext();
+        let Some(gl_context) = self.gl_context.take() else {
+            // Painting in the game's context, whose objects are gone if the game replaced it.
+            if let Some(painter) = self.painter.take() {
+                if self.game_context == Some(o_context) {
+                    painter.destroy();
+                } else {
+                    std::mem::forget(painter);
+                }
+            }
+            self.surface = None;
+            self.max_texture_side = None;
+            return;
+        };
+
+        match wglMakeCurrent(hdc, gl_context) {
+            Ok(()) => {
+                if let Some(painter) = self.painter.take() {
+                    painter.destroy();
+                }
+            }
+            Err(e) => {
+                // Deleting the objects with another context current would delete that context's objects instead.
+                std::mem::forget(self.painter.take());
+                self.report(&Error::MakeCurrent(e));
+            }
+        }
+        self.restore_context(hdc, o_context);
+
+        if let Err(e) = wglDeleteContext(gl_context) {
+            log!("Failed to delete the overlay context: {e}");
+        }
+
+        self.surface = None;
+        self.max_texture_side = None;
+    }
+
+    fn context_failed(&mut self, error: &Error) {
+        if self.context_backoff.fail() >= PERSISTENT_FAILURES {
+            self.report(error);
+        }
+    }
+
+    /// Decides whether to capture the mouse, so egui drags continue outside of the client area.
+    /// Returns `Some(true)` to capture and `Some(false)` to release it. Both send `WM_CAPTURECHANGED`
+    /// right away, so they have to be called from the window's thread with the lock released.
+    fn capture_change(&mut self, umsg: u32, wparam: WPARAM) -> Option<bool> {
+        let capture = match umsg {
+            WM_LBUTTONDOWN | WM_LBUTTONDBLCLK | WM_RBUTTONDOWN | WM_RBUTTONDBLCLK
+            | WM_MBUTTONDOWN | WM_MBUTTONDBLCLK | WM_XBUTTONDOWN | WM_XBUTTONDBLCLK => {
+                self.ctx.wants_pointer_input()
+            }
+            WM_MOUSEMOVE => self.ctx.is_using_pointer(),
+            WM_LBUTTONUP | WM_RBUTTONUP | WM_MBUTTONUP | WM_XBUTTONUP => {
+                let buttons = MK_LBUTTON | MK_RBUTTON | MK_MBUTTON | MK_XBUTTON1 | MK_XBUTTON2;
+                if self.pointer_captured && wparam.0 as u32 & buttons.0 == 0 {
+                    self.pointer_captured = false;
+                    return Some(false);
+                }
+                false
+            }
+            WM_CAPTURECHANGED => {
+                self.pointer_captured = false;
+                false
+            }
+            WM_KILLFOCUS if self.pointer_captured => {
+                self.pointer_captured = false;
+                return Some(false);
+            }
+            _ => false,
+        };
+
+        // Never take the capture away from the game.
+        if capture && !self.pointer_captured && unsafe { GetCapture() }.0 == 0 {
+            self.pointer_captured = true;
+            return Some(true);
+        }
+
+        None
+    }
+
+    /// Scale egui renders at, before its zoom factor is applied.
+    fn native_pixels_per_point(&self) -> f32 {
+        self.pixels_per_point.unwrap_or(self.dpi_scale)
+    }
+
+    /// Lets files be dropped onto the window, see [`Self::drop_files`].
+    unsafe fn accept_files(&mut self) {
+        // Read once per window, afterwards the flag is the overlay's own.
+        if self.files_window == Some(self.window) {
+            return;
+        }
+        self.restore_files();
+
+        let ex_style = GetWindowLongW(self.window, GWL_EXSTYLE) as u32;
+        self.game_accepts_files = ex_style & WS_EX_ACCEPTFILES.0 != 0;
+        DragAcceptFiles(self.window, true);
+        self.files_window = Some(self.window);
+
+        // Games running elevated would otherwise not receive drops from Explorer.
+        for msg in [WM_DROPFILES, WM_COPYDATA, WM_COPYGLOBALDATA] {
+            let _ = ChangeWindowMessageFilterEx(self.window, msg, MSGFLT_ALLOW, None);
+        }
+    }
+
+    /// Stops the window from accepting dropped files if it didn't before [`Self::accept_files`].
+    unsafe fn restore_files(&mut self) {
+        if let Some(window) = self.files_window.take() {
+            if !self.game_accepts_files && IsWindow(window).as_bool() {
+                DragAcceptFiles(window, false);
+            }
+        }
+    }
+
+    /// Hands files dropped onto egui's windows to egui, other drops go to the game if it accepts files.
+    fn drop_files(&mut self, wparam: WPARAM, lparam: LPARAM, menu_open: bool) -> Option<LRESULT> {
+        let hdrop = HDROP(wparam.0 as _);
+        let over_egui = menu_open
+            && self
+                .ctx
+                .layer_id_at(self.input_collector.drop_pos(wparam.0))
+                .is_some();
+
+        if !over_egui && self.game_accepts_files {
+            return None;
+        }
+
+        if over_egui {
+            self.input_collector
+                .process(WM_DROPFILES, wparam.0, lparam.0);
+        }
+        unsafe { DragFinish(hdrop) };
+        Some(LRESULT(0))
+    }
+
+    fn lose_window(&mut self) {
+        if !self.window_lost {
+            self.window_lost = true;
+            let window = self.window;
+            self.emit(AppEvent::WindowLost(window));
+        }
+    }
 }
 
 #[cfg(feature = "parking-lot")]
@@ -33,11 +531,60 @@ use lock_api::MappedMutexGuard;
 
 /// Heart and soul of this integration.
 /// Main methods you are going to use are:
-/// * [`Self::render`] - Should be called inside of wglSwapBuffers hook.
+/// * [`Self::render`] - Should be called inside of the hook of the game's present function, e.g. `wglSwapBuffers`.
 /// * [`Self::wnd_proc`] - Should be called on each `WndProc`.
 pub struct OpenGLApp<T = ()> {
     data: Mutex<Option<AppData<T>>>,
-    hwnd: OnceCell<HWND>,
+    /// Set by the first `init_*` call, cleared by [`Self::shutdown`].
+    initialized: AtomicBool,
+    /// Id of the thread that last drew the overlay, `0` if none did yet.
+    render_thread: AtomicU32,
+    /// Handle to the context, kept outside of the app data so [`Self::context`] works from within the UI.
+    ctx: Mutex<Option<Context>>,
+    tasks: Tasks<T>,
+    notifications: Notifications,
+    settings: Settings,
+    rebuild_atlas: AtomicBool,
+    /// Bits of the `f32` set with [`Self::set_opacity`].
+    opacity: AtomicU32,
+    /// Bits of the scale egui rendered the last frame at, see [`Self::pixels_per_point`].
+    native_pixels_per_point: AtomicU32,
+    /// Bits of the `f32`s set with [`Self::set_font_gamma`] and [`Self::set_font_weight`].
+    font_gamma: AtomicU32,
+    font_weight: AtomicU32,
+    captures: Mutex<Vec<Capture<T>>>,
+    /// Stats of the last painted frame, kept outside of the app data so the UI can show them.
+    last_frame_stats: Mutex<Option<RenderStats>>,
+    frame_clock: Mutex<FrameClock>,
+    /// Set with [`Self::set_fps_overlay`].
+    fps_overlay: AtomicBool,
+    menu_open: AtomicBool,
+    /// Set with [`Self::hide`], frames are skipped entirely.
+    hidden: AtomicBool,
+    /// Virtual key code set with [`Self::set_toggle_key`], `0` for none.
+    toggle_key: AtomicU32,
+    /// Whether the UI closure runs while the menu is closed, see [`Self::set_run_ui_when_closed`].
+    run_ui_when_closed: AtomicBool,
+    #[cfg(feature = "mouse-unlock")]
+    mouse_unlock: AtomicBool,
+    /// Events queued with [`Self::push_event`], kept outside of the app data so the UI can queue them.
+    pushed_events: Mutex<Vec<egui::Event>>,
+    mouse_tracker: Mutex<MouseTracker>,
+    /// Last key pressed in the window, see [`Self::take_key_press`].
+    key_press: Mutex<Option<KeyCode>>,
+    /// Window attached with [`Self::attach_wnd_proc`], `0` when none is.
+    attached_window: AtomicIsize,
+    /// Next user texture ID kept when the app data is dropped, so IDs held from before don't alias new textures.
+    next_user_texture: AtomicU64,
+    /// Set with [`Self::set_message_capture`] to [`MessageCapture::Hook`].
+    hook_messages: AtomicBool,
+    /// UI closure set with [`Self::set_ui`], swapped in before the next frame.
+    #[allow(clippy::type_complexity)]
+    pending_ui: Mutex<Option<Box<dyn FnMut(&Context, &mut T) + 'static>>>,
+    layers: Layers<T>,
+    /// Where egui's memory is kept between launches, see [`Self::set_memory_path`].
+    #[cfg(feature = "persistence")]
+    memory_path: Mutex<Option<std::path::PathBuf>>,
 }
 
 impl<T> OpenGLApp<T> {
@@ -45,17 +592,63 @@ impl<T> OpenGLApp<T> {
     pub const fn new() -> Self {
         Self {
             data: Mutex::new(None),
-            hwnd: OnceCell::new(),
+            initialized: AtomicBool::new(false),
+            render_thread: AtomicU32::new(0),
+            ctx: Mutex::new(None),
+            tasks: Tasks::new(),
+            notifications: Notifications::new(),
+            settings: Settings::new(),
+            rebuild_atlas: AtomicBool::new(false),
+            opacity: AtomicU32::new(1f32.to_bits()),
+            native_pixels_per_point: AtomicU32::new(1f32.to_bits()),
+            font_gamma: AtomicU32::new(1f32.to_bits()),
+            font_weight: AtomicU32::new(1f32.to_bits()),
+            captures: Mutex::new(vec![]),
+            last_frame_stats: Mutex::new(None),
+            frame_clock: Mutex::new(FrameClock::new()),
+            fps_overlay: AtomicBool::new(false),
+            menu_open: AtomicBool::new(true),
+            hidden: AtomicBool::new(false),
+            toggle_key: AtomicU32::new(0),
+            run_ui_when_closed: AtomicBool::new(true),
+            #[cfg(feature = "mouse-unlock")]
+            mouse_unlock: AtomicBool::new(false),
+            pushed_events: Mutex::new(vec![]),
+            mouse_tracker: Mutex::new(MouseTracker::new()),
+            key_press: Mutex::new(None),
+            attached_window: AtomicIsize::new(0),
+            next_user_texture: AtomicU64::new(0),
+            hook_messages: AtomicBool::new(false),
+            pending_ui: Mutex::new(None),
+            layers: Layers::new(),
+            #[cfg(feature = "persistence")]
+            memory_path: Mutex::new(None),
         }
     }
 
     /// Checks if the app is ready to draw and if it's safe to invoke `render`, `wndproc`, etc.
     /// `true` means that you have already called an `init_*` on the application.
     pub fn is_ready(&self) -> bool {
-        self.hwnd.get().is_some()
+        self.initialized.load(Ordering::Acquire)
+    }
+
+    /// Returns a handle to the egui context, `None` until the app is initialized. Use it to request repaints,
+    /// install image loaders or change the style from other threads. Safe to call from within the UI closure.
+    pub fn context(&self) -> Option<Context> {
+        self.ctx.lock().clone()
     }
 
-    /// Initializes application and state. You should call this only once!
+    /// Starts configuring the app with a given state, see [`OpenGLAppBuilder`].
+    pub fn builder_with_state(state: T) -> OpenGLAppBuilder<T> {
+        OpenGLAppBuilder::with_state(state)
+    }
+
+    /// Initializes application and state.
+    /// Fails if `window` is invalid or the app was initialized before, unless it was shut down with
+    /// [`Self::shutdown`] or its window was destroyed: then it's initialized again for the new window,
+    /// replacing the UI closure, state and egui context. Failing to create the OpenGL context is not
+    /// an error here, [`Self::render`] keeps retrying.
+    #[inline]
     pub fn init_with_state_context(
         &self,
         hdc: HDC,
@@ -63,43 +656,146 @@ impl<T> OpenGLApp<T> {
         ui: impl FnMut(&Context, &mut T) + 'static,
         state: T,
         context: Context,
-    ) {
-        unsafe {
-            if self.hwnd.get().is_some() {
-                panic_msg!("You must call init only once");
-            }
+    ) -> Result<(), Error> {
+        OpenGLAppBuilder::with_state(state)
+            .ui(ui)
+            .context(context)
+            .init(self, hdc, window)
+    }
+
+    pub(crate) fn init_data(
+        &self,
+        hdc: HDC,
+        window: HWND,
+        options: InitOptions<T>,
+    ) -> Result<(), Error> {
+        let InitOptions {
+            ui,
+            state,
+            context,
+            context_mode,
+            on_event,
+            on_error,
+        } = options;
 
+        unsafe {
             if window.0 == -1 {
-                panic_msg!("Invalid output window descriptor");
+                return Err(Error::InvalidWindow);
             }
 
-            let _ = self.hwnd.set(window);
+            if self.initialized.swap(true, Ordering::AcqRel) {
+                self.replace_lost_window(hdc)?;
+            }
 
             // loads gl with all the opengl functions using get_proc_address which is hardcoded to look in the opengl32.dll module
             gl::load_with(|s| utils::get_proc_address(s) as *const _);
 
             let o_context = wglGetCurrentContext();
-            let gl_context = wglCreateContext(hdc).unwrap();
-            wglMakeCurrent(hdc, gl_context).unwrap();
+            *self.ctx.lock() = Some(context.clone());
+            self.tasks.set_context(context.clone());
+            self.notifications.set_context(context.clone());
+
+            // Detached viewports would need windows and GL surfaces of their own, they are shown as
+            // windows inside the game's window instead.
+            if !context.embed_viewports() {
+                log!("Native viewports are not supported, embedding them into the main window");
+                context.set_embed_viewports(true);
+            }
+
+            // Decodes images for `ui.image(...)`, `include_image!` and `file://` uris.
+            #[cfg(feature = "loaders")]
+            egui_extras::install_image_loaders(&context);
 
-            let painter = painter::Painter::new();
+            #[cfg(feature = "persistence")]
+            if let Some(path) = self.memory_path.lock().as_ref() {
+                if let Err(e) = crate::memory::load(&context, path) {
+                    log!("Failed to restore egui memory from {}: {e}", path.display());
+                }
+            }
 
-            *self.data.lock() = Some(AppData {
+            let mut data = AppData {
                 input_collector: InputCollector::new(window),
-                ui: Box::new(ui),
-                gl_context,
+                ui,
+                gl_context: None,
                 window,
                 ctx: context,
                 client_rect: (0, 0),
                 state,
-                painter,
-            });
+                painter: None,
+                window_lost: false,
+                on_event,
+                on_error,
+                on_resize: None,
+                context_backoff: Backoff::new(),
+                surface: None,
+                framebuffer_srgb: None,
+                thread_policy: ThreadPolicy::default(),
+                window_policy: WindowPolicy::default(),
+                gl_state_check: cfg!(debug_assertions),
+                pointer_captured: false,
+                max_texture_side: None,
+                atlas_generation: false,
+                dpi_scale: self.get_window_scale(window),
+                pixels_per_point: None,
+                compositing: false,
+                debug_overlay: false,
+                ui_interval: None,
+                last_ui_run: None,
+                repaint_at: None,
+                damage_tracking: false,
+                cached_frame: None,
+                ui_runs: 0,
+                cached_frames: 0,
+                ui_panic: None,
+                #[cfg(feature = "persistence")]
+                memory_autosave: crate::memory::Autosave::new(),
+                #[cfg(feature = "persistence")]
+                state_saver: None,
+                render_resolution: None,
+                pending_shader: None,
+                unregistered_textures: vec![],
+                game_context: (o_context.0 != 0).then_some(o_context),
+                pixel_format: pixel_format(hdc),
+                context_mode,
+                rebuild_context: false,
+                cursor_icon: egui::CursorIcon::Default,
+                input_blocking: InputBlocking::default(),
+                menu_was_open: true,
+                game_accepts_files: false,
+                files_window: None,
+                next_user_texture: self.next_user_texture.load(Ordering::Acquire),
+                #[cfg(feature = "gamepad")]
+                gamepad: None,
+            };
+
+            data.accept_files();
+
+            // Context failures are not fatal here, `render` keeps retrying and reports them.
+            if let Err(e) = data.make_current(hdc, o_context) {
+                data.context_failed(&e);
+            }
+            data.restore_context(hdc, o_context);
+
+            *self.data.lock() = Some(data);
+            Ok(())
+        }
+    }
 
-            wglMakeCurrent(hdc, o_context).unwrap();
+    /// Like [`Self::init_with_state_context`], but panics on errors like init did before it returned a `Result`.
+    pub fn init_or_panic(
+        &self,
+        hdc: HDC,
+        window: HWND,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+        context: Context,
+    ) {
+        if let Err(e) = self.init_with_state_context(hdc, window, ui, state, context) {
+            panic_msg!("Failed to initialize: {e}");
         }
     }
 
-    /// Initializes application and state. Sets egui's context to default value. You should call this only once!
+    /// Initializes application and state. Sets egui's context to default value. Can be called again like [`Self::init_with_state_context`].
     #[inline]
     pub fn init_with_state(
         &self,
@@ -107,11 +803,13 @@ impl<T> OpenGLApp<T> {
         window: HWND,
         ui: impl FnMut(&Context, &mut T) + 'static,
         state: T,
-    ) {
-        self.init_with_state_context(hdc, window, ui, state, Context::default())
+    ) -> Result<(), Error> {
+        OpenGLAppBuilder::with_state(state)
+            .ui(ui)
+            .init(self, hdc, window)
     }
 
-    /// Initializes application and state while allowing you to mutate the initial state of the egui's context. You should call this only once!
+    /// Initializes application and state while allowing you to mutate the initial state of the egui's context. Can be called again like [`Self::init_with_state_context`].
     #[inline]
     pub fn init_with_mutate(
         &self,
@@ -120,11 +818,14 @@ impl<T> OpenGLApp<T> {
         ui: impl FnMut(&Context, &mut T) + 'static,
         mut state: T,
         mutate: impl FnOnce(&mut Context, &mut T),
-    ) {
+    ) -> Result<(), Error> {
         let mut ctx = Context::default();
         mutate(&mut ctx, &mut state);
 
-        self.init_with_state_context(hdc, window, ui, state, ctx);
+        OpenGLAppBuilder::with_state(state)
+            .ui(ui)
+            .context(ctx)
+            .init(self, hdc, window)
     }
 
     #[cfg(feature = "parking-lot")]
@@ -137,106 +838,1580 @@ impl<T> OpenGLApp<T> {
         MutexGuard::map(self.data.lock(), |app| &mut app.as_mut().unwrap().state)
     }
 
-    fn lock_data(&self) -> impl DerefMut<Target = AppData<T>> + '_ {
-        MutexGuard::map(self.data.lock(), |app| {
-            expect!(app.as_mut(), "You need to call init first")
-        })
+    /// Sets a handler that receives [`AppEvent`]s.
+    /// The handler is called while the app is locked, so it must not call back into the app.
+    pub fn set_event_handler(&self, handler: impl FnMut(AppEvent) + 'static) {
+        self.lock_data().on_event = Some(Box::new(handler));
     }
-}
 
-impl<T: Default> OpenGLApp<T> {
-    /// Initializes application and sets the state to its default value. You should call this only once!
-    #[inline]
-    pub fn init_default(&self, hdc: HDC, window: HWND, ui: impl FnMut(&Context, &mut T) + 'static) {
-        self.init_with_state_context(hdc, window, ui, T::default(), Context::default());
+    /// Sets a handler that receives render-time [`Error`]s, such as persistent failures to make the context current.
+    /// The handler is called while the app is locked, so it must not call back into the app.
+    pub fn set_error_handler(&self, handler: impl FnMut(&Error) + 'static) {
+        self.lock_data().on_error = Some(Box::new(handler));
     }
-}
 
-impl<T> OpenGLApp<T> {
-    /// Present call. Should be called once per original present call, before or inside of hook.
-    #[allow(clippy::cast_ref_to_mut)]
-    pub fn render(&self, hdc: HDC) {
-        unsafe {
-            let this = &mut *self.lock_data();
+    /// Sets a handler called with the new width and height of the window's client area in pixels,
+    /// on the first frame and whenever the window is resized, e.g. when the game toggles fullscreen.
+    /// The handler is called while the app is locked, so it must not call back into the app.
+    pub fn set_resize_handler(&self, handler: impl FnMut(u32, u32) + 'static) {
+        self.lock_data().on_resize = Some(Box::new(handler));
+    }
 
-            let window = WindowFromDC(hdc);
-            if !window.eq(&this.window) {
-                this.window = window;
-                this.input_collector = InputCollector::new(window);
-                this.client_rect = self.get_client_rect(this.window);
+    /// Overrides whether the UI is written through an sRGB framebuffer, `None` follows the game's
+    /// pixel format. Use it when the detection picks the wrong one and colors look washed out or too dark.
+    pub fn set_framebuffer_srgb(&self, srgb: Option<bool>) {
+        let this = &mut *self.lock_data();
+        this.framebuffer_srgb = srgb;
+        if let Some(surface) = this.surface {
+            let surface = this.painted_surface(surface);
+            if let Some(painter) = this.painter.as_mut() {
+                painter.set_surface(surface);
             }
+        }
+    }
 
-            let o_context = wglGetCurrentContext();
-            wglMakeCurrent(hdc, this.gl_context).unwrap();
+    /// Returns the sRGB and blending strategy selected from the game's pixel format,
+    /// or `None` if the overlay context hasn't been created yet.
+    pub fn surface_config(&self) -> Option<SurfaceConfig> {
+        self.lock_data().surface
+    }
 
-            let output = this.ctx.run(this.input_collector.collect_input(&this.ctx), |ctx| {
-                (this.ui)(ctx, &mut this.state);
-            });
+    /// Sets which threads are allowed to draw the overlay. Resets the current owner thread.
+    pub fn set_thread_policy(&self, policy: ThreadPolicy) {
+        self.lock_data().thread_policy = policy;
+        self.render_thread.store(0, Ordering::Release);
+    }
 
-            if !output.platform_output.copied_text.is_empty() {
-                let _ = WindowsClipboardContext.set_contents(output.platform_output.copied_text);
-            }
+    /// Sets which windows the overlay draws on. With [`WindowPolicy::Bound`] the app stays on the window it was
+    /// initialized with, so several apps can draw on the windows of one game:
+    ///
+    /// ```ignore
+    /// static EDITOR: OpenGLApp<Editor> = OpenGLApp::new();
+    /// static PREVIEW: OpenGLApp<Preview> = OpenGLApp::new();
+    ///
+    /// EDITOR.set_window_policy(WindowPolicy::Bound);
+    /// PREVIEW.set_window_policy(WindowPolicy::Bound);
+    ///
+    /// // In the wglSwapBuffers hook, each app only draws on its own window.
+    /// EDITOR.render(hdc)?;
+    /// PREVIEW.render(hdc)?;
+    /// ```
+    pub fn set_window_policy(&self, policy: WindowPolicy) {
+        self.lock_data().window_policy = policy;
+    }
 
-            if output.shapes.is_empty() {
-                wglMakeCurrent(hdc, o_context).unwrap();
-                return;
-            }
+    /// Selects which OpenGL context the overlay paints with. Prefer [`OpenGLAppBuilder::context_mode`], which
+    /// applies before the first context is created. Changing the mode rebuilds the context and painter on the
+    /// next frame, losing registered textures.
+    pub fn set_context_mode(&self, mode: ContextMode) {
+        let this = &mut *self.lock_data();
+        if this.context_mode != mode {
+            this.context_mode = mode;
+            this.rebuild_context = true;
+        }
+    }
 
-            let client_rect = self.poll_client_rect(this);
-            let clipped_shapes = this.ctx.tessellate(output.shapes, 1.);
-            this.painter.paint_and_update_textures(
-                1.0,
-                &clipped_shapes,
-                &output.textures_delta,
-                &client_rect,
-            );
+    /// Selects which input is kept from the game while the menu is open. Defaults to [`InputBlocking::WhenWanted`].
+    pub fn set_input_blocking(&self, blocking: InputBlocking) {
+        self.lock_data().input_blocking = blocking;
+    }
+
+    /// Selects where text typed into egui comes from. Defaults to [`TextInputSource::Auto`].
+    pub fn set_text_input_source(&self, source: TextInputSource) {
+        self.lock_data().input_collector.set_text_source(source);
+    }
+
+    /// Sets how far the mouse wheel scrolls the UI. Defaults to the lines per notch of the Windows mouse settings.
+    pub fn set_scroll_config(&self, scroll: ScrollConfig) {
+        self.lock_data().input_collector.set_scroll_config(scroll);
+    }
+
+    /// Selects the shortcuts that scale the UI. Defaults to Ctrl with `+`, `-` or `0` only.
+    pub fn set_zoom_config(&self, zoom: ZoomConfig) {
+        let data = &mut *self.lock_data();
+        data.ctx
+            .options_mut(|options| options.zoom_with_keyboard = zoom.keyboard);
+        data.input_collector.set_wheel_zoom(zoom.wheel);
+    }
 
-            wglMakeCurrent(hdc, o_context).unwrap();
+    /// Returns a handle for delivering results into the state from threads you manage yourself.
+    pub fn task_sender(&self) -> TaskSender<T> {
+        self.tasks.sender()
+    }
+
+    /// Shows `notification` as a toast on top of the UI. Safe to call from within the UI closure.
+    pub fn notify(&self, notification: Notification) {
+        self.notifications.push(notification);
+    }
+
+    /// Returns a handle for pushing notifications from threads you manage yourself.
+    pub fn notifier(&self) -> Notifier {
+        self.notifications.notifier()
+    }
+
+    /// Keeps egui's memory, like window positions, collapsed headers and scroll offsets, in the file at `path`
+    /// between launches. Called before init, the memory is restored when initializing, afterwards right away.
+    /// It's saved every 30 seconds while rendering and by [`Self::destroy`]. Must not be called from within the UI closure.
+    #[cfg(feature = "persistence")]
+    pub fn set_memory_path(&self, path: impl Into<std::path::PathBuf>) -> Result<(), Error> {
+        let path = path.into();
+        if let Some(data) = self.data.lock().as_ref() {
+            crate::memory::load(&data.ctx, &path)?;
         }
+        *self.memory_path.lock() = Some(path);
+        Ok(())
     }
 
-    /// Call on each `WndProc` occurence.
-    /// Returns `true` if message was recognized and dispatched by input handler,
-    /// `false` otherwise.
-    #[inline]
-    pub fn wnd_proc(&self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> bool {
-        let this = &mut *self.lock_data();
-        this.input_collector.process(umsg, wparam.0, lparam.0);
+    /// Saves egui's memory to the file set with [`Self::set_memory_path`], does nothing without one.
+    /// Must not be called from within the UI closure.
+    #[cfg(feature = "persistence")]
+    pub fn save_memory(&self) -> Result<(), Error> {
+        let Some(path) = self.memory_path.lock().clone() else {
+            return Ok(());
+        };
+        match self.data.lock().as_ref() {
+            Some(data) => crate::memory::save(&data.ctx, &path),
+            None => Ok(()),
+        }
+    }
 
-        if umsg == WM_SIZE {
-            this.client_rect = self.get_client_rect(this.window);
+    /// Saves the state of an app initialized with [`Self::init_with_persisted_state`], does nothing otherwise.
+    /// Must not be called from within the UI closure.
+    #[cfg(feature = "persistence")]
+    pub fn save_state(&self) -> Result<(), Error> {
+        match self.data.lock().as_mut() {
+            Some(AppData {
+                state_saver: Some(saver),
+                state,
+                ..
+            }) => saver.save(state),
+            _ => Ok(()),
         }
+    }
 
-        this.ctx.wants_keyboard_input() || this.ctx.wants_pointer_input()
+    /// Loads the integration's [`Config`] from `path` and saves every later change to it.
+    /// A missing file is not an error, it's created on the first change.
+    pub fn set_config_path(&self, path: impl Into<std::path::PathBuf>) -> Result<(), Error> {
+        self.settings.set_path(path.into())
     }
 
-    pub fn get_window(&self) -> HWND {
-        let data = &mut *self.lock_data();
-        data.window
+    pub fn config(&self) -> Config {
+        self.settings.config()
     }
-}
 
-impl<T> OpenGLApp<T> {
-    #[inline]
-    fn poll_client_rect(&self, data: &mut AppData<T>) -> (u32, u32) {
-        static INIT: std::sync::Once = std::sync::Once::new();
-        INIT.call_once(|| {
-            data.client_rect = self.get_client_rect(data.window);
-        });
+    /// Tells the UI to hide sensitive information because the user is recording or streaming.
+    /// Safe to call from within the UI closure, widgets can check it with [`crate::streamer_mode`].
+    pub fn set_streamer_mode(&self, enabled: bool) {
+        self.settings.set_streamer_mode(enabled);
+    }
 
-        data.client_rect
+    pub fn streamer_mode(&self) -> bool {
+        self.settings.streamer_mode()
     }
 
-    #[inline]
-    fn get_client_rect(&self, window: HWND) -> (u32, u32) {
-        let mut rect = RECT::default();
-        unsafe {
-            let _ = GetClientRect(window, &mut rect);
+    /// Opens or closes the built-in settings window.
+    pub fn set_settings_open(&self, open: bool) {
+        self.settings.set_window_open(open);
+    }
+
+    /// Fetches the update manifest at `url` (see [`crate::UpdateManifest`]) on a background thread
+    /// and shows a notification if it announces a newer version than `current_version`.
+    #[cfg(feature = "update-check")]
+    pub fn check_for_updates(&self, url: impl Into<String>, current_version: impl Into<String>) {
+        let url = url.into();
+        let current_version = current_version.into();
+        let notifier = self.notifier();
+
+        std::thread::spawn(move || crate::update::check(&url, &current_version, &notifier));
+    }
+
+    /// Enables the GL state self-test, which logs any state the painter changed during a frame.
+    /// Enabled by default in debug builds.
+    pub fn set_gl_state_check(&self, enabled: bool) {
+        let this = &mut *self.lock_data();
+        this.gl_state_check = enabled;
+        if let Some(painter) = this.painter.as_mut() {
+            painter.set_state_check(enabled);
         }
+    }
 
-        (
-            (rect.right - rect.left) as u32,
-            (rect.bottom - rect.top) as u32,
-        )
+    /// Replaces the closure drawing the UI, starting with the next frame. The GL context, textures,
+    /// hooks and state are kept. Safe to call from within the UI closure.
+    pub fn set_ui(&self, ui: impl FnMut(&Context, &mut T) + 'static) {
+        *self.pending_ui.lock() = Some(Box::new(ui));
+    }
+
+    /// Registers a named UI layer drawn after the UI closure, replacing the layer called `name` if there is one.
+    /// Layers start visible at z-order `0`. Can be called before init and from within the UI.
+    pub fn add_layer(&self, name: impl Into<String>, draw: impl FnMut(&Context, &mut T) + 'static) {
+        self.layers.add(name.into(), Box::new(draw));
+    }
+
+    /// Returns `false` if there is no layer called `name`.
+    pub fn remove_layer(&self, name: &str) -> bool {
+        self.layers.remove(name)
+    }
+
+    /// Shows or hides a layer without removing it. Returns `false` if there is no layer called `name`.
+    pub fn set_layer_visible(&self, name: &str, visible: bool) -> bool {
+        self.layers.set_visible(name, visible)
+    }
+
+    /// Returns `None` if there is no layer called `name`.
+    pub fn is_layer_visible(&self, name: &str) -> Option<bool> {
+        self.layers.is_visible(name)
     }
+
+    /// Layers are drawn from the lowest z-order up, so higher ones end up on top of lower ones when
+    /// they first appear. Returns `false` if there is no layer called `name`.
+    pub fn set_layer_z_order(&self, name: &str, z_order: i32) -> bool {
+        self.layers.set_z_order(name, z_order)
+    }
+
+    /// Names of the registered layers in the order they are drawn.
+    pub fn layer_names(&self) -> Vec<String> {
+        self.layers.names()
+    }
+
+    /// Replaces the shaders the UI is painted with, `None` restores the built-in ones. Applied on the next frame;
+    /// if the shaders fail to build or lack required inputs the current ones are kept and the error is reported.
+    pub fn set_custom_shader(&self, shader: Option<CustomShader>) {
+        self.lock_data().pending_shader = Some(shader);
+    }
+
+    /// Overrides the scale egui renders at, `None` follows the DPI of the window's monitor.
+    pub fn set_pixels_per_point(&self, pixels_per_point: Option<f32>) {
+        self.lock_data().pixels_per_point = pixels_per_point.filter(|ppp| *ppp > 0.);
+    }
+
+    /// Returns the scale egui rendered the last frame at, before its zoom factor is applied.
+    /// Follows the window to monitors with another DPI. Safe to call from within the UI closure.
+    pub fn pixels_per_point(&self) -> f32 {
+        f32::from_bits(self.native_pixels_per_point.load(Ordering::Acquire))
+    }
+
+    /// Sets how egui turns shapes into meshes, e.g. disable `feathering` for crisp 1px lines
+    /// instead of antialiased edges. From within the UI closure use [`Context::tessellation_options_mut`].
+    pub fn set_tessellation_options(&self, options: egui::epaint::TessellationOptions) {
+        self.lock_data()
+            .ctx
+            .tessellation_options_mut(|current| *current = options);
+    }
+
+    pub fn tessellation_options(&self) -> egui::epaint::TessellationOptions {
+        self.lock_data()
+            .ctx
+            .tessellation_options(|options| *options)
+    }
+
+    /// Paints the UI offscreen first and blends it onto the game's frame in a final pass.
+    /// Slightly slower, the UI is composited back onto whatever framebuffer is bound when presenting.
+    /// Always used while the game's framebuffer is multisampled.
+    pub fn set_compositing(&self, enabled: bool) {
+        let this = &mut *self.lock_data();
+        this.compositing = enabled;
+        if let Some(painter) = this.painter.as_mut() {
+            painter.set_compositing(enabled);
+        }
+    }
+
+    /// Lays the UI out for a fixed resolution in pixels, e.g. `(1920, 1080)`, and stretches it onto the window,
+    /// so menus look the same at every window size. Pointer positions are mapped accordingly.
+    /// `None` paints at the window's size again. Uses compositing, see [`Self::set_compositing`]; if that is
+    /// unavailable the resolution is reset and [`Error::RenderResolution`] is reported.
+    pub fn set_render_resolution(&self, resolution: Option<(u32, u32)>) {
+        let resolution = resolution.filter(|&(width, height)| width > 0 && height > 0);
+        let this = &mut *self.lock_data();
+        this.render_resolution = resolution;
+        this.input_collector.set_render_resolution(resolution);
+        if let Some(painter) = this.painter.as_mut() {
+            painter.set_render_resolution(resolution);
+        }
+    }
+
+    /// Outlines clip rects and mesh bounds and lists the meshes painted per texture,
+    /// to diagnose scissor and clipping issues. See [`crate::Painter::set_debug_overlay`].
+    pub fn set_debug_overlay(&self, enabled: bool) {
+        let this = &mut *self.lock_data();
+        this.debug_overlay = enabled;
+        if let Some(painter) = this.painter.as_mut() {
+            painter.set_debug_overlay(enabled);
+        }
+    }
+
+    /// Queues an input event for the next frame, as if it came from the window's messages,
+    /// e.g. to drive the UI from scripts or tests. Safe to call from within the UI closure and other threads.
+    pub fn push_event(&self, event: egui::Event) {
+        self.pushed_events.lock().push(event);
+    }
+
+    /// Mouse and wheel movement since the last call, including input egui didn't use or never saw
+    /// because the menu was closed, e.g. for a free camera. Safe to call from within the UI closure.
+    pub fn take_mouse_motion(&self) -> MouseMotion {
+        self.mouse_tracker.lock().take()
+    }
+
+    /// Key pressed last since the last call, whether egui or the game got it, e.g. for keybind widgets
+    /// that need keys egui can't represent. Call it once when starting to listen for a new keybind,
+    /// so earlier presses are discarded. Safe to call from within the UI closure.
+    pub fn take_key_press(&self) -> Option<KeyCode> {
+        self.key_press.lock().take()
+    }
+
+    /// Key that opens and closes the menu, `None` keeps it open unless closed with [`Self::set_menu_open`].
+    /// The key press is not passed on to egui or the game.
+    pub fn set_toggle_key(&self, key: Option<VIRTUAL_KEY>) {
+        let key = key.map_or(0, |key| key.0 as u32);
+        self.toggle_key.store(key, Ordering::Relaxed);
+    }
+
+    pub fn toggle_key(&self) -> Option<VIRTUAL_KEY> {
+        match self.toggle_key.load(Ordering::Relaxed) {
+            0 => None,
+            key => Some(VIRTUAL_KEY(key as _)),
+        }
+    }
+
+    /// Whether the menu is open. Safe to call from within the UI closure, e.g. to only draw a HUD while it's closed.
+    pub fn is_menu_open(&self) -> bool {
+        self.menu_open.load(Ordering::Relaxed)
+    }
+
+    /// Opens or closes the menu. While it's closed, [`Self::wnd_proc`] leaves all input to the game.
+    /// Safe to call from within the UI closure.
+    pub fn set_menu_open(&self, open: bool) {
+        self.menu_open.store(open, Ordering::Relaxed);
+    }
+
+    /// Hides the overlay: [`Self::render`] skips running egui, tessellating and painting, and all messages
+    /// are left to the game, so a hidden overlay costs next to nothing. Unlike closing the menu, nothing is drawn
+    /// and the toggle key is ignored. Safe to call from within the UI closure.
+    pub fn hide(&self) {
+        self.hidden.store(true, Ordering::Relaxed);
+    }
+
+    /// Shows the overlay hidden with [`Self::hide`] again, starting with the next frame.
+    pub fn show(&self) {
+        self.hidden.store(false, Ordering::Relaxed);
+    }
+
+    /// Whether the overlay is hidden with [`Self::hide`].
+    pub fn is_hidden(&self) -> bool {
+        self.hidden.load(Ordering::Relaxed)
+    }
+
+    /// Whether the UI closure still runs while the menu is closed, `true` by default.
+    /// Set to `false` to skip it entirely instead of branching on [`Self::is_menu_open`].
+    pub fn set_run_ui_when_closed(&self, run: bool) {
+        self.run_ui_when_closed.store(run, Ordering::Relaxed);
+    }
+
+    /// Frees the mouse while the menu is open, for games that confine, hide and recenter it.
+    /// Hooks the cursor functions of `user32.dll`, see [`crate::mouse_unlock`].
+    #[cfg(feature = "mouse-unlock")]
+    pub fn set_mouse_unlock(&self, enabled: bool) {
+        if enabled {
+            if let Err(e) = crate::mouse_unlock::install() {
+                log!("Failed to hook the cursor functions: {e}");
+                return;
+            }
+        } else {
+            crate::mouse_unlock::uninstall();
+        }
+
+        self.mouse_unlock.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Lets an XInput controller drive the UI while the game window is focused: the D-pad and
+    /// left stick move the keyboard focus, A activates the focused widget, B drops the focus and
+    /// the shoulder buttons tab backwards and forwards.
+    #[cfg(feature = "gamepad")]
+    pub fn set_gamepad_navigation(&self, enabled: bool) {
+        let this = &mut *self.lock_data();
+        if this.gamepad.is_some() != enabled {
+            this.gamepad = enabled.then(crate::gamepad::GamepadNavigation::new);
+        }
+    }
+
+    /// Fades the whole UI, `1.0` is fully opaque. Values below `1.0` composite the UI,
+    /// see [`Self::set_compositing`]. Safe to call from within the UI closure, e.g. to animate fades.
+    pub fn set_opacity(&self, opacity: f32) {
+        self.opacity.store(opacity.to_bits(), Ordering::Release);
+    }
+
+    pub fn opacity(&self) -> f32 {
+        f32::from_bits(self.opacity.load(Ordering::Acquire))
+    }
+
+    /// Creates a user texture from `image` for use in `egui::Image` and friends.
+    /// Returns `None` if the painter couldn't be created yet. Locks the app, so it can't be called from within the UI closure.
+    /// Large textures are uploaded in the background where supported and show up once the upload completes.
+    pub fn register_texture(
+        &self,
+        image: &ColorImage,
+        options: TextureOptions,
+    ) -> Option<egui::TextureId> {
+        let size = (image.width(), image.height());
+        self.lock_data()
+            .painter
+            .as_mut()
+            .map(|painter| painter.new_user_texture(size, &image.pixels, options))
+    }
+
+    /// Makes a texture created by the game or by your own GL code usable in egui. The texture isn't owned
+    /// by the app and is not deleted by [`Self::unregister_texture`]. Returns `None` if the painter couldn't be created yet.
+    /// Textures of the game are only visible with [`ContextMode::Shared`] or [`ContextMode::Game`].
+    pub fn register_gl_texture(&self, gl_texture: u32) -> Option<egui::TextureId> {
+        self.lock_data()
+            .painter
+            .as_mut()
+            .map(|painter| painter.new_opengl_texture(gl_texture))
+    }
+
+    /// Replaces the pixels of a texture created with [`Self::register_texture`], keeping its size.
+    pub fn update_texture(&self, id: egui::TextureId, pixels: &[Color32]) -> Result<(), Error> {
+        match self.lock_data().painter.as_mut() {
+            Some(painter) => painter.update_user_texture_data(&id, pixels),
+            None => Err(Error::UnknownTexture(id)),
+        }
+    }
+
+    /// Enables anisotropic filtering for a registered texture, see [`crate::Painter::set_texture_anisotropy`].
+    pub fn set_texture_anisotropy(&self, id: egui::TextureId, level: f32) {
+        if let Some(painter) = self.lock_data().painter.as_mut() {
+            painter.set_texture_anisotropy(id, level);
+        }
+    }
+
+    /// Frees a texture created with one of the `register_*` methods. The GL texture is deleted on the next frame,
+    /// when the overlay context is current.
+    pub fn unregister_texture(&self, id: egui::TextureId) {
+        self.lock_data().unregistered_textures.push(id);
+    }
+
+    /// Runs `f` with the painter and the overlay's context current, for GL work the app has no method for,
+    /// like creating textures with your own GL calls. The previous context is current again afterwards.
+    /// `hdc` must be the game's device context, call it from the thread that renders. With [`ContextMode::Game`]
+    /// the game's context has to be current already. Locks the app, so it can't be called from within the UI closure.
+    ///
+    /// ```ignore
+    /// let id = APP.with_painter(hdc, |painter| {
+    ///     let texture = create_my_texture();
+    ///     painter.new_opengl_texture(texture)
+    /// })?;
+    /// ```
+    pub fn with_painter<R>(
+        &self,
+        hdc: HDC,
+        f: impl FnOnce(&mut painter::Painter) -> R,
+    ) -> Result<R, Error> {
+        let mut data = self.lock_data();
+        if data.painter.is_none() {
+            return Err(Error::NoPainter);
+        }
+
+        unsafe {
+            let o_context = wglGetCurrentContext();
+            match data.gl_context {
+                Some(gl_context) => wglMakeCurrent(hdc, gl_context).map_err(Error::MakeCurrent)?,
+                None if data.game_context != Some(o_context) => return Err(Error::NoGameContext),
+                None => {}
+            }
+
+            let result = data.painter.as_mut().map(f);
+            data.restore_context(hdc, o_context);
+            result.ok_or(Error::NoPainter)
+        }
+    }
+
+    /// Deletes every GL object created by the app and its OpenGL context, e.g. before unloading the DLL.
+    /// `hdc` must be the game's device context, call it from the thread that renders. If `render` is called
+    /// again, the context and painter are recreated; textures registered before are lost.
+    /// Also detaches the window procedure attached with [`Self::attach_wnd_proc`].
+    /// Saves egui's memory and the state first if they are persisted.
+    pub fn destroy(&self, hdc: HDC) {
+        #[cfg(feature = "persistence")]
+        if let Err(e) = self.save_memory() {
+            log!("Failed to save egui memory: {e}");
+        }
+        #[cfg(feature = "persistence")]
+        if let Err(e) = self.save_state() {
+            log!("Failed to save the state: {e}");
+        }
+
+        unsafe {
+            let mut data = self.lock_data();
+            data.destroy_gl(hdc);
+            data.restore_files();
+        }
+        self.detach_wnd_proc();
+        // egui only sends its font atlas once, a new painter has to get it again.
+        self.rebuild_font_atlas();
+    }
+
+    /// Lets an initialized app be initialized again if its window was destroyed, e.g. when the game recreated it.
+    /// The old GL objects are deleted with `hdc` if the context can still be made current on it.
+    unsafe fn replace_lost_window(&self, hdc: HDC) -> Result<(), Error> {
+        let mut data = self.data.lock();
+        match data.as_mut() {
+            Some(old) if old.window_lost || !IsWindow(old.window).as_bool() => {
+                old.destroy_gl(hdc);
+                old.restore_files();
+                self.next_user_texture
+                    .store(old.next_user_texture, Ordering::Release);
+                *data = None;
+                drop(data);
+
+                self.detach_wnd_proc();
+                self.render_thread.store(0, Ordering::Release);
+                Ok(())
+            }
+            // Initialized and still alive, or still being initialized.
+            _ => Err(Error::AlreadyInitialized),
+        }
+    }
+
+    /// Tears the app down before unloading the DLL: detaches the window procedure attached with
+    /// [`Self::attach_wnd_proc`], removes the cursor hooks of [`Self::set_mouse_unlock`], deletes every GL object
+    /// and the overlay's context with the right context current, and drops the UI closures and state.
+    /// Persisted memory and state are saved first. `hdc` must be the game's device context, call it from the thread
+    /// that renders. Afterwards [`Self::is_ready`] is `false`, [`Self::render`] fails with [`Error::NotInitialized`]
+    /// and [`Self::wnd_proc`] passes every message through, until the app is initialized again.
+    pub fn shutdown(&self, hdc: HDC) {
+        if !self.is_ready() {
+            return;
+        }
+
+        #[cfg(feature = "persistence")]
+        {
+            if let Err(e) = self.save_memory() {
+                log!("Failed to save egui memory: {e}");
+            }
+            if let Err(e) = self.save_state() {
+                log!("Failed to save the state: {e}");
+            }
+        }
+
+        self.detach_wnd_proc();
+
+        #[cfg(feature = "mouse-unlock")]
+        if self.mouse_unlock.swap(false, Ordering::AcqRel) {
+            crate::mouse_unlock::uninstall();
+        }
+
+        let data = self.data.lock().take();
+        if let Some(mut data) = data {
+            unsafe {
+                data.destroy_gl(hdc);
+                data.restore_files();
+            }
+            self.next_user_texture
+                .store(data.next_user_texture, Ordering::Release);
+        }
+
+        self.pending_ui.lock().take();
+        self.ctx.lock().take();
+        self.layers.clear();
+        self.captures.lock().clear();
+        self.render_thread.store(0, Ordering::Release);
+        // Last, an `init_*` call racing with the shutdown must not have its data taken.
+        self.initialized.store(false, Ordering::Release);
+    }
+
+    /// Returns counters of the painter's GPU resources and the cost of the last painted frame,
+    /// zeroed until a frame was painted. Safe to call from within the UI closure.
+    pub fn last_frame_stats(&self) -> RenderStats {
+        self.last_frame_stats.lock().unwrap_or_default()
+    }
+
+    /// Caps how often the UI closure runs and egui tessellates, e.g. `Some(60.)` in games presenting hundreds of
+    /// frames per second. Frames in between paint the primitives of the last run again, so input and animations
+    /// update at this rate. `None` runs the UI every frame, which is the default.
+    pub fn set_ui_rate(&self, hz: Option<f32>) {
+        let this = &mut *self.lock_data();
+        this.ui_interval = hz
+            .filter(|hz| *hz > 0.)
+            .map(|hz| Duration::from_secs_f32(1. / hz));
+        this.cached_frame = None;
+    }
+
+    /// Paints the primitives of the last frame again instead of running the UI while nothing changed:
+    /// there is no new input, egui didn't ask to run again, e.g. for an animation, and nothing requested
+    /// a repaint with [`Context::request_repaint`]. Only enable it if the UI doesn't read game state
+    /// every frame without requesting repaints. Compare [`RenderStats::cached_frames`] and
+    /// [`RenderStats::ui_runs`] for the hit rate. Disabled by default.
+    pub fn set_damage_tracking(&self, enabled: bool) {
+        let this = &mut *self.lock_data();
+        this.damage_tracking = enabled;
+        this.cached_frame = None;
+    }
+
+    /// Returns the game's frame rate and the time the overlay takes per frame, measured in [`Self::render`].
+    /// Safe to call from within the UI closure.
+    pub fn frame_timing(&self) -> FrameTiming {
+        self.frame_clock.lock().timing()
+    }
+
+    /// Shows the frame rate, frame time and the overlay's own cost in the top right corner.
+    /// Safe to call from within the UI closure.
+    pub fn set_fps_overlay(&self, enabled: bool) {
+        self.fps_overlay.store(enabled, Ordering::Relaxed);
+    }
+
+    #[deprecated(note = "renamed to `last_frame_stats`")]
+    pub fn render_stats(&self) -> RenderStats {
+        self.last_frame_stats()
+    }
+
+    /// Makes egui rebuild its font atlas on the next frame, the old atlas texture is freed.
+    /// Safe to call from within the UI closure.
+    pub fn rebuild_font_atlas(&self) {
+        self.rebuild_atlas.store(true, Ordering::Release);
+    }
+
+    /// Gamma applied to the coverage of font glyphs, values below `1.0` counter text looking thin in some games.
+    /// Rebuilds the font atlas, safe to call from within the UI closure.
+    pub fn set_font_gamma(&self, gamma: f32) {
+        self.font_gamma.store(gamma.to_bits(), Ordering::Release);
+        self.rebuild_font_atlas();
+    }
+
+    /// Multiplier of the coverage of font glyphs, values above `1.0` make text heavier.
+    /// Rebuilds the font atlas, safe to call from within the UI closure.
+    pub fn set_font_weight(&self, weight: f32) {
+        self.font_weight.store(weight.to_bits(), Ordering::Release);
+        self.rebuild_font_atlas();
+    }
+
+    /// Returns the id of the thread that last drew the overlay.
+    pub fn render_thread_id(&self) -> Option<u32> {
+        match self.render_thread.load(Ordering::Acquire) {
+            0 => None,
+            id => Some(id),
+        }
+    }
+
+    /// Returns `true` if the window has been destroyed and rendering is paused until a new one is found.
+    pub fn is_window_lost(&self) -> bool {
+        self.lock_data().window_lost
+    }
+
+    fn lock_data(&self) -> impl DerefMut<Target = AppData<T>> + '_ {
+        MutexGuard::map(self.data.lock(), |app| {
+            expect!(app.as_mut(), "You need to call init first")
+        })
+    }
+}
+
+impl<T: 'static> OpenGLApp<T> {
+    /// Runs `job` on a background thread. The closure it returns is applied to the state right before
+    /// the next frame and a repaint is requested. Safe to call from within the UI closure.
+    /// ```ignore
+    /// APP.spawn_task(|| {
+    ///     let motd = std::fs::read_to_string("motd.txt").unwrap_or_default();
+    ///     move |state: &mut State| state.motd = motd
+    /// });
+    /// ```
+    pub fn spawn_task<C>(&self, job: impl FnOnce() -> C + Send + 'static)
+    where
+        C: FnOnce(&mut T) + Send + 'static,
+    {
+        self.tasks.spawn(job);
+    }
+
+    /// Like [`Self::spawn_task`], but runs `future` on a shared tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn spawn_future<C>(&self, future: impl std::future::Future<Output = C> + Send + 'static)
+    where
+        C: FnOnce(&mut T) + Send + 'static,
+    {
+        self.tasks.spawn_future(future);
+    }
+
+    /// Reads back the game's frame at the next swap and hands it to `apply` together with the state,
+    /// right before the swap. With `include_ui` the image also contains the overlay, otherwise it's taken
+    /// before the UI is painted. Safe to call from within the UI closure, e.g. from a screenshot button.
+    /// ```ignore
+    /// if ui.button("Screenshot").clicked() {
+    ///     APP.capture_frame(false, |state: &mut State, image| state.screenshot = Some(image));
+    /// }
+    /// ```
+    pub fn capture_frame(
+        &self,
+        include_ui: bool,
+        apply: impl FnOnce(&mut T, ColorImage) + Send + 'static,
+    ) {
+        self.captures.lock().push(Capture {
+            include_ui,
+            apply: Box::new(apply),
+        });
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned + Default> OpenGLApp<T> {
+    /// Initializes the app with the state saved at `path`, or the default state if there is no file yet.
+    /// The state is saved back as RON every 30 seconds while rendering, by [`Self::destroy`] and by
+    /// [`Self::save_state`]. Besides the errors of [`Self::init_with_state_context`], fails if the file
+    /// can't be read or parsed.
+    pub fn init_with_persisted_state(
+        &self,
+        path: impl Into<std::path::PathBuf>,
+        hdc: HDC,
+        window: HWND,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+    ) -> Result<(), Error> {
+        let path = path.into();
+        let state = crate::state::load(&path)?.unwrap_or_default();
+        Self::builder_with_state(state)
+            .ui(ui)
+            .init(self, hdc, window)?;
+
+        self.lock_data().state_saver = Some(crate::state::StateSaver::new(path));
+        Ok(())
+    }
+}
+
+impl<T: Default> OpenGLApp<T> {
+    /// Starts configuring the app with the default state, see [`OpenGLAppBuilder`].
+    pub fn builder() -> OpenGLAppBuilder<T> {
+        OpenGLAppBuilder::with_state(T::default())
+    }
+
+    /// Initializes application and sets the state to its default value. Can be called again like [`Self::init_with_state_context`].
+    #[inline]
+    pub fn init_default(
+        &self,
+        hdc: HDC,
+        window: HWND,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+    ) -> Result<(), Error> {
+        Self::builder().ui(ui).init(self, hdc, window)
+    }
+}
+
+impl<T> OpenGLApp<T> {
+    /// Present call. Should be called once per original present call, before or inside of hook.
+    /// Games present with `wglSwapBuffers`, gdi32's `SwapBuffers` or `wglSwapLayerBuffers` with the main plane,
+    /// see [`utils::get_gdi_swap_buffers`] and [`utils::get_swap_layer_buffers`]. When hooking more than one,
+    /// use [`utils::PresentGuard`] so a frame isn't drawn twice when one calls the other.
+    /// Returns errors that kept the overlay from being drawn. Context failures are retried on the next frames
+    /// and also passed to the error handler once they persist. Skipped frames, e.g. while the window is lost, are `Ok`.
+    pub fn render(&self, hdc: HDC) -> Result<(), Error> {
+        let started = FrameClock::now();
+        let result = self.render_frame(hdc, started);
+        self.frame_clock.lock().finish(started, FrameClock::now());
+        result
+    }
+
+    #[allow(clippy::cast_ref_to_mut)]
+    fn render_frame(&self, hdc: HDC, started: i64) -> Result<(), Error> {
+        unsafe {
+            let thread = GetCurrentThreadId();
+            let owner = self.render_thread.load(Ordering::Acquire);
+
+            let mut data = if owner == 0 || owner == thread {
+                self.data.lock()
+            } else {
+                // Never stall a present on another thread drawing the overlay.
+                match self.data.try_lock() {
+                    Some(data) => data,
+                    None => return Ok(()),
+                }
+            };
+            let Some(this) = data.as_mut() else {
+                return Err(Error::NotInitialized);
+            };
+
+            let window = WindowFromDC(hdc);
+            if this.window_policy == WindowPolicy::Bound && !window.eq(&this.window) {
+                // The game may keep presenting to its other windows after the bound one is gone.
+                if !IsWindow(this.window).as_bool() {
+                    this.lose_window();
+                }
+                return Ok(());
+            }
+            self.frame_clock.lock().present(started);
+
+            // Another thread may have claimed the overlay while we were waiting for the lock.
+            let owner = self.render_thread.load(Ordering::Acquire);
+            match this.thread_policy {
+                ThreadPolicy::FirstThread => {
+                    if owner != 0 && owner != thread {
+                        return Ok(());
+                    }
+                }
+                ThreadPolicy::AnyThread => {}
+            }
+            self.render_thread.store(thread, Ordering::Release);
+
+            if !window.eq(&this.window) && IsWindow(window).as_bool() {
+                this.window = window;
+                this.input_collector.set_window(window);
+                this.pointer_captured = false;
+                this.dpi_scale = self.get_window_scale(this.window);
+
+                if this.window_lost {
+                    this.window_lost = false;
+                    this.emit(AppEvent::WindowRestored(window));
+                }
+            }
+
+            if this.window_lost || !IsWindow(this.window).as_bool() {
+                this.lose_window();
+                return Ok(());
+            }
+            // After a window switch or `destroy`.
+            this.accept_files();
+
+            // Catches resizes whose messages the app never saw, e.g. without a window procedure hook.
+            self.update_client_rect(this);
+
+            if self.is_hidden() {
+                self.skip_hidden_frame(this);
+                return Ok(());
+            }
+
+            if this.context_backoff.skip() {
+                return Ok(());
+            }
+
+            let o_context = wglGetCurrentContext();
+            let context_replaced = o_context.0 != 0
+                && this
+                    .game_context
+                    .is_some_and(|context| context != o_context);
+            // E.g. a new device context after the game changed its video settings.
+            let pixel_format = pixel_format(hdc);
+            let format_changed = matches!(
+                (this.pixel_format, pixel_format),
+                (Some(old), Some(new)) if old != new
+            );
+
+            if context_replaced || format_changed {
+                if context_replaced {
+                    log!("The game recreated its OpenGL context, rebuilding the overlay's");
+                } else {
+                    log!("The pixel format of the device context changed, rebuilding the overlay's context");
+                }
+                this.destroy_gl(hdc);
+                self.rebuild_font_atlas();
+                this.emit(AppEvent::ContextRecreated);
+            }
+            if o_context.0 != 0 {
+                this.game_context = Some(o_context);
+            }
+            if pixel_format.is_some() {
+                this.pixel_format = pixel_format;
+            }
+
+            if std::mem::take(&mut this.rebuild_context) {
+                this.destroy_gl(hdc);
+                self.rebuild_font_atlas();
+            }
+
+            if let Err(e) = this.make_current(hdc, o_context) {
+                // A failed `wglMakeCurrent` releases the current context, give it back to the game.
+                this.context_failed(&e);
+                this.restore_context(hdc, o_context);
+                return Err(e);
+            }
+            this.context_backoff.succeed();
+
+            if let Some(painter) = this.painter.as_mut() {
+                for id in this.unregistered_textures.drain(..) {
+                    painter.free_texture(id);
+                }
+            }
+
+            self.take_captures(this, false);
+
+            // Polled before deciding whether the UI runs, its presses then count as input like the window's.
+            #[cfg(feature = "gamepad")]
+            Self::poll_gamepad(this);
+
+            let input_pending = this.input_collector.has_input()
+                || !self.pushed_events.lock().is_empty()
+                || self.pending_ui.lock().is_some()
+                || self.rebuild_atlas.load(Ordering::Acquire)
+                || this.menu_was_open != self.is_menu_open();
+            if let Some(frame) = this.take_cached_frame(input_pending) {
+                this.cached_frames += 1;
+                if !frame.primitives.is_empty() {
+                    self.paint(
+                        this,
+                        frame.pixels_per_point,
+                        &frame.primitives,
+                        &egui::TexturesDelta::default(),
+                    );
+                }
+                this.cached_frame = Some(frame);
+
+                self.take_captures(this, true);
+                this.restore_context(hdc, o_context);
+                return Ok(());
+            }
+
+            self.tasks.apply(&mut this.state);
+
+            // Counted while painting the last frame.
+            let mesh_counts = match (this.debug_overlay, this.painter.as_ref()) {
+                (true, Some(painter)) => painter.mesh_counts().to_vec(),
+                _ => vec![],
+            };
+
+            if let Some(ui) = self.pending_ui.lock().take() {
+                this.ui = ui;
+            }
+
+            let input = self.collect_input(this);
+            let run_ui = self.is_menu_open() || self.run_ui_when_closed.load(Ordering::Relaxed);
+            let mut panicked = false;
+            let output = this.ctx.run(input, |ctx| {
+                self.settings.publish(ctx);
+                if run_ui {
+                    match &this.ui_panic {
+                        None => {
+                            let result = panic_guard::catch(|| {
+                                (this.ui)(ctx, &mut this.state);
+                                self.layers.draw(ctx, &mut this.state);
+                            });
+                            if let Err(panic) = result {
+                                log!(
+                                    "The UI panicked, it won't run until resumed: {}",
+                                    panic.message
+                                );
+                                this.ui_panic = Some(panic);
+                                panicked = true;
+                            }
+                        }
+                        Some(panic) => {
+                            if panic_guard::show_window(ctx, panic) {
+                                this.ui_panic = None;
+                            }
+                        }
+                    }
+                }
+                self.settings.show_window(ctx);
+                self.notifications.show(ctx, self.settings.streamer_mode());
+                if this.debug_overlay {
+                    show_mesh_counts(ctx, &mesh_counts);
+                }
+                if self.fps_overlay.load(Ordering::Relaxed) {
+                    let gpu_time = self
+                        .last_frame_stats
+                        .lock()
+                        .and_then(|stats| stats.gpu_time);
+                    show_frame_timing(ctx, self.frame_timing(), gpu_time);
+                }
+            });
+
+            if panicked {
+                this.emit(AppEvent::UiPanicked);
+            }
+
+            let now = Instant::now();
+            this.last_ui_run = Some(now);
+            this.ui_runs += 1;
+
+            #[cfg(feature = "persistence")]
+            if this.memory_autosave.due() {
+                if let Some(path) = self.memory_path.lock().as_ref() {
+                    if let Err(e) = crate::memory::save(&this.ctx, path) {
+                        this.report(&e);
+                    }
+                }
+            }
+            #[cfg(feature = "persistence")]
+            if let Some(saver) = this.state_saver.as_mut() {
+                if saver.autosave.due() {
+                    if let Err(e) = saver.save(&this.state) {
+                        this.report(&e);
+                    }
+                }
+            }
+            this.repaint_at = output
+                .viewport_output
+                .get(&egui::ViewportId::ROOT)
+                .and_then(|viewport| now.checked_add(viewport.repaint_delay));
+            this.cursor_icon = output.platform_output.cursor_icon;
+
+            if let Some(open_url) = output.platform_output.open_url.as_ref() {
+                open_url_in_browser(open_url.url.clone());
+            }
+
+            if !output.platform_output.copied_text.is_empty() {
+                if let Err(e) =
+                    clipboard::set_text(this.window, &output.platform_output.copied_text)
+                {
+                    this.report(&e);
+                }
+            }
+
+            if output.shapes.is_empty() {
+                this.cached_frame = this.caches_frames().then(|| CachedFrame {
+                    primitives: vec![],
+                    pixels_per_point: output.pixels_per_point,
+                    client_rect: this.client_rect,
+                });
+                self.take_captures(this, true);
+                this.restore_context(hdc, o_context);
+                return Ok(());
+            }
+
+            let clipped_shapes = this.ctx.tessellate(output.shapes, output.pixels_per_point);
+            self.paint(
+                this,
+                output.pixels_per_point,
+                &clipped_shapes,
+                &output.textures_delta,
+            );
+            this.cached_frame = this.caches_frames().then(|| CachedFrame {
+                primitives: clipped_shapes,
+                pixels_per_point: output.pixels_per_point,
+                client_rect: this.client_rect,
+            });
+
+            self.take_captures(this, true);
+            this.restore_context(hdc, o_context);
+            Ok(())
+        }
+    }
+
+    unsafe fn paint(
+        &self,
+        this: &mut AppData<T>,
+        pixels_per_point: f32,
+        primitives: &[egui::ClippedPrimitive],
+        textures_delta: &egui::TexturesDelta,
+    ) {
+        // A minimized window keeps its last size, but there is nothing to paint on.
+        let client_rect = if IsIconic(this.window).as_bool() {
+            (0, 0)
+        } else {
+            this.client_rect
+        };
+        // In the game's context one of its own framebuffers may still be bound.
+        let game_state = (this.context_mode == ContextMode::Game).then(|| {
+            let guard = GlStateGuard::new();
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            guard
+        });
+        if let Some(painter) = this.painter.as_mut() {
+            painter.set_opacity(self.opacity());
+            painter.set_font_gamma(f32::from_bits(self.font_gamma.load(Ordering::Acquire)));
+            painter.set_font_weight(f32::from_bits(self.font_weight.load(Ordering::Acquire)));
+            painter.paint_and_update_textures(
+                pixels_per_point,
+                primitives,
+                textures_delta,
+                &client_rect,
+            );
+            *self.last_frame_stats.lock() = Some(RenderStats {
+                ui_runs: this.ui_runs,
+                cached_frames: this.cached_frames,
+                ..painter.stats()
+            });
+
+            // Without compositing, the UI was painted at the window's size and must be laid out for it.
+            if this.render_resolution.is_some() && painter.render_resolution().is_none() {
+                this.render_resolution = None;
+                this.input_collector.set_render_resolution(None);
+                this.report(&Error::RenderResolution);
+            }
+        }
+        drop(game_state);
+    }
+
+    /// Call on each `WndProc` occurence.
+    /// Returns `true` if the message was consumed by egui and must not be passed to the game,
+    /// `false` otherwise.
+    #[inline]
+    pub fn wnd_proc(&self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> bool {
+        self.wnd_proc_result(umsg, wparam, lparam).is_consumed()
+    }
+
+    /// Like [`Self::wnd_proc`], but also tells what the window procedure should return for consumed messages,
+    /// e.g. `WM_GETDLGCODE` claims Tab, arrows and characters while egui has keyboard focus.
+    /// Keyboard messages are only consumed while egui wants the keyboard, mouse messages while it wants the pointer.
+    pub fn wnd_proc_result(&self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> WndProcResult {
+        match self.process_message(umsg, wparam, lparam) {
+            Some(result) => WndProcResult::Consumed(result),
+            None => WndProcResult::PassThrough,
+        }
+    }
+
+    /// Subclasses `window` so its messages go through [`Self::wnd_proc_result`] first, and the ones egui
+    /// doesn't consume on to the game, or hooks its thread, see [`Self::set_message_capture`].
+    /// Replaces the window attached before. Messages arriving before
+    /// an `init_*` call are passed through. Called from another thread than the window's, it blocks
+    /// until the window's thread processes messages.
+    /// The subclass is removed by [`Self::detach_wnd_proc`], [`Self::destroy`] or the window being destroyed.
+    pub fn attach_wnd_proc(&'static self, window: HWND) -> Result<(), Error> {
+        if self.attached_window.load(Ordering::Acquire) == window.0 {
+            return Ok(());
+        }
+
+        self.detach_wnd_proc();
+        unsafe {
+            if self.hook_messages.load(Ordering::Acquire) {
+                subclass::hook(self, window)
+            } else {
+                subclass::attach(self, window)
+            }
+        }
+        .map_err(Error::AttachWndProc)?;
+        self.attached_window.store(window.0, Ordering::Release);
+        Ok(())
+    }
+
+    /// Selects how [`Self::attach_wnd_proc`] receives the window's messages. Defaults to
+    /// [`MessageCapture::Subclass`]. An attached window is attached again with the new backend.
+    pub fn set_message_capture(&'static self, capture: MessageCapture) -> Result<(), Error> {
+        let hook = capture == MessageCapture::Hook;
+        if self.hook_messages.swap(hook, Ordering::AcqRel) == hook {
+            return Ok(());
+        }
+
+        match HWND(self.attached_window.load(Ordering::Acquire)) {
+            HWND(0) => Ok(()),
+            window => {
+                self.detach_wnd_proc();
+                self.attach_wnd_proc(window)
+            }
+        }
+    }
+
+    /// Restores the window procedure replaced by [`Self::attach_wnd_proc`], does nothing if none is attached.
+    pub fn detach_wnd_proc(&self) {
+        let window = HWND(self.attached_window.swap(0, Ordering::AcqRel));
+        if window.0 != 0 && unsafe { IsWindow(window) }.as_bool() {
+            if let Err(e) = unsafe { subclass::detach(self, window) } {
+                log!("Failed to detach the window procedure: {e}");
+            }
+        }
+    }
+
+    /// Called when `window` removed its subclass or hooks on its own, because it's being destroyed.
+    pub(crate) fn wnd_proc_detached(&self, window: HWND) {
+        let _ =
+            self.attached_window
+                .compare_exchange(window.0, 0, Ordering::AcqRel, Ordering::Acquire);
+    }
+
+    /// Window procedure body for subclassed windows: feeds the message to egui and passes
+    /// what egui didn't consume on to the `original` window procedure.
+    ///
+    /// # Safety
+    /// `original` must be a valid window procedure for `window`, usually the one it was subclassed from.
+    pub unsafe fn call_wnd_proc(
+        &self,
+        original: WNDPROC,
+        window: HWND,
+        umsg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        match self.wnd_proc_result(umsg, wparam, lparam) {
+            WndProcResult::Consumed(result) => result,
+            WndProcResult::PassThrough => CallWindowProcW(original, window, umsg, wparam, lparam),
+        }
+    }
+
+    fn process_message(&self, umsg: u32, wparam: WPARAM, lparam: LPARAM) -> Option<LRESULT> {
+        self.mouse_tracker.lock().process(umsg, wparam.0, lparam.0);
+        if matches!(umsg, WM_KEYDOWN | WM_SYSKEYDOWN) && !input::is_key_repeat(lparam.0) {
+            *self.key_press.lock() = Some(KeyCode::from_message(wparam.0, lparam.0));
+        }
+
+        // Follows the menu from the window's thread, which owns the cursor display counter.
+        #[cfg(feature = "mouse-unlock")]
+        if self.mouse_unlock.load(Ordering::Relaxed) {
+            crate::mouse_unlock::set_unlocked(self.is_menu_open());
+        }
+
+        // Passed through before init and after shutdown.
+        let Ok(mut data) = MutexGuard::try_map(self.data.lock(), Option::as_mut) else {
+            return None;
+        };
+        let this = &mut *data;
+        if this.window_lost {
+            return None;
+        }
+
+        if umsg == WM_NCDESTROY {
+            this.lose_window();
+            return None;
+        }
+
+        // Fullscreen toggles change the display mode or the window's size. Like the DPI below, tracked while
+        // hidden and with the menu closed too, nothing queries them again later.
+        if matches!(umsg, WM_SIZE | WM_DISPLAYCHANGE) {
+            self.update_client_rect(this);
+            this.dpi_scale = self.get_window_scale(this.window);
+        }
+
+        // Sent when the window moved to a monitor with another DPI, before the game resizes it.
+        if umsg == WM_DPICHANGED {
+            let dpi = (wparam.0 & 0xFFFF) as u32;
+            this.dpi_scale = dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32;
+            self.update_client_rect(this);
+            this.emit(AppEvent::DpiChanged(dpi));
+        }
+
+        if self.is_hidden() {
+            // Released from the window's thread, which owns the capture.
+            if std::mem::take(&mut this.pointer_captured) {
+                drop(data);
+                unsafe {
+                    let _ = ReleaseCapture();
+                }
+            }
+            return None;
+        }
+
+        if umsg == WM_KEYDOWN && self.is_toggle_key(wparam, lparam) {
+            let open = !self.menu_open.fetch_xor(true, Ordering::Relaxed);
+            // Don't keep the mouse captured for a drag the closed menu won't finish.
+            if !open && std::mem::take(&mut this.pointer_captured) {
+                drop(data);
+                unsafe {
+                    let _ = ReleaseCapture();
+                }
+            }
+            return Some(LRESULT(0));
+        }
+
+        if umsg == WM_DROPFILES {
+            return this.drop_files(wparam, lparam, self.is_menu_open());
+        }
+
+        if !self.is_menu_open() {
+            return None;
+        }
+
+        if umsg == WM_GETDLGCODE {
+            // Keeps dialog-style message loops from turning navigation keys into focus changes of their own.
+            return this.ctx.wants_keyboard_input().then_some(LRESULT(
+                (DLGC_WANTALLKEYS | DLGC_WANTARROWS | DLGC_WANTTAB | DLGC_WANTCHARS) as _,
+            ));
+        }
+
+        if umsg == WM_SETCURSOR {
+            // Only over the client area and while egui wants the pointer, the game keeps its own cursor otherwise.
+            let over_client = lparam.0 as u32 & 0xFFFF == HTCLIENT;
+            if !over_client || !(this.ctx.wants_pointer_input() || this.ctx.is_using_pointer()) {
+                return None;
+            }
+
+            let cursor = win32_cursor(this.cursor_icon);
+            drop(data);
+            unsafe {
+                SetCursor(
+                    cursor
+                        .and_then(|name| LoadCursorW(None, name).ok())
+                        .unwrap_or(HCURSOR(0)),
+                );
+            }
+            return Some(LRESULT(1));
+        }
+
+        this.input_collector.process(umsg, wparam.0, lparam.0);
+        if matches!(
+            umsg,
+            WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP | WM_TOUCH
+        ) {
+            // Never consumed, DefWindowProc still synthesizes mouse messages and the game closes the touch handle.
+            return None;
+        }
+
+        let capture = this.capture_change(umsg, wparam);
+
+        let window = this.window;
+        let wants_input = match umsg {
+            WM_KEYFIRST..=WM_KEYLAST | WM_PASTE => this.ctx.wants_keyboard_input(),
+            WM_MOUSEFIRST..=WM_MOUSELAST => {
+                this.ctx.wants_pointer_input() || this.ctx.is_using_pointer()
+            }
+            _ => false,
+        };
+        let block = this.input_blocking == InputBlocking::All && is_input_message(umsg);
+        drop(data);
+
+        unsafe {
+            match capture {
+                Some(true) => {
+                    SetCapture(window);
+                }
+                Some(false) => {
+                    let _ = ReleaseCapture();
+                }
+                None => {}
+            }
+        }
+
+        if block && umsg == WM_INPUT {
+            // Raw input still needs its cleanup, the game just doesn't get to read it.
+            return Some(unsafe { DefWindowProcW(window, umsg, wparam, lparam) });
+        }
+
+        (wants_input || block).then_some(LRESULT(1))
+    }
+
+    fn is_toggle_key(&self, wparam: WPARAM, lparam: LPARAM) -> bool {
+        // Bit 30 is set for auto-repeated key presses.
+        self.toggle_key()
+            .is_some_and(|key| wparam.0 == key.0 as usize && lparam.0 & (1 << 30) == 0)
+    }
+
+    pub fn get_window(&self) -> HWND {
+        let data = &mut *self.lock_data();
+        data.window
+    }
+}
+
+impl<T> OpenGLApp<T> {
+    fn collect_input(&self, data: &mut AppData<T>) -> egui::RawInput {
+        let native_pixels_per_point = data.native_pixels_per_point();
+        self.native_pixels_per_point
+            .store(native_pixels_per_point.to_bits(), Ordering::Release);
+        data.input_collector.apply_wheel_zoom(&data.ctx);
+        data.input_collector
+            .set_pixels_per_point(native_pixels_per_point * data.ctx.zoom_factor());
+
+        // egui won't see the releases of keys and buttons held while closing the menu.
+        let menu_open = self.is_menu_open();
+        if std::mem::replace(&mut data.menu_was_open, menu_open) && !menu_open {
+            data.input_collector.lose_focus();
+        }
+
+        for event in self.pushed_events.lock().drain(..) {
+            data.input_collector.push_event(event);
+        }
+
+        let mut input = data.input_collector.collect_input(&data.ctx);
+        let screen_rect = input.screen_rect;
+        let viewport = input.viewports.entry(input.viewport_id).or_default();
+        viewport.native_pixels_per_point = Some(native_pixels_per_point);
+        viewport.inner_rect = screen_rect;
+        let focused = unsafe { GetForegroundWindow() } == data.window;
+        viewport.focused = Some(focused);
+        unsafe {
+            viewport.minimized = Some(IsIconic(data.window).as_bool());
+            viewport.monitor_size = self.get_monitor_size(data.window, native_pixels_per_point);
+        }
+
+        if self.rebuild_atlas.swap(false, Ordering::AcqRel) {
+            data.atlas_generation = !data.atlas_generation;
+        }
+
+        // egui recreates its fonts whenever `max_texture_side` changes, so an odd generation
+        // reports one less than the actual limit.
+        input.max_texture_side = data
+            .max_texture_side
+            .map(|side| side - data.atlas_generation as usize);
+
+        input
+    }
+
+    /// Queues the key presses of the controller while the window is in the foreground.
+    #[cfg(feature = "gamepad")]
+    fn poll_gamepad(data: &mut AppData<T>) {
+        let focused = unsafe { GetForegroundWindow() } == data.window;
+        if let Some(gamepad) = data.gamepad.as_mut().filter(|_| focused) {
+            let has_focus = data.ctx.memory(|mem| mem.focus().is_some());
+            let mut events = vec![];
+            gamepad.poll(has_focus, &mut events);
+            for event in events {
+                data.input_collector.push_event(event);
+            }
+        }
+    }
+
+    /// Keeps the input consistent while hidden: egui won't see the messages, so held keys and buttons
+    /// are released once shown again, and events pushed meanwhile are dropped.
+    fn skip_hidden_frame(&self, data: &mut AppData<T>) {
+        data.input_collector.lose_focus();
+        self.pushed_events.lock().clear();
+    }
+
+    /// Reads the back buffer for the pending captures matching `include_ui`, once for all of them.
+    fn take_captures(&self, data: &mut AppData<T>, include_ui: bool) {
+        let captures = {
+            let mut pending = self.captures.lock();
+            if pending.iter().all(|c| c.include_ui != include_ui) {
+                return;
+            }
+
+            let (taken, kept) = std::mem::take(&mut *pending)
+                .into_iter()
+                .partition::<Vec<_>, _>(|c| c.include_ui == include_ui);
+            *pending = kept;
+            taken
+        };
+
+        let (width, height) = data.client_rect;
+        let Some(painter) = data.painter.as_ref() else {
+            return;
+        };
+
+        let image = painter.read_backbuffer([width as _, height as _]);
+        for capture in captures {
+            (capture.apply)(&mut data.state, image.clone());
+        }
+    }
+
+    /// Size of the monitor `window` is on, in points.
+    unsafe fn get_monitor_size(&self, window: HWND, pixels_per_point: f32) -> Option<egui::Vec2> {
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as _,
+            ..Default::default()
+        };
+        let monitor = MonitorFromWindow(window, MONITOR_DEFAULTTONEAREST);
+        GetMonitorInfoW(monitor, &mut info).as_bool().then(|| {
+            let rect = info.rcMonitor;
+            egui::vec2(
+                (rect.right - rect.left) as f32,
+                (rect.bottom - rect.top) as f32,
+            ) / pixels_per_point
+        })
+    }
+
+    /// Reads the size of the window's client area, calling the resize handler if it changed.
+    /// Minimized windows keep the last size.
+    fn update_client_rect(&self, data: &mut AppData<T>) {
+        let (width, height) = self.get_client_rect(data.window);
+        if width == 0 || height == 0 || (width, height) == data.client_rect {
+            return;
+        }
+
+        data.client_rect = (width, height);
+        if let Some(handler) = data.on_resize.as_mut() {
+            handler(width, height);
+        }
+    }
+
+    /// Returns the scale of the monitor `window` is on, `1.0` at 96 DPI.
+    #[inline]
+    fn get_window_scale(&self, window: HWND) -> f32 {
+        match unsafe { GetDpiForWindow(window) } {
+            0 => 1.,
+            dpi => dpi as f32 / USER_DEFAULT_SCREEN_DPI as f32,
+        }
+    }
+
+    #[inline]
+    fn get_client_rect(&self, window: HWND) -> (u32, u32) {
+        let mut rect = RECT::default();
+        unsafe {
+            let _ = GetClientRect(window, &mut rect);
+        }
+
+        (
+            (rect.right - rect.left) as u32,
+            (rect.bottom - rect.top) as u32,
+        )
+    }
+}
+
+/// Opens http(s) links from the UI in the default browser. Runs on its own thread since `ShellExecuteW`
+/// may block, other schemes are refused as they could launch arbitrary programs.
+fn open_url_in_browser(url: String) {
+    let scheme = url.split_once("://").map(|(scheme, _)| scheme);
+    if !scheme.is_some_and(|scheme| {
+        scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+    }) {
+        log!("Not opening {url}, only http(s) links are opened");
+        return;
+    }
+
+    std::thread::spawn(move || unsafe {
+        ShellExecuteW(
+            None,
+            &HSTRING::from("open"),
+            &HSTRING::from(url.as_str()),
+            None,
+            None,
+            SW_SHOWNORMAL,
+        );
+    });
+}
+
+/// Index of the pixel format of `hdc`, `None` if it has none, e.g. a memory device context.
+unsafe fn pixel_format(hdc: HDC) -> Option<i32> {
+    Some(GetPixelFormat(hdc)).filter(|&index| index != 0)
+}
+
+/// Lists the meshes painted per texture in the top left corner, for the debug overlay.
+fn show_mesh_counts(ctx: &Context, mesh_counts: &[(egui::TextureId, u32)]) {
+    egui::Area::new(egui::Id::new("egui-opengl-internal-mesh-counts"))
+        .order(egui::Order::Debug)
+        .fixed_pos([4., 4.])
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                for (texture_id, count) in mesh_counts {
+                    ui.monospace(format!("{texture_id:?}: {count} meshes"));
+                }
+            });
+        });
+}
+
+fn show_frame_timing(ctx: &Context, timing: FrameTiming, gpu_time: Option<Duration>) {
+    egui::Area::new(egui::Id::new("egui-opengl-internal-frame-timing"))
+        .order(egui::Order::Debug)
+        .anchor(egui::Align2::RIGHT_TOP, [-4., 4.])
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let ms = |time: Duration| time.as_secs_f32() * 1000.;
+                ui.monospace(format!("{:.0} fps", timing.fps));
+                ui.monospace(format!("frame    {:6.2} ms", ms(timing.frame_time)));
+                ui.monospace(format!("overlay  {:6.2} ms", ms(timing.overlay_time)));
+                if let Some(gpu_time) = gpu_time {
+                    ui.monospace(format!("gpu      {:6.2} ms", ms(gpu_time)));
+                }
+            });
+        });
+    // The numbers change every frame.
+    ctx.request_repaint();
+}
+
+fn is_input_message(umsg: u32) -> bool {
+    matches!(umsg, WM_MOUSEFIRST..=WM_MOUSELAST | WM_KEYFIRST..=WM_KEYLAST | WM_INPUT)
+}
+
+/// Closest system cursor to `icon`, `None` hides the cursor.
+fn win32_cursor(icon: egui::CursorIcon) -> Option<PCWSTR> {
+    use egui::CursorIcon as Icon;
+
+    let cursor = match icon {
+        Icon::None => return None,
+        Icon::Default
+        | Icon::ContextMenu
+        | Icon::Cell
+        | Icon::VerticalText
+        | Icon::Alias
+        | Icon::Copy
+        | Icon::ZoomIn
+        | Icon::ZoomOut => IDC_ARROW,
+        Icon::Help => IDC_HELP,
+        Icon::PointingHand => IDC_HAND,
+        Icon::Progress => IDC_APPSTARTING,
+        Icon::Wait => IDC_WAIT,
+        Icon::Crosshair => IDC_CROSS,
+        Icon::Text => IDC_IBEAM,
+        Icon::Move | Icon::Grab | Icon::Grabbing | Icon::AllScroll => IDC_SIZEALL,
+        Icon::NoDrop | Icon::NotAllowed => IDC_NO,
+        Icon::ResizeHorizontal | Icon::ResizeEast | Icon::ResizeWest | Icon::ResizeColumn => {
+            IDC_SIZEWE
+        }
+        Icon::ResizeVertical | Icon::ResizeNorth | Icon::ResizeSouth | Icon::ResizeRow => {
+            IDC_SIZENS
+        }
+        Icon::ResizeNeSw | Icon::ResizeNorthEast | Icon::ResizeSouthWest => IDC_SIZENESW,
+        Icon::ResizeNwSe | Icon::ResizeNorthWest | Icon::ResizeSouthEast => IDC_SIZENWSE,
+    };
+
+    Some(cursor)
 }
@@ -0,0 +1,61 @@
+/// Upper bound for the backoff exponent, so at most `2^MAX_EXPONENT - 1` frames are skipped between attempts.
+const MAX_EXPONENT: u32 = 8;
+
+/// Frame based exponential backoff used to retry failing operations without hammering the driver every frame.
+#[derive(Default)]
+pub struct Backoff {
+    failures: u32,
+    remaining: u32,
+}
+
+impl Backoff {
+    pub const fn new() -> Self {
+        Self {
+            failures: 0,
+            remaining: 0,
+        }
+    }
+
+    /// Returns `true` if the current frame should be skipped.
+    pub fn skip(&mut self) -> bool {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Records a failed attempt and returns the number of consecutive failures.
+    pub fn fail(&mut self) -> u32 {
+        self.failures += 1;
+        self.remaining = (1 << self.failures.min(MAX_EXPONENT)) - 1;
+        self.failures
+    }
+
+    /// Records a successful attempt.
+    pub fn succeed(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[test]
+fn test_backoff() {
+    let mut backoff = Backoff::new();
+    assert!(!backoff.skip());
+
+    assert_eq!(backoff.fail(), 1);
+    assert!(backoff.skip());
+    assert!(!backoff.skip());
+
+    assert_eq!(backoff.fail(), 2);
+    assert_eq!((0..4).filter(|_| backoff.skip()).count(), 3);
+
+    for _ in 0..20 {
+        backoff.fail();
+    }
+    assert_eq!((0..1000).filter(|_| backoff.skip()).count(), 255);
+
+    backoff.succeed();
+    assert!(!backoff.skip());
+}
@@ -0,0 +1,436 @@
+use crate::{gl_version::has_extension, GlVersion};
+use gl::types::*;
+
+/// Number of frames the GPU may lag behind, each gets its own region of a [`PersistentBuffer`].
+const FRAMES_IN_FLIGHT: usize = 3;
+/// Initial size of every region of a [`PersistentBuffer`], grown as needed.
+const INITIAL_REGION_SIZE: usize = 256 * 1024;
+/// Longest wait for the GPU to release a region before writing into it anyway.
+const FENCE_TIMEOUT_NS: u64 = 1_000_000_000;
+
+/// Buffer object that is written once per draw. The storage only grows, uploads that fit
+/// orphan it and write with `glBufferSubData` so the driver doesn't reallocate every time.
+pub struct StreamBuffer {
+    target: GLenum,
+    id: GLuint,
+    /// Size of the allocated storage in bytes.
+    capacity: usize,
+}
+
+impl StreamBuffer {
+    pub fn new(target: GLenum) -> Self {
+        let mut id = 0;
+        unsafe { gl::GenBuffers(1, &mut id) };
+
+        Self {
+            target,
+            id,
+            capacity: 0,
+        }
+    }
+
+    /// Binds the buffer and replaces its contents with `data`.
+    pub unsafe fn upload<T>(&mut self, data: &[T]) {
+        let bytes = std::mem::size_of_val(data);
+
+        gl::BindBuffer(self.target, self.id);
+        if bytes > self.capacity {
+            self.capacity = bytes.next_power_of_two();
+        }
+
+        // Orphaning lets the driver hand out fresh storage while draws reading the old contents are in flight.
+        gl::BufferData(
+            self.target,
+            self.capacity as GLsizeiptr,
+            std::ptr::null(),
+            gl::STREAM_DRAW,
+        );
+        gl::BufferSubData(
+            self.target,
+            0,
+            bytes as GLsizeiptr,
+            data.as_ptr() as *const _,
+        );
+    }
+
+    /// Size of the allocated storage in bytes.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl Drop for StreamBuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.id) };
+    }
+}
+
+/// Buffer written through `glMapBufferRange`, uploads are appended unsynchronized within a frame
+/// and the first upload of every frame invalidates the whole buffer, which orphans it like [`StreamBuffer`]
+/// without the extra copy `glBufferSubData` makes.
+pub struct MappedBuffer {
+    target: GLenum,
+    id: GLuint,
+    capacity: usize,
+    /// Bytes already written this frame.
+    cursor: usize,
+}
+
+impl MappedBuffer {
+    /// Whether the current context supports mapping buffer ranges.
+    pub fn is_supported(version: GlVersion) -> bool {
+        gl::MapBufferRange::is_loaded()
+            && (version.at_least(3, 0) || has_extension("GL_ARB_map_buffer_range"))
+    }
+
+    pub fn new(target: GLenum) -> Self {
+        let mut id = 0;
+        unsafe { gl::GenBuffers(1, &mut id) };
+
+        Self {
+            target,
+            id,
+            capacity: 0,
+            cursor: 0,
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        // Makes the next upload invalidate the buffer, draws of the last frame may still read from it.
+        self.cursor = self.capacity;
+    }
+
+    /// Binds the buffer and appends `data`, returns its byte offset into the buffer.
+    /// `None` if the driver fails to map or unmap the buffer.
+    pub unsafe fn upload<T>(&mut self, data: &[T]) -> Option<usize> {
+        let bytes = std::mem::size_of_val(data);
+        // Keeps the next upload aligned for any attribute or index type.
+        let aligned = (bytes + 3) & !3;
+
+        gl::BindBuffer(self.target, self.id);
+        if bytes == 0 {
+            return Some(0);
+        }
+
+        let (offset, invalidate) = if self.cursor + aligned <= self.capacity {
+            // Nothing has read this part of the buffer since it was invalidated.
+            (self.cursor, gl::MAP_INVALIDATE_RANGE_BIT)
+        } else {
+            if aligned > self.capacity {
+                self.capacity = aligned.max(INITIAL_REGION_SIZE).next_power_of_two();
+                gl::BufferData(
+                    self.target,
+                    self.capacity as GLsizeiptr,
+                    std::ptr::null(),
+                    gl::STREAM_DRAW,
+                );
+            }
+            (0, gl::MAP_INVALIDATE_BUFFER_BIT)
+        };
+
+        let access = gl::MAP_WRITE_BIT | gl::MAP_UNSYNCHRONIZED_BIT | invalidate;
+        let ptr = gl::MapBufferRange(self.target, offset as GLintptr, bytes as GLsizeiptr, access)
+            as *mut u8;
+        if ptr.is_null() {
+            return None;
+        }
+
+        std::ptr::copy_nonoverlapping(data.as_ptr() as *const u8, ptr, bytes);
+        // The contents are lost if the storage was corrupted while mapped, e.g. by a mode switch.
+        if gl::UnmapBuffer(self.target) == gl::FALSE {
+            return None;
+        }
+
+        self.cursor = offset + aligned;
+        Some(offset)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl Drop for MappedBuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.id) };
+    }
+}
+
+/// Buffer that stays mapped for its whole lifetime (`ARB_buffer_storage`), split into one region
+/// per frame in flight. Every upload is appended to the current frame's region, fences keep the
+/// CPU from overwriting a region the GPU still reads from.
+pub struct PersistentBuffer {
+    target: GLenum,
+    id: GLuint,
+    ptr: *mut u8,
+    region_size: usize,
+    region: usize,
+    /// Bytes of the current region already written this frame.
+    cursor: usize,
+    fences: [GLsync; FRAMES_IN_FLIGHT],
+}
+
+impl PersistentBuffer {
+    /// Whether the current context supports persistently mapped buffers.
+    pub fn is_supported(version: GlVersion) -> bool {
+        gl::BufferStorage::is_loaded()
+            && gl::FenceSync::is_loaded()
+            && (version.at_least(4, 4) || has_extension("GL_ARB_buffer_storage"))
+    }
+
+    /// Returns `None` if the driver fails to map the buffer.
+    pub unsafe fn new(target: GLenum) -> Option<Self> {
+        let mut buffer = Self {
+            target,
+            id: 0,
+            ptr: std::ptr::null_mut(),
+            region_size: 0,
+            region: 0,
+            cursor: 0,
+            fences: [std::ptr::null(); FRAMES_IN_FLIGHT],
+        };
+
+        buffer.allocate(INITIAL_REGION_SIZE).then_some(buffer)
+    }
+
+    /// Replaces the storage with a new one of `region_size` bytes per region.
+    unsafe fn allocate(&mut self, region_size: usize) -> bool {
+        self.release();
+
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+        let size = (region_size * FRAMES_IN_FLIGHT) as GLsizeiptr;
+
+        gl::GenBuffers(1, &mut self.id);
+        gl::BindBuffer(self.target, self.id);
+        gl::BufferStorage(self.target, size, std::ptr::null(), flags);
+        self.ptr = gl::MapBufferRange(self.target, 0, size, flags) as *mut u8;
+        self.region_size = region_size;
+        self.cursor = 0;
+
+        !self.ptr.is_null()
+    }
+
+    unsafe fn release(&mut self) {
+        for fence in self.fences.iter_mut() {
+            if !fence.is_null() {
+                gl::DeleteSync(*fence);
+                *fence = std::ptr::null();
+            }
+        }
+
+        if self.id != 0 {
+            // Draws still reading from the buffer keep it alive until they're done.
+            gl::BindBuffer(self.target, self.id);
+            gl::UnmapBuffer(self.target);
+            gl::DeleteBuffers(1, &self.id);
+            self.id = 0;
+            self.ptr = std::ptr::null_mut();
+        }
+    }
+
+    /// Moves on to the next region, waiting for the GPU to finish the frame that last used it.
+    pub unsafe fn begin_frame(&mut self) {
+        self.region = (self.region + 1) % FRAMES_IN_FLIGHT;
+        self.cursor = 0;
+
+        let fence = std::mem::replace(&mut self.fences[self.region], std::ptr::null());
+        if !fence.is_null() {
+            let status = gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, FENCE_TIMEOUT_NS);
+            if status == gl::TIMEOUT_EXPIRED || status == gl::WAIT_FAILED {
+                log!("Waiting for a streaming buffer region failed: {status:#x}");
+            }
+            gl::DeleteSync(fence);
+        }
+    }
+
+    /// Marks the current region as in use by the draws issued since [`Self::begin_frame`].
+    pub unsafe fn end_frame(&mut self) {
+        if self.cursor > 0 {
+            self.fences[self.region] = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+        }
+    }
+
+    /// Binds the buffer and appends `data`, returns its byte offset into the buffer.
+    pub unsafe fn upload<T>(&mut self, data: &[T]) -> Option<usize> {
+        let bytes = std::mem::size_of_val(data);
+        // Keeps the next upload aligned for any attribute or index type.
+        let aligned = (bytes + 3) & !3;
+
+        if self.cursor + aligned > self.region_size {
+            let needed = (self.cursor + aligned).max(self.region_size * 2);
+            if !self.allocate(needed.next_power_of_two()) {
+                return None;
+            }
+        }
+
+        gl::BindBuffer(self.target, self.id);
+        let offset = self.region * self.region_size + self.cursor;
+        std::ptr::copy_nonoverlapping(data.as_ptr() as *const u8, self.ptr.add(offset), bytes);
+        self.cursor += aligned;
+
+        Some(offset)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.region_size * FRAMES_IN_FLIGHT
+    }
+}
+
+impl Drop for PersistentBuffer {
+    fn drop(&mut self) {
+        unsafe { self.release() };
+    }
+}
+
+/// Pixel data staged in a pixel buffer object for a texture upload, so `glTexImage2D` returns without
+/// waiting for the copy. A fence tells when the texture is complete.
+pub struct PixelUpload {
+    pbo: GLuint,
+    fence: GLsync,
+}
+
+impl PixelUpload {
+    /// Whether the current context supports pixel buffer objects and fences.
+    pub fn is_supported(version: GlVersion) -> bool {
+        gl::FenceSync::is_loaded()
+            && (version.at_least(3, if version.es { 0 } else { 2 }) || has_extension("GL_ARB_sync"))
+    }
+
+    /// Copies `data` into a new pixel buffer object and binds it to `GL_PIXEL_UNPACK_BUFFER`,
+    /// pixel pointers of uploads are offsets into it until [`Self::submit`].
+    pub unsafe fn stage(data: &[u8]) -> Self {
+        let mut pbo = 0;
+        gl::GenBuffers(1, &mut pbo);
+        gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, pbo);
+        gl::BufferData(
+            gl::PIXEL_UNPACK_BUFFER,
+            data.len() as GLsizeiptr,
+            data.as_ptr() as *const _,
+            gl::STREAM_DRAW,
+        );
+
+        Self {
+            pbo,
+            fence: std::ptr::null(),
+        }
+    }
+
+    /// Unbinds the buffer and fences the uploads issued since [`Self::stage`].
+    pub unsafe fn submit(&mut self) {
+        gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+        self.fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+    }
+
+    /// Checks the fence without waiting. A failed wait counts as complete, so the texture isn't hidden forever.
+    pub unsafe fn is_complete(&self) -> bool {
+        self.fence.is_null() || gl::ClientWaitSync(self.fence, 0, 0) != gl::TIMEOUT_EXPIRED
+    }
+
+    /// Deletes the fence and the buffer, uploads still reading from it keep it alive until they're done.
+    pub unsafe fn delete(&self) {
+        if !self.fence.is_null() {
+            gl::DeleteSync(self.fence);
+        }
+        gl::DeleteBuffers(1, &self.pbo);
+    }
+}
+
+/// How the painter streams vertices and indices to the GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferKind {
+    /// Orphaned with `glBufferData` and written with `glBufferSubData`, supported everywhere.
+    Stream,
+    /// Written through `glMapBufferRange`, see [`MappedBuffer`].
+    Mapped,
+    /// Persistently mapped, see [`PersistentBuffer`].
+    Persistent,
+}
+
+impl BufferKind {
+    /// Fastest kind the current context supports.
+    pub fn detect(version: GlVersion) -> Self {
+        if PersistentBuffer::is_supported(version) {
+            Self::Persistent
+        } else if MappedBuffer::is_supported(version) {
+            Self::Mapped
+        } else {
+            Self::Stream
+        }
+    }
+}
+
+/// Vertex or index buffer of the painter, persistently mapped where the driver supports it.
+pub enum MeshBuffer {
+    Stream(StreamBuffer),
+    Mapped(MappedBuffer),
+    Persistent(PersistentBuffer),
+}
+
+impl MeshBuffer {
+    pub fn new(target: GLenum, kind: BufferKind) -> Self {
+        match kind {
+            BufferKind::Stream => Self::Stream(StreamBuffer::new(target)),
+            BufferKind::Mapped => Self::Mapped(MappedBuffer::new(target)),
+            BufferKind::Persistent => unsafe { PersistentBuffer::new(target) }
+                .map(Self::Persistent)
+                .unwrap_or_else(|| Self::Stream(StreamBuffer::new(target))),
+        }
+    }
+
+    pub unsafe fn begin_frame(&mut self) {
+        match self {
+            Self::Stream(_) => {}
+            Self::Mapped(buffer) => buffer.begin_frame(),
+            Self::Persistent(buffer) => buffer.begin_frame(),
+        }
+    }
+
+    pub unsafe fn end_frame(&mut self) {
+        if let Self::Persistent(buffer) = self {
+            buffer.end_frame();
+        }
+    }
+
+    /// Binds the buffer and writes `data` into it, returns its byte offset into the buffer.
+    /// Falls back to an orphaned buffer if a mapped one can't grow or be mapped.
+    pub unsafe fn upload<T>(&mut self, data: &[T]) -> usize {
+        let target = match self {
+            Self::Stream(buffer) => {
+                buffer.upload(data);
+                return 0;
+            }
+            Self::Mapped(buffer) => match buffer.upload(data) {
+                Some(offset) => return offset,
+                None => {
+                    log!("Failed to map a streaming buffer, falling back to orphaning");
+                    buffer.target
+                }
+            },
+            Self::Persistent(buffer) => match buffer.upload(data) {
+                Some(offset) => return offset,
+                None => {
+                    log!("Failed to grow a persistently mapped buffer, falling back to orphaning");
+                    buffer.target
+                }
+            },
+        };
+
+        let mut buffer = StreamBuffer::new(target);
+        buffer.upload(data);
+        *self = Self::Stream(buffer);
+        0
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        matches!(self, Self::Persistent(_))
+    }
+
+    /// Size of the allocated storage in bytes.
+    pub fn capacity(&self) -> usize {
+        match self {
+            Self::Stream(buffer) => buffer.capacity(),
+            Self::Mapped(buffer) => buffer.capacity(),
+            Self::Persistent(buffer) => buffer.capacity(),
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Chainable configuration for [`OpenGLApp`], see [`OpenGLApp::builder`].
+
+use crate::{
+    app::InitOptions, input::TextInputSource, AppEvent, ContextMode, Error, InputBlocking,
+    OpenGLApp,
+};
+use egui::{Context, FontDefinitions, Visuals};
+use windows::Win32::{
+    Foundation::HWND, Graphics::Gdi::HDC, UI::Input::KeyboardAndMouse::VIRTUAL_KEY,
+};
+
+/// Options applied to an [`OpenGLApp`] when it's initialized with [`Self::init`].
+/// Options left unset keep the app's defaults.
+///
+/// ```ignore
+/// static APP: OpenGLApp<i32> = OpenGLApp::new();
+///
+/// OpenGLApp::builder()
+///     .ui(|ctx, counter: &mut i32| {
+///         egui::Window::new("Counter").show(ctx, |ui| {
+///             if ui.button("+1").clicked() {
+///                 *counter += 1;
+///             }
+///         });
+///     })
+///     .toggle_key(Some(VK_INSERT))
+///     .visuals(egui::Visuals::light())
+///     .init(&APP, hdc, window)
+///     .unwrap();
+/// ```
+#[allow(clippy::type_complexity)]
+pub struct OpenGLAppBuilder<T> {
+    ui: Option<Box<dyn FnMut(&Context, &mut T) + 'static>>,
+    state: T,
+    context: Context,
+    toggle_key: Option<Option<VIRTUAL_KEY>>,
+    pixels_per_point: Option<f32>,
+    framebuffer_srgb: Option<bool>,
+    input_blocking: Option<InputBlocking>,
+    text_input_source: Option<TextInputSource>,
+    fonts: Option<FontDefinitions>,
+    visuals: Option<Visuals>,
+    gl_state_check: Option<bool>,
+    context_mode: ContextMode,
+    on_event: Option<Box<dyn FnMut(AppEvent) + 'static>>,
+    on_error: Option<Box<dyn FnMut(&Error) + 'static>>,
+    #[cfg(feature = "persistence")]
+    memory_path: Option<std::path::PathBuf>,
+}
+
+impl<T> OpenGLAppBuilder<T> {
+    /// Starts with `state`, a default egui context and a UI that draws nothing.
+    pub fn with_state(state: T) -> Self {
+        Self {
+            ui: None,
+            state,
+            context: Context::default(),
+            toggle_key: None,
+            pixels_per_point: None,
+            framebuffer_srgb: None,
+            input_blocking: None,
+            text_input_source: None,
+            fonts: None,
+            visuals: None,
+            gl_state_check: None,
+            context_mode: ContextMode::default(),
+            on_event: None,
+            on_error: None,
+            #[cfg(feature = "persistence")]
+            memory_path: None,
+        }
+    }
+
+    /// Closure drawing the UI every frame.
+    pub fn ui(mut self, ui: impl FnMut(&Context, &mut T) + 'static) -> Self {
+        self.ui = Some(Box::new(ui));
+        self
+    }
+
+    /// Replaces the initial state.
+    pub fn state(mut self, state: T) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// egui context to use instead of a default one.
+    pub fn context(mut self, context: Context) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// See [`OpenGLApp::set_toggle_key`].
+    pub fn toggle_key(mut self, key: Option<VIRTUAL_KEY>) -> Self {
+        self.toggle_key = Some(key);
+        self
+    }
+
+    /// Fixed scale to render at instead of following the monitor's DPI, see [`OpenGLApp::set_pixels_per_point`].
+    pub fn pixels_per_point(mut self, pixels_per_point: f32) -> Self {
+        self.pixels_per_point = Some(pixels_per_point);
+        self
+    }
+
+    /// Forces the sRGB mode instead of detecting it, see [`OpenGLApp::set_framebuffer_srgb`].
+    pub fn framebuffer_srgb(mut self, srgb: bool) -> Self {
+        self.framebuffer_srgb = Some(srgb);
+        self
+    }
+
+    /// See [`OpenGLApp::set_input_blocking`].
+    pub fn input_blocking(mut self, blocking: InputBlocking) -> Self {
+        self.input_blocking = Some(blocking);
+        self
+    }
+
+    /// See [`OpenGLApp::set_text_input_source`].
+    pub fn text_input_source(mut self, source: TextInputSource) -> Self {
+        self.text_input_source = Some(source);
+        self
+    }
+
+    /// Fonts set on the context before the first frame.
+    pub fn fonts(mut self, fonts: FontDefinitions) -> Self {
+        self.fonts = Some(fonts);
+        self
+    }
+
+    /// Theme set on the context before the first frame, e.g. [`Visuals::light`].
+    pub fn visuals(mut self, visuals: Visuals) -> Self {
+        self.visuals = Some(visuals);
+        self
+    }
+
+    /// See [`OpenGLApp::set_gl_state_check`].
+    pub fn gl_state_check(mut self, enabled: bool) -> Self {
+        self.gl_state_check = Some(enabled);
+        self
+    }
+
+    /// Context the overlay paints with from the start, unlike [`OpenGLApp::set_context_mode`] after init
+    /// no [`ContextMode::Separate`] context is created first. For drivers that fail to create one, use [`ContextMode::Game`].
+    pub fn context_mode(mut self, mode: ContextMode) -> Self {
+        self.context_mode = mode;
+        self
+    }
+
+    /// See [`OpenGLApp::set_event_handler`].
+    pub fn event_handler(mut self, handler: impl FnMut(AppEvent) + 'static) -> Self {
+        self.on_event = Some(Box::new(handler));
+        self
+    }
+
+    /// See [`OpenGLApp::set_error_handler`].
+    pub fn error_handler(mut self, handler: impl FnMut(&Error) + 'static) -> Self {
+        self.on_error = Some(Box::new(handler));
+        self
+    }
+
+    /// See [`OpenGLApp::set_memory_path`].
+    #[cfg(feature = "persistence")]
+    pub fn memory_path(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.memory_path = Some(path.into());
+        self
+    }
+
+    /// Logs errors reported by the app to stderr, unless feature `no-msgs` is present.
+    pub fn log_errors(self) -> Self {
+        self.error_handler(|e| log!("egui-opengl-internal: {e}"))
+    }
+
+    /// Initializes `app` and applies the options.
+    /// Fails like [`OpenGLApp::init_with_state_context`].
+    pub fn init(self, app: &OpenGLApp<T>, hdc: HDC, window: HWND) -> Result<(), Error> {
+        if let Some(fonts) = self.fonts {
+            self.context.set_fonts(fonts);
+        }
+        if let Some(visuals) = self.visuals {
+            self.context.set_visuals(visuals);
+        }
+        if let Some(key) = self.toggle_key {
+            app.set_toggle_key(key);
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(path) = self.memory_path {
+            app.set_memory_path(path)?;
+        }
+
+        // The handlers are installed with the data, so errors of the first context creation reach them.
+        app.init_data(
+            hdc,
+            window,
+            InitOptions {
+                ui: self.ui.unwrap_or_else(|| Box::new(|_, _| {})),
+                state: self.state,
+                context: self.context,
+                context_mode: self.context_mode,
+                on_event: self.on_event,
+                on_error: self.on_error,
+            },
+        )?;
+
+        if self.pixels_per_point.is_some() {
+            app.set_pixels_per_point(self.pixels_per_point);
+        }
+        if self.framebuffer_srgb.is_some() {
+            app.set_framebuffer_srgb(self.framebuffer_srgb);
+        }
+        if let Some(blocking) = self.input_blocking {
+            app.set_input_blocking(blocking);
+        }
+        if let Some(source) = self.text_input_source {
+            app.set_text_input_source(source);
+        }
+        if let Some(enabled) = self.gl_state_check {
+            app.set_gl_state_check(enabled);
+        }
+        Ok(())
+    }
+}
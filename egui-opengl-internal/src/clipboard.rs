@@ -0,0 +1,123 @@
+use crate::Error;
+use std::{thread, time::Duration};
+use windows::Win32::{
+    Foundation::{GlobalFree, HANDLE, HWND},
+    System::{
+        DataExchange::{
+            CloseClipboard, EmptyClipboard, GetClipboardData, IsClipboardFormatAvailable,
+            OpenClipboard, SetClipboardData,
+        },
+        Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+        Ole::CF_UNICODETEXT,
+    },
+};
+
+/// How many times opening the clipboard is attempted while another application holds it.
+const OPEN_ATTEMPTS: u32 = 5;
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(5);
+
+/// Places `text` on the clipboard as `CF_UNICODETEXT`, converting line endings to `\r\n`.
+pub fn set_text(owner: HWND, text: &str) -> Result<(), Error> {
+    let wide: Vec<u16> = to_crlf(text).encode_utf16().chain(Some(0)).collect();
+
+    unsafe {
+        open(owner)?;
+        let result = write(&wide);
+        let _ = CloseClipboard();
+
+        result
+    }
+}
+
+/// Reads `CF_UNICODETEXT` from the clipboard, converting line endings to `\n`.
+pub fn get_text(owner: HWND) -> Result<Option<String>, Error> {
+    unsafe {
+        if IsClipboardFormatAvailable(CF_UNICODETEXT.0 as _).is_err() {
+            return Ok(None);
+        }
+
+        open(owner)?;
+        let result = read();
+        let _ = CloseClipboard();
+
+        result.map(|text| text.map(|text| text.replace("\r\n", "\n")))
+    }
+}
+
+unsafe fn open(owner: HWND) -> Result<(), Error> {
+    let mut attempt = 1;
+    loop {
+        match OpenClipboard(owner) {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= OPEN_ATTEMPTS => return Err(Error::Clipboard(e)),
+            Err(_) => {
+                attempt += 1;
+                thread::sleep(OPEN_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+unsafe fn write(wide: &[u16]) -> Result<(), Error> {
+    EmptyClipboard().map_err(Error::Clipboard)?;
+
+    let size = std::mem::size_of_val(wide);
+    let memory = GlobalAlloc(GMEM_MOVEABLE, size).map_err(Error::Clipboard)?;
+
+    let ptr = GlobalLock(memory) as *mut u16;
+    if ptr.is_null() {
+        let _ = GlobalFree(memory);
+        return Err(Error::Clipboard(windows::core::Error::from_win32()));
+    }
+
+    std::ptr::copy_nonoverlapping(wide.as_ptr(), ptr, wide.len());
+    let _ = GlobalUnlock(memory);
+
+    // The system owns the memory once `SetClipboardData` succeeds.
+    if let Err(e) = SetClipboardData(CF_UNICODETEXT.0 as _, HANDLE(memory.0 as _)) {
+        let _ = GlobalFree(memory);
+        return Err(Error::Clipboard(e));
+    }
+
+    Ok(())
+}
+
+unsafe fn read() -> Result<Option<String>, Error> {
+    let handle = GetClipboardData(CF_UNICODETEXT.0 as _).map_err(Error::Clipboard)?;
+    let memory = windows::Win32::Foundation::HGLOBAL(handle.0 as _);
+
+    let ptr = GlobalLock(memory) as *const u16;
+    if ptr.is_null() {
+        return Ok(None);
+    }
+
+    let len = (0..).take_while(|&i| *ptr.add(i) != 0).count();
+    let text = String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len));
+    let _ = GlobalUnlock(memory);
+
+    Ok(Some(text))
+}
+
+/// Converts lone `\n` line endings into `\r\n`, which is what `CF_UNICODETEXT` expects.
+fn to_crlf(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.len() / 16);
+    let mut prev = '\0';
+
+    for ch in text.chars() {
+        if ch == '\n' && prev != '\r' {
+            out.push('\r');
+        }
+        out.push(ch);
+        prev = ch;
+    }
+
+    out
+}
+
+#[test]
+fn test_to_crlf() {
+    assert_eq!(to_crlf("a\nb"), "a\r\nb");
+    assert_eq!(to_crlf("a\r\nb\n"), "a\r\nb\r\n");
+    assert_eq!(to_crlf("\n\n"), "\r\n\r\n");
+    assert_eq!(to_crlf("plain"), "plain");
+}
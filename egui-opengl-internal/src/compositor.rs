@@ -0,0 +1,134 @@
+use crate::{
+    offscreen::Offscreen,
+    shader::{Shader, ShaderError, ShaderVersion},
+    BlendMode, ProgramLocations, SurfaceConfig,
+};
+use gl::types::*;
+
+/// Corners of a full screen quad, drawn as a triangle strip.
+const QUAD: [f32; 8] = [-1., -1., 1., -1., -1., 1., 1., 1.];
+
+/// Paints the UI into an offscreen framebuffer first and blends it onto the target in a final pass,
+/// which allows effects on the UI as a whole, like fading it in and out.
+pub struct Compositor {
+    program: GLuint,
+    a_pos: GLuint,
+    u_sampler: GLint,
+    u_opacity: GLint,
+    quad: GLuint,
+    offscreen: Option<Offscreen>,
+    /// Framebuffer bound before [`Self::begin`], the composite target.
+    target: GLuint,
+}
+
+impl Compositor {
+    pub fn new(shader_version: ShaderVersion) -> Result<Self, ShaderError> {
+        let program = Shader::build(
+            &shader_version.source(include_str!("shader/composite.vert")),
+            &shader_version.source(include_str!("shader/composite.frag")),
+        )?;
+        let locations = ProgramLocations::new(program);
+
+        let mut quad = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut quad);
+            gl::BindBuffer(gl::ARRAY_BUFFER, quad);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&QUAD) as GLsizeiptr,
+                QUAD.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+        }
+
+        Ok(Self {
+            program,
+            a_pos: expect!(locations.attrib("a_pos"), "Composite shader has no a_pos"),
+            u_sampler: locations.uniform("u_sampler"),
+            u_opacity: locations.uniform("u_opacity"),
+            quad,
+            offscreen: None,
+            target: 0,
+        })
+    }
+
+    /// Binds a cleared offscreen framebuffer of `size`. Returns `false`, leaving the bound
+    /// framebuffer alone, if it can't be created.
+    pub unsafe fn begin(&mut self, size: (u32, u32), surface: SurfaceConfig) -> bool {
+        if self.offscreen.as_ref().map(Offscreen::size) != Some(size) {
+            self.offscreen = None;
+            self.offscreen = Offscreen::new(size.0, size.1, surface.framebuffer_srgb);
+        }
+
+        let Some(offscreen) = self.offscreen.as_ref() else {
+            return false;
+        };
+
+        gl::Disable(gl::SCISSOR_TEST);
+        offscreen.clear([0.; 4]);
+        self.target = offscreen.bind();
+        true
+    }
+
+    /// Blends what was painted since [`Self::begin`] onto the previously bound framebuffer,
+    /// stretched to `target_size` if that differs from the size painted at.
+    pub unsafe fn finish(
+        &mut self,
+        vertex_array: Option<GLuint>,
+        surface: SurfaceConfig,
+        opacity: f32,
+        target_size: (u32, u32),
+    ) {
+        let Some(offscreen) = self.offscreen.as_ref() else {
+            return;
+        };
+        offscreen.unbind(self.target);
+
+        let (width, height) = target_size;
+        gl::Viewport(0, 0, width as _, height as _);
+        gl::Disable(gl::SCISSOR_TEST);
+        if surface.framebuffer_srgb {
+            gl::Enable(gl::FRAMEBUFFER_SRGB);
+        }
+        gl::Enable(gl::BLEND);
+        match surface.blend {
+            BlendMode::Premultiplied => gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::PremultipliedKeepAlpha => gl::BlendFuncSeparate(
+                gl::ONE,
+                gl::ONE_MINUS_SRC_ALPHA,
+                gl::ONE_MINUS_DST_ALPHA,
+                gl::ONE,
+            ),
+        }
+
+        gl::UseProgram(self.program);
+        gl::Uniform1i(self.u_sampler, 0);
+        gl::Uniform1f(self.u_opacity, opacity.clamp(0., 1.));
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, offscreen.texture());
+        let filter = match offscreen.size() == target_size {
+            true => gl::NEAREST,
+            false => gl::LINEAR,
+        };
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as _);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as _);
+
+        if let Some(vertex_array) = vertex_array {
+            gl::BindVertexArray(vertex_array);
+        }
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.quad);
+        gl::VertexAttribPointer(self.a_pos, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+        gl::EnableVertexAttribArray(self.a_pos);
+        gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        gl::DisableVertexAttribArray(self.a_pos);
+    }
+}
+
+impl Drop for Compositor {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.quad);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use gl::types::GLenum;
+
+// From EXT_texture_compression_s3tc, which isn't part of core OpenGL.
+const COMPRESSED_RGB_S3TC_DXT1_EXT: GLenum = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: GLenum = 0x83F3;
+
+/// Block compressed texture formats accepted by [`crate::Painter::new_compressed_texture`].
+/// The data is expected in sRGB color space, like egui's own textures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressedFormat {
+    /// DXT1, opaque RGB at 4 bits per pixel.
+    Bc1,
+    /// DXT5, RGBA with interpolated alpha at 8 bits per pixel.
+    Bc3,
+    /// High quality RGBA at 8 bits per pixel, needs OpenGL 4.2 or `ARB_texture_compression_bptc`.
+    Bc7,
+}
+
+impl CompressedFormat {
+    pub(crate) fn gl_format(self) -> GLenum {
+        match self {
+            Self::Bc1 => COMPRESSED_RGB_S3TC_DXT1_EXT,
+            Self::Bc3 => COMPRESSED_RGBA_S3TC_DXT5_EXT,
+            Self::Bc7 => gl::COMPRESSED_RGBA_BPTC_UNORM,
+        }
+    }
+
+    /// Size of a single 4x4 block in bytes.
+    pub fn block_bytes(self) -> usize {
+        match self {
+            Self::Bc1 => 8,
+            Self::Bc3 | Self::Bc7 => 16,
+        }
+    }
+
+    /// Size in bytes of a `width` x `height` image, partial blocks at the edges are stored whole.
+    pub fn data_len(self, width: usize, height: usize) -> usize {
+        width.div_ceil(4) * height.div_ceil(4) * self.block_bytes()
+    }
+
+    /// Checks whether the current context can upload this format.
+    pub fn is_supported(self) -> bool {
+        let mut count = 0;
+        unsafe { gl::GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, &mut count) };
+
+        let mut formats = vec![0; count.max(0) as usize];
+        if !formats.is_empty() {
+            unsafe { gl::GetIntegerv(gl::COMPRESSED_TEXTURE_FORMATS, formats.as_mut_ptr()) };
+        }
+
+        formats.contains(&(self.gl_format() as _))
+    }
+}
+
+/// Size of mip `level` of an image of `size`.
+pub(crate) fn level_size(size: (usize, usize), level: u32) -> (usize, usize) {
+    ((size.0 >> level).max(1), (size.1 >> level).max(1))
+}
+
+/// Length in bytes of a single mip level, RGBA if `format` is `None`.
+pub(crate) fn level_len(format: Option<CompressedFormat>, width: usize, height: usize) -> usize {
+    match format {
+        Some(format) => format.data_len(width, height),
+        None => width * height * 4,
+    }
+}
+
+#[test]
+fn test_data_len() {
+    assert_eq!(CompressedFormat::Bc1.data_len(256, 256), 64 * 64 * 8);
+    assert_eq!(CompressedFormat::Bc3.data_len(4, 4), 16);
+    assert_eq!(CompressedFormat::Bc7.data_len(5, 1), 2 * 16);
+}
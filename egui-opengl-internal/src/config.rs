@@ -0,0 +1,163 @@
+use crate::Error;
+use egui::{Context, Id};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "parking-lot")]
+use parking_lot::Mutex;
+#[cfg(feature = "spin-lock")]
+use spin::lock_api::Mutex;
+
+/// Settings of the integration itself, stored as `key = value` lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Config {
+    /// Hide sensitive information while the user is recording or streaming.
+    pub streamer_mode: bool,
+}
+
+impl Config {
+    /// Reads the config at `path`, a missing file yields the defaults.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::Config(e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.serialize()).map_err(Error::Config)
+    }
+
+    /// Unknown keys and malformed values are ignored.
+    fn parse(text: &str) -> Self {
+        let mut config = Self::default();
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            if key.trim() == "streamer_mode" {
+                if let Ok(value) = value.trim().parse() {
+                    config.streamer_mode = value;
+                }
+            }
+        }
+
+        config
+    }
+
+    fn serialize(&self) -> String {
+        format!("streamer_mode = {}\n", self.streamer_mode)
+    }
+}
+
+/// Lock-free home of the [`Config`], so it can be read and changed from within the UI closure.
+pub struct Settings {
+    streamer_mode: AtomicBool,
+    window_open: AtomicBool,
+    path: Mutex<Option<PathBuf>>,
+}
+
+impl Settings {
+    pub const fn new() -> Self {
+        Self {
+            streamer_mode: AtomicBool::new(false),
+            window_open: AtomicBool::new(false),
+            path: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        Config {
+            streamer_mode: self.streamer_mode(),
+        }
+    }
+
+    /// Loads the config at `path` and saves every later change to it.
+    pub fn set_path(&self, path: PathBuf) -> Result<(), Error> {
+        let config = Config::load(&path)?;
+        self.streamer_mode
+            .store(config.streamer_mode, Ordering::Release);
+        *self.path.lock() = Some(path);
+
+        Ok(())
+    }
+
+    pub fn streamer_mode(&self) -> bool {
+        self.streamer_mode.load(Ordering::Acquire)
+    }
+
+    pub fn set_streamer_mode(&self, enabled: bool) {
+        if self.streamer_mode.swap(enabled, Ordering::AcqRel) != enabled {
+            self.save();
+        }
+    }
+
+    pub fn set_window_open(&self, open: bool) {
+        self.window_open.store(open, Ordering::Release);
+    }
+
+    /// Saves the config if a path was set. Failures are only logged, the setting still applies.
+    fn save(&self) {
+        if let Some(path) = self.path.lock().as_ref() {
+            if let Err(e) = self.config().save(path) {
+                log!("Failed to save {}: {e}", path.display());
+            }
+        }
+    }
+
+    /// Publishes the settings to `ctx` for [`streamer_mode`], call before the UI closure runs.
+    pub fn publish(&self, ctx: &Context) {
+        let streamer_mode = self.streamer_mode();
+        ctx.data_mut(|data| data.insert_temp(streamer_mode_id(), streamer_mode));
+    }
+
+    /// Draws the settings window if it's open.
+    pub fn show_window(&self, ctx: &Context) {
+        let mut streamer_mode = self.streamer_mode();
+        let mut open = self.window_open.load(Ordering::Acquire);
+        if !open {
+            return;
+        }
+
+        egui::Window::new("Overlay settings")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut streamer_mode, "Streamer mode")
+                    .on_hover_text("Hide sensitive information while recording or streaming");
+            });
+
+        self.set_window_open(open);
+        self.set_streamer_mode(streamer_mode);
+    }
+}
+
+fn streamer_mode_id() -> Id {
+    Id::new("egui-opengl-internal-streamer-mode")
+}
+
+/// Returns `true` if streamer mode is on, see [`crate::OpenGLApp::set_streamer_mode`].
+/// For widgets that only have access to the egui context.
+pub fn streamer_mode(ctx: &Context) -> bool {
+    ctx.data(|data| data.get_temp(streamer_mode_id()))
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_config_roundtrip() {
+    let config = Config {
+        streamer_mode: true,
+    };
+    assert_eq!(Config::parse(&config.serialize()), config);
+    assert_eq!(
+        Config::parse("# comment\nstreamer_mode = maybe\nunknown = 1"),
+        Config::default()
+    );
+}
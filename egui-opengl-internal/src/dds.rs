@@ -0,0 +1,244 @@
+use crate::{
+    compressed::{level_len, level_size},
+    CompressedFormat,
+};
+use thiserror::Error;
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_LEN: usize = 124;
+const DX10_HEADER_LEN: usize = 20;
+
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+
+const DXGI_FORMAT_R8G8B8A8_UNORM: u32 = 28;
+const DXGI_FORMAT_R8G8B8A8_UNORM_SRGB: u32 = 29;
+const DXGI_FORMAT_BC1_UNORM: u32 = 71;
+const DXGI_FORMAT_BC1_UNORM_SRGB: u32 = 72;
+const DXGI_FORMAT_BC3_UNORM: u32 = 77;
+const DXGI_FORMAT_BC3_UNORM_SRGB: u32 = 78;
+const DXGI_FORMAT_B8G8R8A8_UNORM: u32 = 87;
+const DXGI_FORMAT_B8G8R8A8_UNORM_SRGB: u32 = 91;
+const DXGI_FORMAT_BC7_UNORM: u32 = 98;
+const DXGI_FORMAT_BC7_UNORM_SRGB: u32 = 99;
+
+#[derive(Debug, Error)]
+pub enum DdsError {
+    #[error("failed to read DDS file: {0}")]
+    Io(std::io::Error),
+
+    #[error("not a DDS file")]
+    InvalidHeader,
+
+    #[error("unsupported DDS pixel format {0}")]
+    UnsupportedFormat(String),
+
+    #[error("DDS file is shorter than its header describes")]
+    Truncated,
+}
+
+/// Decoded `.dds` file, see [`crate::Painter::texture_from_dds`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DdsImage {
+    pub size: (usize, usize),
+    /// `None` for uncompressed images, whose `data` is converted to RGBA.
+    pub format: Option<CompressedFormat>,
+    /// Number of mip levels stored in `data`, at least `1`.
+    pub mip_levels: u32,
+    /// Every mip level, largest first.
+    pub data: Vec<u8>,
+}
+
+enum PixelLayout {
+    Compressed(CompressedFormat),
+    Rgba,
+    Bgra,
+    /// BGR with an unused fourth byte.
+    Bgrx,
+}
+
+impl DdsImage {
+    pub fn parse(bytes: &[u8]) -> Result<Self, DdsError> {
+        if bytes.len() < 4 + HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(DdsError::InvalidHeader);
+        }
+
+        let header = &bytes[4..4 + HEADER_LEN];
+        let field = |offset: usize| {
+            u32::from_le_bytes(
+                header[offset..offset + 4]
+                    .try_into()
+                    .expect("Header fields are 4 bytes long"),
+            )
+        };
+
+        if field(0) as usize != HEADER_LEN {
+            return Err(DdsError::InvalidHeader);
+        }
+
+        let size = (field(12) as usize, field(8) as usize);
+        let mip_levels = match field(4) & DDSD_MIPMAPCOUNT {
+            0 => 1,
+            _ => field(24).max(1),
+        };
+
+        let (pf_flags, four_cc) = (field(76), field(80));
+        let mut data_start = 4 + HEADER_LEN;
+
+        let layout = if pf_flags & DDPF_FOURCC != 0 {
+            match &four_cc.to_le_bytes() {
+                b"DXT1" => PixelLayout::Compressed(CompressedFormat::Bc1),
+                b"DXT5" => PixelLayout::Compressed(CompressedFormat::Bc3),
+                b"DX10" => {
+                    let dx10 = bytes
+                        .get(data_start..data_start + DX10_HEADER_LEN)
+                        .ok_or(DdsError::Truncated)?;
+                    data_start += DX10_HEADER_LEN;
+
+                    let dxgi_format = u32::from_le_bytes(
+                        dx10[..4]
+                            .try_into()
+                            .expect("Header fields are 4 bytes long"),
+                    );
+                    match dxgi_format {
+                        DXGI_FORMAT_BC1_UNORM | DXGI_FORMAT_BC1_UNORM_SRGB => {
+                            PixelLayout::Compressed(CompressedFormat::Bc1)
+                        }
+                        DXGI_FORMAT_BC3_UNORM | DXGI_FORMAT_BC3_UNORM_SRGB => {
+                            PixelLayout::Compressed(CompressedFormat::Bc3)
+                        }
+                        DXGI_FORMAT_BC7_UNORM | DXGI_FORMAT_BC7_UNORM_SRGB => {
+                            PixelLayout::Compressed(CompressedFormat::Bc7)
+                        }
+                        DXGI_FORMAT_R8G8B8A8_UNORM | DXGI_FORMAT_R8G8B8A8_UNORM_SRGB => {
+                            PixelLayout::Rgba
+                        }
+                        DXGI_FORMAT_B8G8R8A8_UNORM | DXGI_FORMAT_B8G8R8A8_UNORM_SRGB => {
+                            PixelLayout::Bgra
+                        }
+                        other => return Err(DdsError::UnsupportedFormat(format!("DXGI {other}"))),
+                    }
+                }
+                other => {
+                    return Err(DdsError::UnsupportedFormat(
+                        String::from_utf8_lossy(other).into_owned(),
+                    ))
+                }
+            }
+        } else if pf_flags & DDPF_RGB != 0 && field(84) == 32 {
+            let alpha = pf_flags & DDPF_ALPHAPIXELS != 0;
+            match (field(88), alpha) {
+                (0xff, true) => PixelLayout::Rgba,
+                (0xff0000, true) => PixelLayout::Bgra,
+                (0xff0000, false) => PixelLayout::Bgrx,
+                (mask, _) => {
+                    return Err(DdsError::UnsupportedFormat(format!(
+                        "32-bit RGB with red mask {mask:#x}"
+                    )))
+                }
+            }
+        } else {
+            return Err(DdsError::UnsupportedFormat(format!(
+                "pixel format flags {pf_flags:#x}"
+            )));
+        };
+
+        let format = match layout {
+            PixelLayout::Compressed(format) => Some(format),
+            _ => None,
+        };
+
+        // Keep only the levels that are actually present, some tools write a larger mip count.
+        let body = &bytes[data_start..];
+        let mut len = 0;
+        let mut levels = 0;
+        for level in 0..mip_levels {
+            let (width, height) = level_size(size, level);
+            let level_len = level_len(format, width, height);
+            if len + level_len > body.len() {
+                break;
+            }
+            len += level_len;
+            levels += 1;
+        }
+
+        if levels == 0 {
+            return Err(DdsError::Truncated);
+        }
+
+        let mut data = body[..len].to_vec();
+        match layout {
+            PixelLayout::Compressed(_) | PixelLayout::Rgba => {}
+            PixelLayout::Bgra | PixelLayout::Bgrx => {
+                for pixel in data.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                    if matches!(layout, PixelLayout::Bgrx) {
+                        pixel[3] = 255;
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            size,
+            format,
+            mip_levels: levels,
+            data,
+        })
+    }
+}
+
+#[test]
+fn test_parse_dds() {
+    fn header(flags: u32, mips: u32, pf_flags: u32, four_cc: &[u8; 4], red_mask: u32) -> Vec<u8> {
+        let mut header = [0u32; HEADER_LEN / 4];
+        header[0] = HEADER_LEN as _;
+        header[1] = flags;
+        header[2] = 4;
+        header[3] = 8;
+        header[6] = mips;
+        header[18] = 32;
+        header[19] = pf_flags;
+        header[20] = u32::from_le_bytes(*four_cc);
+        header[21] = 32;
+        header[22] = red_mask;
+
+        MAGIC
+            .iter()
+            .copied()
+            .chain(header.iter().flat_map(|field| field.to_le_bytes()))
+            .collect()
+    }
+
+    // 8x4 BC1 declaring 5 mip levels, of which only 3 are present.
+    let mut bytes = header(DDSD_MIPMAPCOUNT, 5, DDPF_FOURCC, b"DXT1", 0);
+    bytes.extend([0; 16 + 8 + 8]);
+    let image = DdsImage::parse(&bytes).unwrap();
+    assert_eq!(image.size, (8, 4));
+    assert_eq!(image.format, Some(CompressedFormat::Bc1));
+    assert_eq!(image.mip_levels, 3);
+    assert_eq!(image.data.len(), 32);
+
+    // BGRA is swizzled to RGBA.
+    let mut bytes = header(0, 0, DDPF_RGB | DDPF_ALPHAPIXELS, &[0; 4], 0xff0000);
+    bytes.extend([1, 2, 3, 4].repeat(8 * 4));
+    let image = DdsImage::parse(&bytes).unwrap();
+    assert_eq!(image.format, None);
+    assert_eq!(image.mip_levels, 1);
+    assert_eq!(&image.data[..4], &[3, 2, 1, 4]);
+
+    assert!(matches!(
+        DdsImage::parse(&header(0, 0, DDPF_FOURCC, b"DXT3", 0)),
+        Err(DdsError::UnsupportedFormat(_))
+    ));
+    assert!(matches!(
+        DdsImage::parse(&header(0, 0, DDPF_FOURCC, b"DXT1", 0)),
+        Err(DdsError::Truncated)
+    ));
+    assert!(matches!(
+        DdsImage::parse(b"PNG"),
+        Err(DdsError::InvalidHeader)
+    ));
+}
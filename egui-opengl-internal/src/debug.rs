@@ -0,0 +1,80 @@
+//! `KHR_debug` integration, enabled with the `gl-debug` feature. Driver messages for the overlay's context
+//! are logged and every UI pass is wrapped in a debug group, so apitrace and RenderDoc attribute them to the overlay.
+
+use gl::types::*;
+use std::ffi::{c_void, CStr};
+
+const GROUP_NAME: &str = "egui overlay";
+
+/// Logs the driver messages of the current context. Does nothing without `KHR_debug`.
+pub(crate) unsafe fn install() {
+    if !gl::DebugMessageCallback::is_loaded() {
+        log!("KHR_debug is not supported, driver messages won't be logged");
+        return;
+    }
+
+    gl::Enable(gl::DEBUG_OUTPUT);
+    // Messages are reported on the thread and in the call that caused them.
+    gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+    gl::DebugMessageCallback(Some(on_message), std::ptr::null());
+}
+
+extern "system" fn on_message(
+    source: GLenum,
+    ty: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _: *mut c_void,
+) {
+    // Group markers are our own, notifications are mostly buffer placement hints.
+    if ty == gl::DEBUG_TYPE_PUSH_GROUP
+        || ty == gl::DEBUG_TYPE_POP_GROUP
+        || severity == gl::DEBUG_SEVERITY_NOTIFICATION
+    {
+        return;
+    }
+
+    let message = match length {
+        0.. => unsafe { std::slice::from_raw_parts(message as *const u8, length as usize) },
+        _ => unsafe { CStr::from_ptr(message) }.to_bytes(),
+    };
+    let severity = match severity {
+        gl::DEBUG_SEVERITY_HIGH => "high",
+        gl::DEBUG_SEVERITY_MEDIUM => "medium",
+        _ => "low",
+    };
+
+    log!(
+        "GL debug message {id} from source {source:#x}, type {ty:#x}, {severity} severity: {}",
+        String::from_utf8_lossy(message)
+    );
+}
+
+/// Debug group around a UI pass, popped when dropped.
+pub(crate) struct DebugGroup(bool);
+
+impl DebugGroup {
+    pub unsafe fn push() -> Self {
+        let supported = gl::PushDebugGroup::is_loaded();
+        if supported {
+            gl::PushDebugGroup(
+                gl::DEBUG_SOURCE_APPLICATION,
+                0,
+                GROUP_NAME.len() as _,
+                GROUP_NAME.as_ptr() as *const GLchar,
+            );
+        }
+
+        Self(supported)
+    }
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        if self.0 {
+            unsafe { gl::PopDebugGroup() };
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use thiserror::Error;
+
+/// Errors reported by [`crate::OpenGLApp`].
+/// Render-time errors are delivered to the handler set with [`crate::OpenGLApp::set_error_handler`].
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("the app was initialized already")]
+    AlreadyInitialized,
+
+    #[error("the app is not initialized")]
+    NotInitialized,
+
+    #[error("invalid window handle")]
+    InvalidWindow,
+
+    #[error("failed to create OpenGL context: {0}")]
+    CreateContext(windows::core::Error),
+
+    #[error("failed to make OpenGL context current: {0}")]
+    MakeCurrent(windows::core::Error),
+
+    #[error("the game has no OpenGL context current to paint with")]
+    NoGameContext,
+
+    #[error("failed to attach to the window's messages: {0}")]
+    AttachWndProc(windows::core::Error),
+
+    #[error("clipboard operation failed: {0}")]
+    Clipboard(windows::core::Error),
+
+    #[error("failed to access config: {0}")]
+    Config(std::io::Error),
+
+    #[error("the painter is created with the first frame, none was rendered yet")]
+    NoPainter,
+
+    #[error("texture {0:?} does not exist")]
+    UnknownTexture(egui::TextureId),
+
+    #[error("expected {expected} pixels for the texture, got {actual}")]
+    TextureSize { expected: usize, actual: usize },
+
+    #[error("failed to build shaders: {0}")]
+    Shader(crate::ShaderError),
+
+    #[error("compositing is unavailable, the UI is painted at the window's size instead of the render resolution")]
+    RenderResolution,
+
+    #[cfg(feature = "overlay")]
+    #[error("another app or overlay is attached already")]
+    AlreadyAttached,
+
+    #[cfg(feature = "overlay")]
+    #[error("failed to hook the present functions: {0}")]
+    Hook(retour::Error),
+
+    #[cfg(feature = "overlay")]
+    #[error("the game didn't present in time to release the app")]
+    DetachTimeout,
+
+    #[cfg(feature = "persistence")]
+    #[error("failed to access egui memory: {0}")]
+    Memory(std::io::Error),
+
+    #[cfg(feature = "persistence")]
+    #[error("failed to access the persisted state: {0}")]
+    State(std::io::Error),
+
+    #[cfg(feature = "image")]
+    #[error("failed to load image: {0}")]
+    Image(image::ImageError),
+
+    #[cfg(feature = "dds")]
+    #[error("failed to load DDS texture: {0}")]
+    Dds(crate::DdsError),
+}
@@ -0,0 +1,22 @@
+use windows::Win32::Foundation::HWND;
+
+/// Notifications emitted by [`crate::OpenGLApp`] about changes in its environment.
+/// Register a handler with [`crate::OpenGLApp::set_event_handler`] to receive them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppEvent {
+    /// The window the app was drawing to has been destroyed.
+    /// Rendering and input processing are paused until a new window is found.
+    WindowLost(HWND),
+    /// Rendering resumed on a new window after [`AppEvent::WindowLost`].
+    WindowRestored(HWND),
+    /// The game replaced its OpenGL context or presented to a device context with another pixel format,
+    /// and the overlay's context and painter were rebuilt.
+    /// Textures registered with [`crate::OpenGLApp::register_texture`] are lost and need to be registered again.
+    ContextRecreated,
+    /// The window moved to a monitor with another DPI, carried by the event. Unless overridden with
+    /// [`crate::OpenGLApp::set_pixels_per_point`], egui renders at the new scale from the next frame.
+    DpiChanged(u32),
+    /// The UI closure or a layer panicked. The panic was caught and the UI replaced with a window
+    /// showing it, until the user resumes the UI from there. Builds with `panic = "abort"` abort instead.
+    UiPanicked,
+}
@@ -0,0 +1,213 @@
+//! XInput controller navigation, enabled with the `gamepad` feature. The D-pad and left stick move
+//! egui's keyboard focus, A activates the focused widget and B drops the focus.
+
+use egui::{Event, Key, Modifiers};
+use std::time::{Duration, Instant};
+use windows::Win32::{
+    Foundation::ERROR_SUCCESS,
+    UI::Input::XboxController::{
+        XInputGetState, XINPUT_GAMEPAD_A, XINPUT_GAMEPAD_B, XINPUT_GAMEPAD_DPAD_DOWN,
+        XINPUT_GAMEPAD_DPAD_LEFT, XINPUT_GAMEPAD_DPAD_RIGHT, XINPUT_GAMEPAD_DPAD_UP,
+        XINPUT_GAMEPAD_LEFT_SHOULDER, XINPUT_GAMEPAD_LEFT_THUMB_DEADZONE,
+        XINPUT_GAMEPAD_RIGHT_SHOULDER, XINPUT_STATE, XUSER_MAX_COUNT,
+    },
+};
+
+/// Delay before a held direction starts repeating, and the interval it repeats at.
+const REPEAT_DELAY: Duration = Duration::from_millis(400);
+const REPEAT_INTERVAL: Duration = Duration::from_millis(120);
+/// `XInputGetState` on an empty slot is slow, so disconnected controllers are looked for at most this often.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+const DPAD: u16 = XINPUT_GAMEPAD_DPAD_UP.0
+    | XINPUT_GAMEPAD_DPAD_DOWN.0
+    | XINPUT_GAMEPAD_DPAD_LEFT.0
+    | XINPUT_GAMEPAD_DPAD_RIGHT.0;
+
+pub(crate) struct GamepadNavigation {
+    /// Slot of the controller in use, `None` while none is connected.
+    user: Option<u32>,
+    last_scan: Option<Instant>,
+    buttons: u16,
+    /// Direction being held and when it repeats next.
+    repeat: Option<(u16, Instant)>,
+}
+
+impl GamepadNavigation {
+    pub fn new() -> Self {
+        Self {
+            user: None,
+            last_scan: None,
+            buttons: 0,
+            repeat: None,
+        }
+    }
+
+    /// Reads the controller and appends the key presses it translates to.
+    /// `has_focus` tells whether a widget has the keyboard focus, directions only move an existing focus.
+    pub fn poll(&mut self, has_focus: bool, events: &mut Vec<Event>) {
+        let now = Instant::now();
+        let buttons = match self.read(now) {
+            Some(state) => buttons(&state),
+            None => 0,
+        };
+
+        self.update(buttons, has_focus, now, events);
+    }
+
+    fn read(&mut self, now: Instant) -> Option<XINPUT_STATE> {
+        let mut state = XINPUT_STATE::default();
+        if let Some(user) = self.user {
+            if unsafe { XInputGetState(user, &mut state) } == ERROR_SUCCESS.0 {
+                return Some(state);
+            }
+
+            log!("Controller {user} disconnected");
+            self.user = None;
+        }
+
+        if self
+            .last_scan
+            .is_some_and(|last| now.duration_since(last) < RESCAN_INTERVAL)
+        {
+            return None;
+        }
+        self.last_scan = Some(now);
+
+        let user = (0..XUSER_MAX_COUNT)
+            .find(|&user| unsafe { XInputGetState(user, &mut state) } == ERROR_SUCCESS.0)?;
+        log!("Navigating with controller {user}");
+        self.user = Some(user);
+        Some(state)
+    }
+
+    fn update(&mut self, buttons: u16, has_focus: bool, now: Instant, events: &mut Vec<Event>) {
+        let pressed = buttons & !self.buttons;
+        self.buttons = buttons;
+
+        let direction = buttons & DPAD;
+        let repeated = match self.repeat {
+            Some((held, at)) if held == direction && now >= at => {
+                self.repeat = Some((held, at + REPEAT_INTERVAL));
+                direction
+            }
+            Some((held, _)) if held == direction => 0,
+            _ => {
+                self.repeat = (direction != 0).then_some((direction, now + REPEAT_DELAY));
+                pressed & DPAD
+            }
+        };
+
+        for (button, key) in [
+            (XINPUT_GAMEPAD_DPAD_UP.0, Key::ArrowUp),
+            (XINPUT_GAMEPAD_DPAD_DOWN.0, Key::ArrowDown),
+            (XINPUT_GAMEPAD_DPAD_LEFT.0, Key::ArrowLeft),
+            (XINPUT_GAMEPAD_DPAD_RIGHT.0, Key::ArrowRight),
+        ] {
+            if repeated & button == 0 {
+                continue;
+            }
+
+            // Arrows only move an existing focus, tabbing gives the first widget focus.
+            if has_focus {
+                push_key(events, key, Modifiers::NONE);
+            } else {
+                let backwards = matches!(key, Key::ArrowUp | Key::ArrowLeft);
+                push_key(events, Key::Tab, shift(backwards));
+            }
+        }
+
+        for (button, key, modifiers) in [
+            (XINPUT_GAMEPAD_A.0, Key::Enter, Modifiers::NONE),
+            (XINPUT_GAMEPAD_B.0, Key::Escape, Modifiers::NONE),
+            (XINPUT_GAMEPAD_LEFT_SHOULDER.0, Key::Tab, shift(true)),
+            (XINPUT_GAMEPAD_RIGHT_SHOULDER.0, Key::Tab, shift(false)),
+        ] {
+            if pressed & button != 0 {
+                push_key(events, key, modifiers);
+            }
+        }
+    }
+}
+
+/// Buttons of the controller, with the left stick folded into the D-pad.
+fn buttons(state: &XINPUT_STATE) -> u16 {
+    let gamepad = &state.Gamepad;
+    let deadzone = XINPUT_GAMEPAD_LEFT_THUMB_DEADZONE.0 as i32;
+    let (x, y) = (gamepad.sThumbLX as i32, gamepad.sThumbLY as i32);
+
+    let mut buttons = gamepad.wButtons.0;
+    if x.abs() > deadzone || y.abs() > deadzone {
+        // Only the dominant axis counts, diagonals would move the focus twice.
+        buttons |= match (x.abs() > y.abs(), x > 0, y > 0) {
+            (true, true, _) => XINPUT_GAMEPAD_DPAD_RIGHT.0,
+            (true, false, _) => XINPUT_GAMEPAD_DPAD_LEFT.0,
+            (false, _, true) => XINPUT_GAMEPAD_DPAD_UP.0,
+            (false, _, false) => XINPUT_GAMEPAD_DPAD_DOWN.0,
+        };
+    }
+
+    buttons
+}
+
+fn shift(shift: bool) -> Modifiers {
+    Modifiers {
+        shift,
+        ..Modifiers::NONE
+    }
+}
+
+fn push_key(events: &mut Vec<Event>, key: Key, modifiers: Modifiers) {
+    for pressed in [true, false] {
+        events.push(Event::Key {
+            key,
+            physical_key: None,
+            pressed,
+            repeat: false,
+            modifiers,
+        });
+    }
+}
+
+#[test]
+fn test_gamepad_navigation() {
+    fn pressed_keys(events: &mut Vec<Event>) -> Vec<(Key, bool)> {
+        events
+            .drain(..)
+            .filter_map(|event| match event {
+                Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } => Some((key, modifiers.shift)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    let mut nav = GamepadNavigation::new();
+    let mut events = vec![];
+    let start = Instant::now();
+    let down = XINPUT_GAMEPAD_DPAD_DOWN.0;
+
+    // Without a focused widget directions tab into the UI.
+    nav.update(down, false, start, &mut events);
+    assert_eq!(pressed_keys(&mut events), [(Key::Tab, false)]);
+
+    // Held directions repeat after the delay.
+    nav.update(down, true, start + REPEAT_DELAY / 2, &mut events);
+    assert!(events.is_empty());
+    nav.update(down, true, start + REPEAT_DELAY, &mut events);
+    assert_eq!(pressed_keys(&mut events), [(Key::ArrowDown, false)]);
+    nav.update(down, true, start + REPEAT_DELAY, &mut events);
+    assert!(events.is_empty());
+
+    // Buttons fire once per press.
+    let a = XINPUT_GAMEPAD_A.0;
+    nav.update(a, true, start, &mut events);
+    nav.update(a, true, start, &mut events);
+    assert_eq!(pressed_keys(&mut events), [(Key::Enter, false)]);
+    nav.update(XINPUT_GAMEPAD_LEFT_SHOULDER.0, true, start, &mut events);
+    assert_eq!(pressed_keys(&mut events), [(Key::Tab, true)]);
+}
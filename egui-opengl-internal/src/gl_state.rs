@@ -0,0 +1,218 @@
+use gl::types::*;
+
+/// Snapshot of the OpenGL state the painter touches, see [`GlStateGuard`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlState {
+    pub framebuffer_srgb: bool,
+    pub scissor_test: bool,
+    pub blend: bool,
+    pub depth_test: bool,
+    pub stencil_test: bool,
+    pub cull_face: bool,
+    pub blend_src_rgb: GLint,
+    pub blend_dst_rgb: GLint,
+    pub blend_src_alpha: GLint,
+    pub blend_dst_alpha: GLint,
+    pub blend_equation_rgb: GLint,
+    pub blend_equation_alpha: GLint,
+    pub program: GLint,
+    pub active_texture: GLint,
+    /// Texture bound to `GL_TEXTURE_2D` of texture unit 0.
+    pub texture_2d: GLint,
+    /// `None` on contexts without vertex array objects.
+    pub vertex_array: Option<GLint>,
+    pub array_buffer: GLint,
+    /// Part of the vertex array object's state when there is one.
+    pub element_array_buffer: GLint,
+    pub draw_framebuffer: GLint,
+    pub read_framebuffer: GLint,
+    pub viewport: [GLint; 4],
+    pub scissor_box: [GLint; 4],
+    pub unpack_alignment: GLint,
+    pub unpack_row_length: GLint,
+    pub unpack_skip_pixels: GLint,
+    pub unpack_skip_rows: GLint,
+    /// `None` on contexts without pixel buffer objects.
+    pub pixel_unpack_buffer: Option<GLint>,
+}
+
+impl GlState {
+    /// Reads the state from the current context.
+    pub unsafe fn capture() -> Self {
+        let mut active_texture = 0;
+        gl::GetIntegerv(gl::ACTIVE_TEXTURE, &mut active_texture);
+        gl::ActiveTexture(gl::TEXTURE0);
+        let texture_2d = get_integer(gl::TEXTURE_BINDING_2D);
+        gl::ActiveTexture(active_texture as _);
+
+        let mut viewport = [0; 4];
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        let mut scissor_box = [0; 4];
+        gl::GetIntegerv(gl::SCISSOR_BOX, scissor_box.as_mut_ptr());
+
+        Self {
+            framebuffer_srgb: gl::IsEnabled(gl::FRAMEBUFFER_SRGB) == gl::TRUE,
+            scissor_test: gl::IsEnabled(gl::SCISSOR_TEST) == gl::TRUE,
+            blend: gl::IsEnabled(gl::BLEND) == gl::TRUE,
+            depth_test: gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE,
+            stencil_test: gl::IsEnabled(gl::STENCIL_TEST) == gl::TRUE,
+            cull_face: gl::IsEnabled(gl::CULL_FACE) == gl::TRUE,
+            blend_src_rgb: get_integer(gl::BLEND_SRC_RGB),
+            blend_dst_rgb: get_integer(gl::BLEND_DST_RGB),
+            blend_src_alpha: get_integer(gl::BLEND_SRC_ALPHA),
+            blend_dst_alpha: get_integer(gl::BLEND_DST_ALPHA),
+            blend_equation_rgb: get_integer(gl::BLEND_EQUATION_RGB),
+            blend_equation_alpha: get_integer(gl::BLEND_EQUATION_ALPHA),
+            program: get_integer(gl::CURRENT_PROGRAM),
+            active_texture,
+            texture_2d,
+            vertex_array: gl::BindVertexArray::is_loaded()
+                .then(|| get_integer(gl::VERTEX_ARRAY_BINDING)),
+            array_buffer: get_integer(gl::ARRAY_BUFFER_BINDING),
+            element_array_buffer: get_integer(gl::ELEMENT_ARRAY_BUFFER_BINDING),
+            draw_framebuffer: get_integer(gl::DRAW_FRAMEBUFFER_BINDING),
+            read_framebuffer: get_integer(gl::READ_FRAMEBUFFER_BINDING),
+            viewport,
+            scissor_box,
+            unpack_alignment: get_integer(gl::UNPACK_ALIGNMENT),
+            unpack_row_length: get_integer(gl::UNPACK_ROW_LENGTH),
+            unpack_skip_pixels: get_integer(gl::UNPACK_SKIP_PIXELS),
+            unpack_skip_rows: get_integer(gl::UNPACK_SKIP_ROWS),
+            pixel_unpack_buffer: gl::FenceSync::is_loaded()
+                .then(|| get_integer(gl::PIXEL_UNPACK_BUFFER_BINDING)),
+        }
+    }
+
+    /// Makes the snapshot the state of the current context again.
+    pub unsafe fn restore(&self) {
+        set_enabled(gl::FRAMEBUFFER_SRGB, self.framebuffer_srgb);
+        set_enabled(gl::SCISSOR_TEST, self.scissor_test);
+        set_enabled(gl::BLEND, self.blend);
+        set_enabled(gl::DEPTH_TEST, self.depth_test);
+        set_enabled(gl::STENCIL_TEST, self.stencil_test);
+        set_enabled(gl::CULL_FACE, self.cull_face);
+        gl::BlendFuncSeparate(
+            self.blend_src_rgb as _,
+            self.blend_dst_rgb as _,
+            self.blend_src_alpha as _,
+            self.blend_dst_alpha as _,
+        );
+        gl::BlendEquationSeparate(self.blend_equation_rgb as _, self.blend_equation_alpha as _);
+
+        gl::UseProgram(self.program as _);
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, self.texture_2d as _);
+        gl::ActiveTexture(self.active_texture as _);
+        if let Some(vertex_array) = self.vertex_array {
+            gl::BindVertexArray(vertex_array as _);
+        }
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.array_buffer as _);
+        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.element_array_buffer as _);
+
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.draw_framebuffer as _);
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.read_framebuffer as _);
+
+        let [x, y, w, h] = self.viewport;
+        gl::Viewport(x, y, w, h);
+        let [x, y, w, h] = self.scissor_box;
+        gl::Scissor(x, y, w, h);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, self.unpack_alignment);
+        gl::PixelStorei(gl::UNPACK_ROW_LENGTH, self.unpack_row_length);
+        gl::PixelStorei(gl::UNPACK_SKIP_PIXELS, self.unpack_skip_pixels);
+        gl::PixelStorei(gl::UNPACK_SKIP_ROWS, self.unpack_skip_rows);
+        if let Some(pixel_unpack_buffer) = self.pixel_unpack_buffer {
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, pixel_unpack_buffer as _);
+        }
+    }
+
+    /// Describes every field that differs between `self` and `other`, e.g. `"blend: false -> true"`.
+    pub fn diff(&self, other: &Self) -> Vec<String> {
+        let mut out = vec![];
+
+        macro_rules! diff_fields {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field != other.$field {
+                        out.push(format!(
+                            "{}: {:?} -> {:?}",
+                            stringify!($field),
+                            self.$field,
+                            other.$field
+                        ));
+                    }
+                )*
+            };
+        }
+
+        diff_fields!(
+            framebuffer_srgb,
+            scissor_test,
+            blend,
+            depth_test,
+            stencil_test,
+            cull_face,
+            blend_src_rgb,
+            blend_dst_rgb,
+            blend_src_alpha,
+            blend_dst_alpha,
+            blend_equation_rgb,
+            blend_equation_alpha,
+            program,
+            active_texture,
+            texture_2d,
+            vertex_array,
+            array_buffer,
+            element_array_buffer,
+            draw_framebuffer,
+            read_framebuffer,
+            viewport,
+            scissor_box,
+            unpack_alignment,
+            unpack_row_length,
+            unpack_skip_pixels,
+            unpack_skip_rows,
+            pixel_unpack_buffer
+        );
+
+        out
+    }
+}
+
+/// Captures the [`GlState`] when created and restores it when dropped,
+/// so the game's next frame starts with the state it left behind.
+pub struct GlStateGuard {
+    state: GlState,
+}
+
+impl GlStateGuard {
+    pub unsafe fn new() -> Self {
+        Self {
+            state: GlState::capture(),
+        }
+    }
+
+    /// State that is restored on drop.
+    pub fn state(&self) -> &GlState {
+        &self.state
+    }
+}
+
+impl Drop for GlStateGuard {
+    fn drop(&mut self) {
+        unsafe { self.state.restore() };
+    }
+}
+
+unsafe fn set_enabled(cap: GLenum, enabled: bool) {
+    if enabled {
+        gl::Enable(cap);
+    } else {
+        gl::Disable(cap);
+    }
+}
+
+unsafe fn get_integer(name: GLenum) -> GLint {
+    let mut value = 0;
+    gl::GetIntegerv(name, &mut value);
+    value
+}
@@ -0,0 +1,119 @@
+use std::ffi::CStr;
+
+/// Version and profile of the current OpenGL context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlVersion {
+    pub major: u32,
+    pub minor: u32,
+    /// Set for core-profile contexts, which lack the deprecated fixed-function and client-side array features.
+    pub core_profile: bool,
+    /// Set for OpenGL ES contexts, e.g. through ANGLE.
+    pub es: bool,
+}
+
+impl GlVersion {
+    /// Reads the version of the current context, `None` if no context is current.
+    pub fn detect() -> Option<Self> {
+        let version = unsafe { gl::GetString(gl::VERSION) };
+        if version.is_null() {
+            return None;
+        }
+
+        let version = unsafe { CStr::from_ptr(version.cast()) }.to_string_lossy();
+        let mut parsed = Self::parse(&version)?;
+
+        if !parsed.es && parsed.at_least(3, 2) {
+            let mut mask = 0;
+            unsafe { gl::GetIntegerv(gl::CONTEXT_PROFILE_MASK, &mut mask) };
+            parsed.core_profile = mask as u32 & gl::CONTEXT_CORE_PROFILE_BIT != 0;
+        }
+
+        Some(parsed)
+    }
+
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self {
+            major,
+            minor,
+            core_profile: false,
+            es: false,
+        }
+    }
+
+    /// Parses a `GL_VERSION` string, e.g. `"4.6.0 NVIDIA 537.13"` or `"OpenGL ES 3.2 Mesa 23.1"`.
+    /// The profile can't be told from the string alone and is reported as compatibility.
+    pub fn parse(version: &str) -> Option<Self> {
+        let (es, version) = match version.strip_prefix("OpenGL ES") {
+            Some(rest) => (true, rest.trim_start_matches(['-', 'C', 'M']).trim_start()),
+            None => (false, version),
+        };
+
+        let number = version.split_whitespace().next()?;
+        let mut parts = number.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+
+        Some(Self {
+            es,
+            ..Self::new(major, minor)
+        })
+    }
+
+    /// Whether the context supports at least OpenGL `major.minor`, ignoring the profile.
+    pub fn at_least(&self, major: u32, minor: u32) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+}
+
+impl std::fmt::Display for GlVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let api = if self.es { "OpenGL ES" } else { "OpenGL" };
+        write!(f, "{api} {}.{}", self.major, self.minor)?;
+        if self.core_profile {
+            write!(f, " core")?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_version() {
+    assert_eq!(
+        GlVersion::parse("4.6.0 NVIDIA 537.13"),
+        Some(GlVersion::new(4, 6))
+    );
+    assert_eq!(
+        GlVersion::parse("3.3 (Core Profile) Mesa 23.1.4"),
+        Some(GlVersion::new(3, 3))
+    );
+    assert_eq!(
+        GlVersion::parse("OpenGL ES 3.0 (ANGLE 2.1)"),
+        Some(GlVersion {
+            es: true,
+            ..GlVersion::new(3, 0)
+        })
+    );
+    assert_eq!(GlVersion::parse("garbage"), None);
+}
+
+/// Checks whether the current context advertises the extension `name`, e.g. `"GL_ARB_buffer_storage"`.
+pub(crate) fn has_extension(name: &str) -> bool {
+    // Contexts before OpenGL 3.0 only have the space separated `GL_EXTENSIONS` string.
+    if !gl::GetStringi::is_loaded() {
+        let extensions = unsafe { gl::GetString(gl::EXTENSIONS) };
+        return !extensions.is_null()
+            && unsafe { CStr::from_ptr(extensions.cast()) }
+                .to_bytes()
+                .split(|&c| c == b' ')
+                .any(|extension| extension == name.as_bytes());
+    }
+
+    let mut count = 0;
+    unsafe { gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count) };
+
+    (0..count.max(0) as u32).any(|i| {
+        let extension = unsafe { gl::GetStringi(gl::EXTENSIONS, i) };
+        !extension.is_null()
+            && unsafe { CStr::from_ptr(extension.cast()) }.to_bytes() == name.as_bytes()
+    })
+}
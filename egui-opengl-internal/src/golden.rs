@@ -0,0 +1,401 @@
+//! Offscreen rendering of egui frames with the painter, for validating painter changes
+//! against stored reference images.
+//! ```ignore
+//! let mut renderer = GoldenRenderer::new(SurfaceConfig::from_pixel_format(None)).unwrap();
+//! let image = renderer.render((256, 64), 1.0, |ctx| {
+//!     egui::CentralPanel::default().show(ctx, |ui| ui.label("Hello"));
+//! });
+//! assert_golden("tests/golden", "label", &image, 2);
+//! ```
+//! References are only written with `UPDATE_GOLDEN=1` set, a missing reference fails the comparison.
+
+use crate::{offscreen::Offscreen, utils, Error, Painter, PreparedFrame, SurfaceConfig};
+use egui::Context;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+};
+use windows::{
+    core::{w, Error as WinError},
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+        Graphics::{
+            Gdi::{GetDC, ReleaseDC, HDC},
+            OpenGL::{
+                wglCreateContext, wglDeleteContext, wglMakeCurrent, ChoosePixelFormat,
+                SetPixelFormat, HGLRC, PFD_DOUBLEBUFFER, PFD_DRAW_TO_WINDOW, PFD_MAIN_PLANE,
+                PFD_SUPPORT_OPENGL, PFD_TYPE_RGBA, PIXELFORMATDESCRIPTOR,
+            },
+        },
+        System::LibraryLoader::GetModuleHandleW,
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DefWindowProcW, DestroyWindow, RegisterClassW, CS_OWNDC,
+            WINDOW_EX_STYLE, WNDCLASSW, WS_OVERLAPPEDWINDOW,
+        },
+    },
+};
+
+/// Hidden window with a current OpenGL context, so the painter can run without a game.
+pub struct HiddenContext {
+    window: HWND,
+    hdc: HDC,
+    context: HGLRC,
+}
+
+impl HiddenContext {
+    pub fn new() -> Result<Self, WinError> {
+        unsafe {
+            let instance = GetModuleHandleW(None)?;
+            let class = WNDCLASSW {
+                style: CS_OWNDC,
+                lpfnWndProc: Some(wnd_proc),
+                hInstance: instance.into(),
+                lpszClassName: w!("egui-opengl-internal-golden"),
+                ..Default::default()
+            };
+            // Fails with "class already exists" for every context after the first one, which is fine.
+            RegisterClassW(&class);
+
+            let window = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                class.lpszClassName,
+                w!(""),
+                WS_OVERLAPPEDWINDOW,
+                0,
+                0,
+                64,
+                64,
+                None,
+                None,
+                instance,
+                None,
+            );
+            if window.0 == 0 {
+                return Err(WinError::from_win32());
+            }
+
+            let hdc = GetDC(window);
+            let pfd = PIXELFORMATDESCRIPTOR {
+                nSize: std::mem::size_of::<PIXELFORMATDESCRIPTOR>() as _,
+                nVersion: 1,
+                dwFlags: PFD_DRAW_TO_WINDOW | PFD_SUPPORT_OPENGL | PFD_DOUBLEBUFFER,
+                iPixelType: PFD_TYPE_RGBA,
+                cColorBits: 32,
+                cAlphaBits: 8,
+                iLayerType: PFD_MAIN_PLANE.0 as _,
+                ..Default::default()
+            };
+
+            let context = (|| {
+                SetPixelFormat(hdc, ChoosePixelFormat(hdc, &pfd), &pfd)?;
+                let context = wglCreateContext(hdc)?;
+                if let Err(e) = wglMakeCurrent(hdc, context) {
+                    let _ = wglDeleteContext(context);
+                    return Err(e);
+                }
+
+                Ok(context)
+            })();
+
+            let context = match context {
+                Ok(context) => context,
+                Err(e) => {
+                    ReleaseDC(window, hdc);
+                    let _ = DestroyWindow(window);
+                    return Err(e);
+                }
+            };
+
+            gl::load_with(|s| utils::get_proc_address(s) as *const _);
+            #[cfg(feature = "gl-debug")]
+            crate::debug::install();
+
+            Ok(Self {
+                window,
+                hdc,
+                context,
+            })
+        }
+    }
+}
+
+impl Drop for HiddenContext {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = wglMakeCurrent(HDC(0), HGLRC(0));
+            let _ = wglDeleteContext(self.context);
+            ReleaseDC(self.window, self.hdc);
+            let _ = DestroyWindow(self.window);
+        }
+    }
+}
+
+unsafe extern "system" fn wnd_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+/// Paints egui frames into an offscreen framebuffer and reads them back.
+pub struct GoldenRenderer {
+    // Dropped in declaration order, the painter and framebuffer need the context to still exist.
+    offscreen: Option<Offscreen>,
+    painter: Painter,
+    ctx: Context,
+    surface: SurfaceConfig,
+    _context: HiddenContext,
+}
+
+impl GoldenRenderer {
+    /// Creates a hidden context and a painter using `surface` for its sRGB and blending strategy.
+    pub fn new(surface: SurfaceConfig) -> Result<Self, Error> {
+        let context = HiddenContext::new().map_err(Error::CreateContext)?;
+
+        let mut painter = Painter::new().map_err(Error::Shader)?;
+        painter.set_surface(surface);
+        // Golden images must not depend on the build profile.
+        painter.set_state_check(false);
+
+        Ok(Self {
+            offscreen: None,
+            painter,
+            ctx: Context::default(),
+            surface,
+            _context: context,
+        })
+    }
+
+    /// Gives access to the egui context, e.g. to set fonts or style before rendering.
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    /// Gives access to the painter, e.g. to enable compositing.
+    pub fn painter(&mut self) -> &mut Painter {
+        &mut self.painter
+    }
+
+    /// Runs `ui` (see [`PreparedFrame::run`]) and paints the result onto a transparent `size` framebuffer.
+    pub fn render(
+        &mut self,
+        size: (u32, u32),
+        pixels_per_point: f32,
+        ui: impl FnMut(&Context),
+    ) -> Image {
+        let frame = PreparedFrame::run(&self.ctx, size, pixels_per_point, ui);
+        self.paint(&frame);
+
+        let offscreen = expect!(self.offscreen.as_ref(), "Offscreen framebuffer is missing");
+        Image {
+            width: frame.size.0,
+            height: frame.size.1,
+            pixels: unsafe { offscreen.read_pixels() },
+        }
+    }
+
+    /// Clears the offscreen framebuffer and paints `frame` onto it, without reading it back.
+    pub fn paint(&mut self, frame: &PreparedFrame) {
+        if self.offscreen.as_ref().map(Offscreen::size) != Some(frame.size) {
+            self.offscreen = None;
+            self.offscreen = unsafe {
+                Offscreen::new(frame.size.0, frame.size.1, self.surface.framebuffer_srgb)
+            };
+        }
+        let offscreen = expect!(
+            self.offscreen.as_ref(),
+            "Failed to create offscreen framebuffer"
+        );
+
+        unsafe {
+            offscreen.clear([0.; 4]);
+            let previous = offscreen.bind();
+            self.painter.paint_frame(frame);
+            offscreen.unbind(previous);
+        }
+    }
+
+    /// Blocks until the GPU finished all painting, for timing measurements.
+    pub fn finish(&self) {
+        unsafe { gl::Finish() };
+    }
+}
+
+/// RGBA image, rows top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Result of comparing two images with [`Image::compare`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImageDiff {
+    /// Number of pixels with a channel differing by more than the tolerance.
+    pub mismatched: usize,
+    /// Largest difference of any channel.
+    pub max_delta: u8,
+}
+
+impl ImageDiff {
+    pub fn is_match(&self) -> bool {
+        self.mismatched == 0
+    }
+}
+
+impl Image {
+    /// Compares against `other`, channels may differ by up to `tolerance`.
+    /// Images of different sizes mismatch in every pixel.
+    pub fn compare(&self, other: &Image, tolerance: u8) -> ImageDiff {
+        if self.width != other.width || self.height != other.height {
+            return ImageDiff {
+                mismatched: (self.width * self.height).max(other.width * other.height) as usize,
+                max_delta: u8::MAX,
+            };
+        }
+
+        let mut diff = ImageDiff::default();
+        for (a, b) in self
+            .pixels
+            .chunks_exact(4)
+            .zip(other.pixels.chunks_exact(4))
+        {
+            let delta = a
+                .iter()
+                .zip(b)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap_or(0);
+            diff.max_delta = diff.max_delta.max(delta);
+            if delta > tolerance {
+                diff.mismatched += 1;
+            }
+        }
+
+        diff
+    }
+
+    /// Writes the image as a PAM (`P7`, `RGB_ALPHA`) file, readable by most image viewers.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        write!(
+            file,
+            "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+            self.width, self.height
+        )?;
+        file.write_all(&self.pixels)?;
+        file.flush()
+    }
+
+    /// Reads a PAM file written by [`Self::save`].
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let invalid =
+            |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_owned());
+        let mut reader = BufReader::new(std::fs::File::open(path)?);
+
+        let (mut width, mut height, mut depth) = (None, None, None);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid("unexpected end of header"));
+            }
+
+            let mut words = line.split_whitespace();
+            let value = |words: &mut std::str::SplitWhitespace| {
+                words.next().and_then(|v| v.parse::<u32>().ok())
+            };
+            match words.next() {
+                Some("WIDTH") => width = value(&mut words),
+                Some("HEIGHT") => height = value(&mut words),
+                Some("DEPTH") => depth = value(&mut words),
+                Some("ENDHDR") => break,
+                _ => {}
+            }
+        }
+
+        let (Some(width), Some(height), Some(4)) = (width, height, depth) else {
+            return Err(invalid("expected an RGBA image"));
+        };
+
+        let mut pixels = vec![0; (width * height * 4) as usize];
+        reader.read_exact(&mut pixels)?;
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// Compares `image` to the reference `<dir>/<name>.pam`, which is written instead when `UPDATE_GOLDEN` is set.
+/// On mismatch, the rendered image is saved next to the reference as `<name>.actual.pam`.
+pub fn assert_golden(dir: impl AsRef<Path>, name: &str, image: &Image, tolerance: u8) {
+    let dir = dir.as_ref();
+    let reference_path = dir.join(format!("{name}.pam"));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        expect!(
+            std::fs::create_dir_all(dir),
+            "Failed to create golden image directory"
+        );
+        expect!(image.save(&reference_path), "Failed to write golden image");
+        return;
+    }
+
+    if !reference_path.exists() {
+        let actual_path = dir.join(format!("{name}.actual.pam"));
+        let _ = std::fs::create_dir_all(dir);
+        let _ = image.save(&actual_path);
+        panic_msg!(
+            "{name}: reference {} is missing, see {} and rerun with UPDATE_GOLDEN=1 to accept it",
+            reference_path.display(),
+            actual_path.display()
+        );
+    }
+
+    let reference = expect!(Image::load(&reference_path), "Failed to read golden image");
+    let diff = image.compare(&reference, tolerance);
+    if !diff.is_match() {
+        let actual_path = dir.join(format!("{name}.actual.pam"));
+        let _ = image.save(&actual_path);
+        panic_msg!(
+            "{name}: {} pixels differ from {} (max delta {}), see {}",
+            diff.mismatched,
+            reference_path.display(),
+            diff.max_delta,
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn test_image_compare() {
+    let a = Image {
+        width: 2,
+        height: 1,
+        pixels: vec![0, 0, 0, 255, 10, 20, 30, 255],
+    };
+    let mut b = a.clone();
+    b.pixels[4] = 13;
+
+    assert_eq!(a.compare(&a, 0), ImageDiff::default());
+    assert_eq!(
+        a.compare(&b, 3),
+        ImageDiff {
+            mismatched: 0,
+            max_delta: 3
+        }
+    );
+    assert_eq!(
+        a.compare(&b, 2),
+        ImageDiff {
+            mismatched: 1,
+            max_delta: 3
+        }
+    );
+}
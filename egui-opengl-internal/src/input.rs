@@ -1,29 +1,176 @@
-use clipboard::{windows_clipboard::WindowsClipboardContext, ClipboardProvider};
-use egui::{Event, Key, Modifiers, PointerButton, Pos2, RawInput, Rect, Vec2, Context};
+//! Translates window messages into egui input, used by [`crate::OpenGLApp`] and usable on its own.
+
+use crate::clipboard;
+use egui::{DroppedFile, Event, Key, Modifiers, PointerButton, Pos2, RawInput, Rect, Vec2, Context, TouchDeviceId, TouchId, TouchPhase};
+use std::{ffi::OsString, os::windows::ffi::OsStringExt, path::PathBuf};
 use windows::Wdk::System::SystemInformation::NtQuerySystemTime;
 use windows::Win32::{
-    Foundation::{HWND, RECT},
+    Foundation::{HWND, POINT, RECT},
+    Graphics::Gdi::ScreenToClient,
     System::SystemServices::{MK_CONTROL, MK_SHIFT},
     UI::{
-        Input::KeyboardAndMouse::{
-            GetAsyncKeyState, VIRTUAL_KEY, VK_BACK, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END,
-            VK_ESCAPE, VK_HOME, VK_INSERT, VK_LEFT, VK_LSHIFT, VK_NEXT, VK_PRIOR, VK_RETURN,
-            VK_RIGHT, VK_SPACE, VK_TAB, VK_UP,
+        Input::{
+            GetRawInputData, HRAWINPUT, RAWINPUT, RAWINPUTHEADER, RID_INPUT, RIM_TYPEMOUSE,
+            KeyboardAndMouse::{
+                GetAsyncKeyState, GetKeyState, GetKeyboardLayout, GetKeyboardState, ToUnicodeEx, VIRTUAL_KEY, VK_BACK, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END,
+                VK_ESCAPE, VK_HOME, VK_INSERT, VK_LEFT, VK_SHIFT, VK_NEXT, VK_PRIOR, VK_RETURN,
+                VK_RIGHT, VK_SPACE, VK_TAB, VK_UP, VK_CAPITAL, VK_LCONTROL, VK_LMENU, VK_LSHIFT,
+                VK_MENU, VK_RCONTROL, VK_RMENU, VK_RSHIFT, GetKeyNameTextW, MapVirtualKeyW,
+                MAPVK_VK_TO_VSC, VK_ADD, VK_DECIMAL, VK_DIVIDE, VK_OEM_1, VK_OEM_2, VK_OEM_3,
+                VK_OEM_4, VK_OEM_5, VK_OEM_6, VK_OEM_COMMA, VK_OEM_MINUS, VK_OEM_PERIOD, VK_OEM_PLUS,
+                VK_SUBTRACT,
+            },
+            Pointer::{
+                GetPointerInfo, GetPointerPenInfo, POINTER_FLAG_CANCELED, POINTER_FLAG_INCONTACT,
+                POINTER_INFO, POINTER_PEN_INFO,
+            },
+            Touch::{GetTouchInputInfo, HTOUCHINPUT, TOUCHEVENTF_DOWN, TOUCHEVENTF_UP, TOUCHINPUT},
         },
+        Shell::{DragQueryFileW, DragQueryPoint, HDROP},
         WindowsAndMessaging::{
-            GetClientRect, KF_REPEAT, WHEEL_DELTA, WM_CHAR, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDBLCLK,
-            WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN, WM_MBUTTONUP,
-            WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN,
-            WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDBLCLK, WM_XBUTTONDOWN,
-            WM_XBUTTONUP, XBUTTON1, XBUTTON2,
+            GetClientRect, GetForegroundWindow, SystemParametersInfoW, KF_REPEAT, PT_MOUSE, PT_PEN,
+            RI_MOUSE_HWHEEL, RI_MOUSE_WHEEL, SPI_GETWHEELSCROLLCHARS, SPI_GETWHEELSCROLLLINES,
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, UNICODE_NOCHAR, WA_INACTIVE, WHEEL_DELTA,
+            WM_ACTIVATE, WM_ACTIVATEAPP, WM_CHAR, WM_DEADCHAR, WM_DROPFILES, WM_INPUT, WM_KEYDOWN,
+            WM_KEYUP, WM_KILLFOCUS, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP,
+            WM_MBUTTONDBLCLK, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE,
+            WM_MOUSEWHEEL, WM_PASTE, WM_POINTERDOWN, WM_POINTERUP, WM_POINTERUPDATE,
+            WM_RBUTTONDBLCLK, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSDEADCHAR, WM_SYSKEYDOWN,
+            WM_SYSKEYUP, WM_TOUCH, WM_UNICHAR, WM_XBUTTONDBLCLK, WM_XBUTTONDOWN, WM_XBUTTONUP,
+            XBUTTON1, XBUTTON2,
         },
     },
 };
 
+/// Where text input (`Event::Text`) is taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextInputSource {
+    /// `WM_CHAR` messages produced by `TranslateMessage` in the game's message loop.
+    WmChar,
+    /// Characters are synthesized from `WM_KEYDOWN` with `ToUnicodeEx` and `WM_CHAR` is ignored.
+    /// For games that never translate key presses, or re-post their own `WM_CHAR`s.
+    ToUnicode,
+    /// Uses `WM_CHAR`, dropping duplicates posted for a single key press,
+    /// and switches to synthesizing characters if key presses don't get translated.
+    #[default]
+    Auto,
+}
+
+/// How far one notch of the mouse wheel scrolls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollConfig {
+    pub unit: ScrollUnit,
+    /// Scales the distance of every notch, negative values invert the direction.
+    pub multiplier: f32,
+}
+
+impl Default for ScrollConfig {
+    fn default() -> Self {
+        Self {
+            unit: ScrollUnit::default(),
+            multiplier: 1.,
+        }
+    }
+}
+
+/// Distance of a wheel notch before [`ScrollConfig::multiplier`] is applied.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ScrollUnit {
+    /// The lines and characters per notch set in the Windows mouse settings,
+    /// scrolling a whole page if they're set to do that.
+    #[default]
+    SystemLines,
+    /// A fixed number of lines, each [`SCROLL_LINE_HEIGHT`] points tall.
+    Lines(f32),
+    /// A fixed number of points.
+    Points(f32),
+}
+
+/// Shortcuts that change egui's zoom factor, scaling the whole UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZoomConfig {
+    /// Ctrl and `+`, `-` or `0`, handled by egui itself.
+    pub keyboard: bool,
+    /// Ctrl and the mouse wheel. When off, it's reported as [`Event::Zoom`] for widgets like plots.
+    pub wheel: bool,
+}
+
+impl Default for ZoomConfig {
+    fn default() -> Self {
+        Self {
+            keyboard: true,
+            wheel: false,
+        }
+    }
+}
+
+/// Height in points of a line scrolled by [`ScrollUnit::Lines`] and [`ScrollUnit::SystemLines`].
+pub const SCROLL_LINE_HEIGHT: f32 = 16.;
+
+/// `SPI_GETWHEELSCROLLLINES` value of wheels set to scroll a page per notch.
+const WHEEL_PAGESCROLL: u32 = u32::MAX;
+
+/// `RAWMOUSE` flag of absolute positions, e.g. from tablets and remote desktops.
+const MOUSE_MOVE_ABSOLUTE: u16 = 1;
+
+/// Pressure reported by `GetPointerPenInfo` for a pen pressed down fully.
+const PEN_MAX_PRESSURE: f32 = 1024.;
+
+/// Number of key presses without a `WM_CHAR` after which [`TextInputSource::Auto`] synthesizes characters.
+const MISSED_CHARS_THRESHOLD: u32 = 2;
+
+/// `ToUnicodeEx` flag that leaves the keyboard state (and pending dead keys) untouched.
+const TOUNICODE_NO_STATE_CHANGE: u32 = 1 << 2;
+
+/// Character synthesized for a key press, waiting for the `WM_CHAR` that should follow it.
+struct AwaitingChar {
+    text: String,
+    frames: u32,
+}
+
+/// Key press that `ToUnicodeEx` reported as a dead key, with the keyboard state it was pressed with.
+struct DeadKey {
+    wparam: usize,
+    lparam: isize,
+    state: Box<[u8; 256]>,
+}
+
+/// Translates the window's messages into egui input, see [`crate::OpenGLApp::wnd_proc`].
 pub struct InputCollector {
     hwnd: HWND,
     events: Vec<Event>,
     modifiers: Option<Modifiers>,
+    text_source: TextInputSource,
+    /// Set when [`TextInputSource::Auto`] detected that the game doesn't translate key presses.
+    synthesize_text: bool,
+    awaiting_char: Option<AwaitingChar>,
+    missed_chars: u32,
+    chars_since_keydown: u32,
+    last_char: Option<char>,
+    /// Set on `WM_DEADCHAR`, the key press completing the dead key may produce two identical characters.
+    dead_char: bool,
+    /// Whether the characters of the current key press complete a dead key.
+    composing: bool,
+    /// Dead key waiting for the next key press while characters are synthesized.
+    dead_key: Option<DeadKey>,
+    /// Set when the window lost the keyboard focus, held keys and buttons are released on the next frame.
+    focus_lost: bool,
+    /// Files dropped onto the window since the last frame.
+    dropped_files: Vec<DroppedFile>,
+    /// First half of a character outside the BMP, which `WM_CHAR` delivers as two UTF-16 surrogates.
+    high_surrogate: Option<u16>,
+    /// Converts the window's pixel coordinates into egui points.
+    pixels_per_point: f32,
+    /// Size in pixels the UI is painted at instead of the window's, see [`crate::OpenGLApp::set_render_resolution`].
+    render_resolution: Option<(u32, u32)>,
+    /// Last non-empty client size in pixels, egui keeps its layout at it while the window is minimized.
+    screen_size: Pos2,
+    scroll: ScrollConfig,
+    /// Zooms the UI with Ctrl and the mouse wheel instead of sending [`Event::Zoom`].
+    wheel_zoom: bool,
+    /// Wheel notches to zoom the UI by on the next frame, positive zooming in.
+    /// Fractions of high resolution wheels are kept until they add up to a notch.
+    zoom_notches: f32,
 }
 
 /// High-level overview of recognized `WndProc` messages.
@@ -38,6 +185,10 @@ pub enum InputResult {
     Scroll,
     Zoom,
     Key,
+    Paste,
+    Touch,
+    DropFiles,
+    FocusLost,
 }
 
 impl InputCollector {
@@ -46,6 +197,264 @@ impl InputCollector {
             hwnd,
             events: vec![],
             modifiers: None,
+            text_source: TextInputSource::default(),
+            synthesize_text: false,
+            awaiting_char: None,
+            missed_chars: 0,
+            chars_since_keydown: 0,
+            last_char: None,
+            dead_char: false,
+            composing: false,
+            dead_key: None,
+            focus_lost: false,
+            dropped_files: vec![],
+            high_surrogate: None,
+            pixels_per_point: 1.,
+            render_resolution: None,
+            screen_size: Pos2::ZERO,
+            scroll: ScrollConfig::default(),
+            wheel_zoom: false,
+            zoom_notches: 0.,
+        }
+    }
+
+    /// Moves the collector to another window, dropping pending input but keeping its configuration.
+    pub fn set_window(&mut self, hwnd: HWND) {
+        *self = Self {
+            text_source: self.text_source,
+            pixels_per_point: self.pixels_per_point,
+            render_resolution: self.render_resolution,
+            scroll: self.scroll,
+            wheel_zoom: self.wheel_zoom,
+            ..Self::new(hwnd)
+        };
+    }
+
+    pub fn set_text_source(&mut self, source: TextInputSource) {
+        self.text_source = source;
+        self.synthesize_text = false;
+        self.awaiting_char = None;
+        self.missed_chars = 0;
+    }
+
+    pub fn set_scroll_config(&mut self, scroll: ScrollConfig) {
+        self.scroll = scroll;
+    }
+
+    pub fn set_wheel_zoom(&mut self, wheel_zoom: bool) {
+        self.wheel_zoom = wheel_zoom;
+        self.zoom_notches = 0.;
+    }
+
+    /// Changes the zoom factor of `ctx` by the wheel notches turned with Ctrl held since the last call,
+    /// in the steps of egui's keyboard zoom. Takes effect at the end of the next frame.
+    pub fn apply_wheel_zoom(&mut self, ctx: &Context) {
+        let steps = self.zoom_notches.trunc();
+        self.zoom_notches -= steps;
+        if steps != 0. {
+            let zoom_factor = (ctx.zoom_factor() + steps * 0.1).clamp(0.2, 5.);
+            ctx.set_zoom_factor((zoom_factor * 10.).round() / 10.);
+        }
+    }
+
+    /// Sets the scale used for pointer positions and the screen rect, including egui's zoom factor.
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: f32) {
+        self.pixels_per_point = pixels_per_point;
+    }
+
+    pub fn set_render_resolution(&mut self, resolution: Option<(u32, u32)>) {
+        self.render_resolution = resolution;
+    }
+
+    fn pos(&self, lparam: isize) -> Pos2 {
+        self.to_points(get_pos(lparam))
+    }
+
+    /// Converts a position in the window's client area from pixels into egui points.
+    fn to_points(&self, pos: Pos2) -> Pos2 {
+        let mut pos = pos.to_vec2();
+        if let Some((width, height)) = self.render_resolution {
+            let window = self.get_screen_size();
+            if window.x > 0. && window.y > 0. {
+                pos = pos * egui::vec2(width as f32 / window.x, height as f32 / window.y);
+            }
+        }
+
+        (pos / self.pixels_per_point).to_pos2()
+    }
+
+    /// Drops half-typed characters, the keys and buttons egui thinks are held are released on the next frame.
+    /// For when egui won't see their releases, e.g. because the window was deactivated.
+    pub fn lose_focus(&mut self) {
+        self.focus_lost = true;
+        self.awaiting_char = None;
+        self.dead_char = false;
+        self.dead_key = None;
+        self.high_surrogate = None;
+        self.alter_modifiers(Modifiers::NONE);
+    }
+
+    /// Releases what egui thinks is held, the window won't get the key and button releases.
+    fn release_held_input(&mut self, ctx: &Context) {
+        let released = ctx.input(|i| {
+            let keys = i.keys_down.iter().map(|&key| Event::Key {
+                key,
+                physical_key: None,
+                pressed: false,
+                repeat: false,
+                modifiers: Modifiers::NONE,
+            });
+            let pos = i.pointer.latest_pos().unwrap_or_default();
+            let buttons = [
+                PointerButton::Primary,
+                PointerButton::Secondary,
+                PointerButton::Middle,
+                PointerButton::Extra1,
+                PointerButton::Extra2,
+            ]
+            .into_iter()
+            .filter(|&button| i.pointer.button_down(button))
+            .map(|button| Event::PointerButton {
+                pos,
+                button,
+                pressed: false,
+                modifiers: Modifiers::NONE,
+            });
+
+            keys.chain(buttons).collect::<Vec<_>>()
+        });
+
+        self.events.extend(released);
+        self.events.push(Event::PointerGone);
+    }
+
+    /// Whether anything egui should see arrived since the last [`Self::collect_input`].
+    pub fn has_input(&self) -> bool {
+        !self.events.is_empty()
+            || !self.dropped_files.is_empty()
+            || self.focus_lost
+            || self.zoom_notches.abs() >= 1.
+    }
+
+    /// Queues an event for the next frame, as if it came from the window's messages.
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    /// Where the files of a `WM_DROPFILES` were dropped, in points.
+    pub fn drop_pos(&self, wparam: usize) -> Pos2 {
+        let mut point = POINT::default();
+        unsafe { DragQueryPoint(HDROP(wparam as _), &mut point) };
+        self.to_points(Pos2::new(point.x as f32, point.y as f32))
+    }
+
+    /// Queues the clipboard's text for the focused text field.
+    fn paste(&mut self) {
+        if let Some(text) = get_clipboard_text(self.hwnd) {
+            self.events.push(Event::Paste(text));
+        }
+    }
+
+    fn synthesizes_text(&self) -> bool {
+        match self.text_source {
+            TextInputSource::WmChar => false,
+            TextInputSource::ToUnicode => true,
+            TextInputSource::Auto => self.synthesize_text,
+        }
+    }
+
+    /// Handles a key press that produced no `WM_CHAR`.
+    fn resolve_awaiting_char(&mut self) {
+        if let Some(awaiting) = self.awaiting_char.take() {
+            self.events.push(Event::Text(awaiting.text));
+
+            self.missed_chars += 1;
+            if self.missed_chars >= MISSED_CHARS_THRESHOLD {
+                log!("Key presses are not translated into WM_CHAR, synthesizing text input");
+                self.synthesize_text = true;
+            }
+        }
+    }
+
+    /// Turns the UTF-16 code unit of a `WM_CHAR` into a character, merging surrogate pairs.
+    fn decode_char(&mut self, wparam: usize) -> Option<char> {
+        match wparam {
+            0xD800..=0xDBFF => {
+                self.high_surrogate = Some(wparam as u16);
+                None
+            }
+            0xDC00..=0xDFFF => {
+                let high = self.high_surrogate.take()?;
+                char::decode_utf16([high, wparam as u16]).next()?.ok()
+            }
+            _ => {
+                self.high_surrogate = None;
+                char::from_u32(wparam as _)
+            }
+        }
+    }
+
+    fn on_char(&mut self, ch: char) {
+        match self.text_source {
+            TextInputSource::WmChar => {}
+            TextInputSource::ToUnicode => return,
+            TextInputSource::Auto => {
+                if self.synthesize_text {
+                    // The game does translate key presses after all, this one was already synthesized.
+                    log!("Received WM_CHAR, switching back to it for text input");
+                    self.synthesize_text = false;
+                    self.missed_chars = 0;
+                    return;
+                }
+
+                self.awaiting_char = None;
+                self.missed_chars = 0;
+                self.chars_since_keydown += 1;
+
+                // A dead key followed by a key it doesn't combine with types both, e.g. "´´".
+                if self.chars_since_keydown > 1 && !self.composing && self.last_char == Some(ch) {
+                    return;
+                }
+            }
+        }
+
+        self.last_char = Some(ch);
+        if !ch.is_control() {
+            self.events.push(Event::Text(ch.into()));
+        }
+    }
+
+    fn on_key_down(&mut self, wparam: usize, lparam: isize) {
+        if self.text_source == TextInputSource::WmChar {
+            return;
+        }
+
+        self.resolve_awaiting_char();
+        self.chars_since_keydown = 0;
+
+        // Modifiers pressed for the character after a dead key don't complete it.
+        if is_modifier_key(wparam) {
+            return;
+        }
+        self.composing = std::mem::take(&mut self.dead_char);
+
+        // `WM_CHAR` is composed by the game's `TranslateMessage`, synthesized text has to replay the dead key.
+        let dead_key = self.dead_key.take().filter(|_| self.synthesizes_text());
+        match translate_key(wparam, lparam, dead_key.as_ref()) {
+            Some(KeyText::Text(text)) if self.synthesizes_text() => {
+                self.events.push(Event::Text(text));
+            }
+            Some(KeyText::Text(text)) => {
+                self.awaiting_char = Some(AwaitingChar { text, frames: 0 });
+            }
+            Some(KeyText::Dead(state)) => {
+                self.dead_key = Some(DeadKey {
+                    wparam,
+                    lparam,
+                    state,
+                });
+            }
+            None => {}
         }
     }
 
@@ -54,7 +463,7 @@ impl InputCollector {
             WM_MOUSEMOVE => {
                 self.alter_modifiers(get_mouse_modifiers(wparam));
 
-                self.events.push(Event::PointerMoved(get_pos(lparam)));
+                self.events.push(Event::PointerMoved(self.pos(lparam)));
                 InputResult::MouseMove
             }
             WM_LBUTTONDOWN | WM_LBUTTONDBLCLK => {
@@ -62,7 +471,7 @@ impl InputCollector {
                 self.alter_modifiers(modifiers);
 
                 self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
+                    pos: self.pos(lparam),
                     button: PointerButton::Primary,
                     pressed: true,
                     modifiers,
@@ -74,7 +483,7 @@ impl InputCollector {
                 self.alter_modifiers(modifiers);
 
                 self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
+                    pos: self.pos(lparam),
                     button: PointerButton::Primary,
                     pressed: false,
                     modifiers,
@@ -86,7 +495,7 @@ impl InputCollector {
                 self.alter_modifiers(modifiers);
 
                 self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
+                    pos: self.pos(lparam),
                     button: PointerButton::Secondary,
                     pressed: true,
                     modifiers,
@@ -98,7 +507,7 @@ impl InputCollector {
                 self.alter_modifiers(modifiers);
 
                 self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
+                    pos: self.pos(lparam),
                     button: PointerButton::Secondary,
                     pressed: false,
                     modifiers,
@@ -110,7 +519,7 @@ impl InputCollector {
                 self.alter_modifiers(modifiers);
 
                 self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
+                    pos: self.pos(lparam),
                     button: PointerButton::Middle,
                     pressed: true,
                     modifiers,
@@ -122,7 +531,7 @@ impl InputCollector {
                 self.alter_modifiers(modifiers);
 
                 self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
+                    pos: self.pos(lparam),
                     button: PointerButton::Middle,
                     pressed: false,
                     modifiers,
@@ -134,7 +543,7 @@ impl InputCollector {
                 self.alter_modifiers(modifiers);
 
                 self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
+                    pos: self.pos(lparam),
                     button: if (wparam as u32) >> 16u32 & XBUTTON1 as u32 != 0u32 {
                         PointerButton::Extra1
                     } else if (wparam as u32) >> 16u32 & XBUTTON2 as u32 != 0u32 {
@@ -152,7 +561,7 @@ impl InputCollector {
                 self.alter_modifiers(modifiers);
 
                 self.events.push(Event::PointerButton {
-                    pos: get_pos(lparam),
+                    pos: self.pos(lparam),
                     button: if (wparam as u32) >> 16u32 & XBUTTON1 as u32 != 0u32 {
                         PointerButton::Extra1
                     } else if (wparam as u32) >> 16u32 & XBUTTON2 as u32 != 0u32 {
@@ -166,9 +575,27 @@ impl InputCollector {
                 InputResult::MouseMiddle
             }
             WM_CHAR => {
-                if let Some(ch) = char::from_u32(wparam as _) {
-                    if !ch.is_control() {
-                        self.events.push(Event::Text(ch.into()));
+                if let Some(ch) = self.decode_char(wparam) {
+                    self.on_char(ch);
+                }
+                InputResult::Character
+            }
+            WM_DEADCHAR | WM_SYSDEADCHAR => {
+                // The accent is combined into the `WM_CHAR` of the next key press.
+                self.dead_char = true;
+                InputResult::Character
+            }
+            WM_DROPFILES => {
+                // Files are dropped where the pointer is, but no mouse moves arrive while dragging.
+                self.events.push(Event::PointerMoved(self.drop_pos(wparam)));
+                self.dropped_files.extend(get_dropped_files(HDROP(wparam as _)));
+                InputResult::DropFiles
+            }
+            WM_UNICHAR => {
+                // Sent by some input methods with the full UTF-32 character.
+                if wparam as u32 != UNICODE_NOCHAR {
+                    if let Some(ch) = char::from_u32(wparam as _) {
+                        self.on_char(ch);
                     }
                 }
                 InputResult::Character
@@ -176,13 +603,13 @@ impl InputCollector {
             WM_MOUSEWHEEL => {
                 self.alter_modifiers(get_mouse_modifiers(wparam));
 
-                let delta = (wparam >> 16) as i16 as f32 * 10. / WHEEL_DELTA as f32;
+                let notches = wheel_notches(wparam >> 16);
 
                 if wparam & MK_CONTROL.0 as usize != 0 {
-                    self.events
-                        .push(Event::Zoom(if delta > 0. { 1.5 } else { 0.5 }));
+                    self.zoom(notches);
                     InputResult::Zoom
                 } else {
+                    let delta = self.scroll_distance(notches, false);
                     self.events.push(Event::Scroll(Vec2::new(0., delta)));
                     InputResult::Scroll
                 }
@@ -190,48 +617,65 @@ impl InputCollector {
             WM_MOUSEHWHEEL => {
                 self.alter_modifiers(get_mouse_modifiers(wparam));
 
-                let delta = (wparam >> 16) as i16 as f32 * 10. / WHEEL_DELTA as f32;
+                let notches = wheel_notches(wparam >> 16);
 
                 if wparam & MK_CONTROL.0 as usize != 0 {
-                    self.events
-                        .push(Event::Zoom(if delta > 0. { 1.5 } else { 0.5 }));
+                    self.zoom(notches);
                     InputResult::Zoom
                 } else {
+                    let delta = self.scroll_distance(notches, true);
                     self.events.push(Event::Scroll(Vec2::new(delta, 0.)));
                     InputResult::Scroll
                 }
             }
-            msg @ (WM_KEYDOWN | WM_SYSKEYDOWN) => {
-                let modifiers = get_key_modifiers(msg);
+            WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP => self.on_pointer(umsg, wparam),
+            WM_TOUCH => self.on_touch(wparam, lparam),
+            WM_KILLFOCUS => {
+                self.lose_focus();
+                InputResult::FocusLost
+            }
+            WM_ACTIVATE if wparam & 0xFFFF == WA_INACTIVE as usize => {
+                self.lose_focus();
+                InputResult::FocusLost
+            }
+            WM_ACTIVATEAPP if wparam == 0 => {
+                self.lose_focus();
+                InputResult::FocusLost
+            }
+            WM_PASTE => {
+                // Sent by edit menus and accessibility tools rather than the keyboard.
+                self.paste();
+                InputResult::Paste
+            }
+            WM_KEYDOWN | WM_SYSKEYDOWN => {
+                let modifiers = get_key_modifiers();
                 self.modifiers = Some(modifiers);
+                self.on_key_down(wparam, lparam);
 
                 if let Some(key) = get_key(wparam) {
-                    if key == Key::V && modifiers.ctrl {
-                        if let Some(clipboard) = get_clipboard_text() {
-                            self.events.push(Event::Text(clipboard));
-                        }
-                    }
-
-                    if key == Key::C && modifiers.ctrl {
-                        self.events.push(Event::Copy);
-                    }
-
-                    if key == Key::X && modifiers.ctrl {
-                        self.events.push(Event::Cut);
+                    // Both the common shortcuts and the older ones with Insert and Delete.
+                    match key {
+                        Key::V if modifiers.ctrl => self.paste(),
+                        Key::Insert if modifiers.shift => self.paste(),
+                        Key::C if modifiers.ctrl => self.events.push(Event::Copy),
+                        Key::Insert if modifiers.ctrl => self.events.push(Event::Copy),
+                        Key::X if modifiers.ctrl => self.events.push(Event::Cut),
+                        Key::Delete if modifiers.shift => self.events.push(Event::Cut),
+                        _ => {}
                     }
 
                     self.events.push(Event::Key {
                         pressed: true,
                         modifiers,
                         key,
-                        repeat: lparam & (KF_REPEAT as isize) > 0,
+                        repeat: is_key_repeat(lparam),
                         physical_key: None,
                     });
                 }
                 InputResult::Key
             }
-            msg @ (WM_KEYUP | WM_SYSKEYUP) => {
-                let modifiers = get_key_modifiers(msg);
+            WM_KEYUP | WM_SYSKEYUP => {
+                let modifiers = get_key_modifiers();
                 self.modifiers = Some(modifiers);
 
                 if let Some(key) = get_key(wparam) {
@@ -239,7 +683,7 @@ impl InputCollector {
                         pressed: false,
                         modifiers,
                         key,
-                        repeat: lparam & (KF_REPEAT as isize) > 0,
+                        repeat: false,
                         physical_key: None,
                     });
                 }
@@ -249,6 +693,85 @@ impl InputCollector {
         }
     }
 
+    /// Translates `WM_POINTER*` of touch and pen input into touch events, which egui uses for gestures
+    /// like pinch zoom. Pointer events come from the mouse messages Windows synthesizes for them.
+    fn on_pointer(&mut self, umsg: u32, wparam: usize) -> InputResult {
+        let pointer_id = (wparam & 0xFFFF) as u32;
+        let mut info = POINTER_INFO::default();
+        if unsafe { GetPointerInfo(pointer_id, &mut info) }.is_err() || info.pointerType == PT_MOUSE {
+            return InputResult::Unknown;
+        }
+
+        let flags = info.pointerFlags;
+        let phase = if flags.contains(POINTER_FLAG_CANCELED) {
+            TouchPhase::Cancel
+        } else {
+            match umsg {
+                WM_POINTERDOWN => TouchPhase::Start,
+                WM_POINTERUP => TouchPhase::End,
+                // Pens report hovering above the display as updates too.
+                _ if flags.contains(POINTER_FLAG_INCONTACT) => TouchPhase::Move,
+                _ => return InputResult::Unknown,
+            }
+        };
+
+        let force = (info.pointerType == PT_PEN)
+            .then(|| {
+                let mut pen = POINTER_PEN_INFO::default();
+                unsafe { GetPointerPenInfo(pointer_id, &mut pen) }.ok()?;
+                Some(pen.pressure as f32 / PEN_MAX_PRESSURE)
+            })
+            .flatten();
+
+        let mut point = info.ptPixelLocation;
+        unsafe { ScreenToClient(self.hwnd, &mut point) };
+        self.events.push(Event::Touch {
+            device_id: TouchDeviceId(info.sourceDevice.0 as u64),
+            id: TouchId(pointer_id as u64),
+            phase,
+            pos: self.to_points(Pos2::new(point.x as f32, point.y as f32)),
+            force,
+        });
+
+        InputResult::Touch
+    }
+
+    /// Translates `WM_TOUCH`, only sent to windows that registered for it with `RegisterTouchWindow`.
+    /// The input handle is left open for the game's window procedure.
+    fn on_touch(&mut self, wparam: usize, lparam: isize) -> InputResult {
+        let mut inputs = vec![TOUCHINPUT::default(); wparam & 0xFFFF];
+        let size = std::mem::size_of::<TOUCHINPUT>() as i32;
+        if unsafe { GetTouchInputInfo(HTOUCHINPUT(lparam), &mut inputs, size) }.is_err() {
+            return InputResult::Unknown;
+        }
+
+        for input in inputs {
+            let phase = if input.dwFlags.contains(TOUCHEVENTF_DOWN) {
+                TouchPhase::Start
+            } else if input.dwFlags.contains(TOUCHEVENTF_UP) {
+                TouchPhase::End
+            } else {
+                TouchPhase::Move
+            };
+
+            // In hundredths of a pixel, relative to the screen.
+            let mut point = POINT {
+                x: input.x / 100,
+                y: input.y / 100,
+            };
+            unsafe { ScreenToClient(self.hwnd, &mut point) };
+            self.events.push(Event::Touch {
+                device_id: TouchDeviceId(input.hSource.0 as u64),
+                id: TouchId(input.dwID as u64),
+                phase,
+                pos: self.to_points(Pos2::new(point.x as f32, point.y as f32)),
+                force: None,
+            });
+        }
+
+        InputResult::Touch
+    }
+
     fn alter_modifiers(&mut self, new: Modifiers) {
         if let Some(old) = self.modifiers.as_mut() {
             *old = new;
@@ -256,6 +779,29 @@ impl InputCollector {
     }
 
     pub fn collect_input(&mut self, ctx: &Context) -> RawInput {
+        // `WM_CHAR` follows its key press within the same message loop iteration,
+        // so a character still awaited a frame later was never translated.
+        if let Some(awaiting) = self.awaiting_char.as_mut() {
+            awaiting.frames += 1;
+            if awaiting.frames > 1 {
+                self.resolve_awaiting_char();
+            }
+        }
+
+        if std::mem::take(&mut self.focus_lost) {
+            self.release_held_input(ctx);
+        }
+
+        // Releases the game swallowed, e.g. of Alt when alt-tabbing, would leave modifiers stuck.
+        let focused = unsafe { GetForegroundWindow() } == self.hwnd;
+        self.alter_modifiers(if focused {
+            get_async_modifiers()
+        } else {
+            Modifiers::NONE
+        });
+
+        self.screen_size = self.get_screen_size();
+
         RawInput {
             modifiers: self.modifiers.unwrap_or_default(),
             events: std::mem::take(&mut self.events),
@@ -264,8 +810,8 @@ impl InputCollector {
             max_texture_side: None,
             predicted_dt: 1. / 60.,
             hovered_files: vec![],
-            dropped_files: vec![],
-            focused: true,
+            dropped_files: std::mem::take(&mut self.dropped_files),
+            focused,
             viewport_id: ctx.viewport_id(),
             viewports: ctx.input(|i| i.raw.viewports.clone()),
         }
@@ -285,6 +831,8 @@ impl InputCollector {
         (time as f64) / 10_000_000.
     }
 
+    /// Size of the client area in pixels. Minimized windows have an empty one, which would clamp
+    /// every egui window to the corner, so the last non-empty size is kept then.
     #[inline]
     pub fn get_screen_size(&self) -> Pos2 {
         let mut rect = RECT::default();
@@ -292,19 +840,222 @@ impl InputCollector {
             let _ = GetClientRect(self.hwnd, &mut rect);
         }
 
-        Pos2::new(
+        let size = Pos2::new(
             (rect.right - rect.left) as f32,
             (rect.bottom - rect.top) as f32,
-        )
+        );
+        if size.x > 0. && size.y > 0. {
+            size
+        } else {
+            self.screen_size
+        }
+    }
+
+    fn zoom(&mut self, notches: f32) {
+        if self.wheel_zoom {
+            self.zoom_notches += notches;
+        } else {
+            self.events.push(Event::Zoom(if notches > 0. { 1.5 } else { 0.5 }));
+        }
+    }
+
+    /// Points scrolled by `notches` turns of the wheel, `horizontal` for tilting it.
+    fn scroll_distance(&self, notches: f32, horizontal: bool) -> f32 {
+        let per_notch = match self.scroll.unit {
+            ScrollUnit::SystemLines => match system_scroll_lines(horizontal) {
+                Some(lines) => lines as f32 * SCROLL_LINE_HEIGHT,
+                None => {
+                    let size = self.get_screen_rect().size();
+                    if horizontal {
+                        size.x
+                    } else {
+                        size.y
+                    }
+                }
+            },
+            ScrollUnit::Lines(lines) => lines * SCROLL_LINE_HEIGHT,
+            ScrollUnit::Points(points) => points,
+        };
+
+        notches * per_notch * self.scroll.multiplier
     }
 
     #[inline]
     pub fn get_screen_rect(&self) -> Rect {
+        let size = match self.render_resolution {
+            Some((width, height)) => egui::vec2(width as f32, height as f32),
+            None => self.get_screen_size().to_vec2(),
+        };
+
         Rect {
             min: Pos2::ZERO,
-            max: self.get_screen_size(),
+            max: (size / self.pixels_per_point).to_pos2(),
+        }
+    }
+}
+
+/// Key of the keyboard as Windows reports it, telling apart keys that egui's [`Key`] doesn't have
+/// or doesn't distinguish, like the numpad, F21 to F24 and media keys. Meant for keybinds,
+/// see [`crate::OpenGLApp::take_key_press`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCode {
+    pub vk: VIRTUAL_KEY,
+    /// Set for the right-hand Ctrl and Alt, numpad Enter and the navigation keys
+    /// between the main keys and the numpad, which share their virtual key with numpad keys.
+    pub extended: bool,
+}
+
+impl KeyCode {
+    /// Key of a `WM_KEYDOWN` or `WM_KEYUP`.
+    pub fn from_message(wparam: usize, lparam: isize) -> Self {
+        Self {
+            vk: VIRTUAL_KEY(wparam as _),
+            extended: lparam & (1 << 24) != 0,
+        }
+    }
+
+    /// The egui key reported for it, if any.
+    pub fn key(&self) -> Option<Key> {
+        get_key(self.vk.0 as _)
+    }
+
+    /// Whether the key is held right now. The extended flag isn't considered.
+    pub fn is_down(&self) -> bool {
+        unsafe { GetAsyncKeyState(self.vk.0 as _) < 0 }
+    }
+
+    /// Name of the key in the active keyboard layout, e.g. `"Num 5"`,
+    /// or its virtual key code for keys without one.
+    pub fn name(&self) -> String {
+        let mut buf = [0u16; 64];
+        let len = unsafe {
+            let scan_code = MapVirtualKeyW(self.vk.0 as _, MAPVK_VK_TO_VSC);
+            let lparam = (scan_code << 16 | (self.extended as u32) << 24) as i32;
+            GetKeyNameTextW(lparam, &mut buf)
+        };
+
+        if len > 0 {
+            String::from_utf16_lossy(&buf[..len as usize])
+        } else {
+            format!("Key {:#04X}", self.vk.0)
+        }
+    }
+}
+
+/// Mouse movement accumulated since it was last taken, see [`crate::OpenGLApp::take_mouse_motion`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MouseMotion {
+    /// Relative movement in pixels. Raw input when the game registered for it, so it keeps counting
+    /// while the game recenters the cursor, cursor movement otherwise.
+    pub delta: Vec2,
+    /// Wheel movement in notches, positive away from the user and to the right.
+    pub wheel: Vec2,
+}
+
+/// Accumulates [`MouseMotion`] from the window's messages, whether egui uses them or not.
+#[derive(Debug, Default)]
+pub struct MouseTracker {
+    motion: MouseMotion,
+    last_pos: Option<Pos2>,
+    /// Set once `WM_INPUT` reported mouse input, cursor messages are ignored from then on.
+    raw_input: bool,
+}
+
+impl MouseTracker {
+    pub const fn new() -> Self {
+        Self {
+            motion: MouseMotion {
+                delta: Vec2::ZERO,
+                wheel: Vec2::ZERO,
+            },
+            last_pos: None,
+            raw_input: false,
+        }
+    }
+
+    pub fn process(&mut self, umsg: u32, wparam: usize, lparam: isize) {
+        match umsg {
+            WM_INPUT => self.on_raw_input(lparam),
+            WM_MOUSEMOVE => {
+                let pos = get_pos(lparam);
+                if let Some(last) = self.last_pos.replace(pos) {
+                    if !self.raw_input {
+                        self.motion.delta += pos - last;
+                    }
+                }
+            }
+            WM_MOUSEWHEEL if !self.raw_input => {
+                self.motion.wheel.y += wheel_notches(wparam >> 16);
+            }
+            WM_MOUSEHWHEEL if !self.raw_input => {
+                self.motion.wheel.x += wheel_notches(wparam >> 16);
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the motion accumulated so far and starts over.
+    pub fn take(&mut self) -> MouseMotion {
+        std::mem::take(&mut self.motion)
+    }
+
+    fn on_raw_input(&mut self, lparam: isize) {
+        let mut raw = RAWINPUT::default();
+        let mut size = std::mem::size_of::<RAWINPUT>() as u32;
+        let read = unsafe {
+            GetRawInputData(
+                HRAWINPUT(lparam),
+                RID_INPUT,
+                Some(&mut raw as *mut _ as _),
+                &mut size,
+                std::mem::size_of::<RAWINPUTHEADER>() as _,
+            )
+        };
+        if read == u32::MAX || raw.header.dwType != RIM_TYPEMOUSE.0 {
+            return;
         }
+
+        self.raw_input = true;
+        let mouse = unsafe { raw.data.mouse };
+        if mouse.usFlags & MOUSE_MOVE_ABSOLUTE == 0 {
+            self.motion.delta += Vec2::new(mouse.lLastX as f32, mouse.lLastY as f32);
+        }
+
+        let buttons = unsafe { mouse.Anonymous.Anonymous };
+        let flags = buttons.usButtonFlags as u32;
+        if flags & RI_MOUSE_WHEEL != 0 {
+            self.motion.wheel.y += wheel_notches(buttons.usButtonData as _);
+        }
+        if flags & RI_MOUSE_HWHEEL != 0 {
+            self.motion.wheel.x += wheel_notches(buttons.usButtonData as _);
+        }
+    }
+}
+
+/// Converts the signed 16-bit wheel delta in the low word of `data` into notches.
+fn wheel_notches(data: usize) -> f32 {
+    data as i16 as f32 / WHEEL_DELTA as f32
+}
+
+/// Lines, or characters when `horizontal`, the user set a wheel notch to scroll. `None` means a page.
+fn system_scroll_lines(horizontal: bool) -> Option<u32> {
+    let action = if horizontal {
+        SPI_GETWHEELSCROLLCHARS
+    } else {
+        SPI_GETWHEELSCROLLLINES
+    };
+
+    let mut lines = 3u32;
+    unsafe {
+        let _ = SystemParametersInfoW(
+            action,
+            0,
+            Some(&mut lines as *mut u32 as _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        );
     }
+
+    (lines != WHEEL_PAGESCROLL).then_some(lines)
 }
 
 fn get_pos(lparam: isize) -> Pos2 {
@@ -316,7 +1067,7 @@ fn get_pos(lparam: isize) -> Pos2 {
 
 fn get_mouse_modifiers(wparam: usize) -> Modifiers {
     Modifiers {
-        alt: false,
+        alt: unsafe { GetKeyState(VK_MENU.0 as _) } < 0,
         ctrl: (wparam & MK_CONTROL.0 as usize) != 0,
         shift: (wparam & MK_SHIFT.0 as usize) != 0,
         mac_cmd: false,
@@ -324,15 +1075,30 @@ fn get_mouse_modifiers(wparam: usize) -> Modifiers {
     }
 }
 
-fn get_key_modifiers(msg: u32) -> Modifiers {
-    let ctrl = unsafe { GetAsyncKeyState(VK_CONTROL.0 as _) != 0 };
-    let shift = unsafe { GetAsyncKeyState(VK_LSHIFT.0 as _) != 0 };
+/// Whether a `WM_KEYDOWN` was sent by keyboard auto-repeat, i.e. the key was already down.
+pub(crate) fn is_key_repeat(lparam: isize) -> bool {
+    // `KF_REPEAT` is a flag of the high word.
+    (lparam >> 16) as u32 & KF_REPEAT != 0
+}
+
+/// Modifiers held when the message being processed was sent.
+fn get_key_modifiers() -> Modifiers {
+    modifiers_from(|key| unsafe { GetKeyState(key.0 as _) } < 0)
+}
+
+/// Modifiers held right now, regardless of the messages processed so far.
+fn get_async_modifiers() -> Modifiers {
+    modifiers_from(|key| unsafe { GetAsyncKeyState(key.0 as _) } < 0)
+}
+
+fn modifiers_from(is_down: impl Fn(VIRTUAL_KEY) -> bool) -> Modifiers {
+    let ctrl = is_down(VK_CONTROL);
 
     Modifiers {
-        alt: msg == WM_SYSKEYDOWN,
+        alt: is_down(VK_MENU),
         mac_cmd: false,
         command: ctrl,
-        shift,
+        shift: is_down(VK_SHIFT),
         ctrl,
     }
 }
@@ -342,6 +1108,8 @@ fn get_key(wparam: usize) -> Option<Key> {
         0x30..=0x39 => unsafe { Some(std::mem::transmute::<_, Key>(wparam as u8 - 0x10)) }, // 0-9
         0x41..=0x5A => unsafe { Some(std::mem::transmute::<_, Key>(wparam as u8 - 0x17)) }, // A-Z
         0x70..=0x83 => unsafe { Some(std::mem::transmute::<_, Key>(wparam as u8 - 0x2C)) }, // F1-F20
+        // egui has no numpad keys, they're reported as the keys typing the same characters.
+        0x60..=0x69 => get_key(wparam - 0x30), // Numpad 0-9
         _ => match VIRTUAL_KEY(wparam as u16) {
             VK_DOWN => Some(Key::ArrowDown),
             VK_LEFT => Some(Key::ArrowLeft),
@@ -358,6 +1126,18 @@ fn get_key(wparam: usize) -> Option<Key> {
             VK_END => Some(Key::End),
             VK_PRIOR => Some(Key::PageUp),
             VK_NEXT => Some(Key::PageDown),
+            VK_ADD => Some(Key::Plus),
+            VK_SUBTRACT | VK_OEM_MINUS => Some(Key::Minus),
+            VK_DECIMAL | VK_OEM_PERIOD => Some(Key::Period),
+            VK_DIVIDE | VK_OEM_2 => Some(Key::Slash),
+            VK_OEM_PLUS => Some(Key::Equals),
+            VK_OEM_COMMA => Some(Key::Comma),
+            // Keys of the US layout, other layouts put different characters on them.
+            VK_OEM_1 => Some(Key::Semicolon),
+            VK_OEM_3 => Some(Key::Backtick),
+            VK_OEM_4 => Some(Key::OpenBracket),
+            VK_OEM_5 => Some(Key::Backslash),
+            VK_OEM_6 => Some(Key::CloseBracket),
             _ => None,
         },
     }
@@ -373,8 +1153,185 @@ fn test_key_map() {
 
     assert_eq!(get_key(0x70), Some(Key::F1));
     assert_eq!(get_key(0x83), Some(Key::F20));
+
+    assert_eq!(get_key(0x60), Some(Key::Num0));
+    assert_eq!(get_key(0x69), Some(Key::Num9));
+    assert_eq!(get_key(0x6B), Some(Key::Plus));
+    assert_eq!(get_key(0xBA), Some(Key::Semicolon));
+    assert_eq!(get_key(0x86), None); // F23
+}
+
+#[test]
+fn test_key_repeat() {
+    // Scan code of A, first press and auto-repeat.
+    assert!(!is_key_repeat(0x001E_0001));
+    assert!(is_key_repeat(0x401E_0001));
+}
+
+#[test]
+fn test_mouse_tracker() {
+    let mut tracker = MouseTracker::default();
+    tracker.process(WM_MOUSEMOVE, 0, 0x0014_000A);
+    tracker.process(WM_MOUSEMOVE, 0, 0x000A_0019);
+    tracker.process(WM_MOUSEWHEEL, (-240i16 as u16 as usize) << 16, 0);
+
+    let motion = tracker.take();
+    assert_eq!(motion.delta, Vec2::new(15., -10.));
+    assert_eq!(motion.wheel, Vec2::new(0., -2.));
+    assert_eq!(tracker.take(), MouseMotion::default());
+}
+
+#[test]
+fn test_surrogate_pairs() {
+    let mut collector = InputCollector::new(HWND(0));
+    for unit in [0xD83D, 0xDE00, 0xDE00, 'a' as usize] {
+        collector.process(WM_CHAR, unit, 0);
+    }
+
+    // The unpaired low surrogate is dropped.
+    assert_eq!(
+        collector.events,
+        [Event::Text("\u{1F600}".into()), Event::Text("a".into())]
+    );
 }
 
-fn get_clipboard_text() -> Option<String> {
-    WindowsClipboardContext.get_contents().ok()
+enum KeyText {
+    Text(String),
+    /// Dead key, with the keyboard state to replay it with.
+    Dead(Box<[u8; 256]>),
+}
+
+/// Translates a key press into text with the active keyboard layout, without touching dead key state.
+/// A `dead_key` pressed before is replayed first, so the text combines with its accent.
+fn translate_key(wparam: usize, lparam: isize, dead_key: Option<&DeadKey>) -> Option<KeyText> {
+    let mut state = [0u8; 256];
+    let mut buf = [0u16; 8];
+    let scan_code = |lparam: isize| ((lparam >> 16) & 0xFF) as u32;
+
+    let len = unsafe {
+        GetKeyboardState(&mut state).ok()?;
+        let layout = GetKeyboardLayout(0);
+
+        // Replaying the dead key stores it in the keyboard state, the key press then consumes it again.
+        let flags = match dead_key {
+            Some(dead) => {
+                ToUnicodeEx(
+                    dead.wparam as _,
+                    scan_code(dead.lparam),
+                    &dead.state,
+                    &mut buf,
+                    0,
+                    layout,
+                );
+                0
+            }
+            None => TOUNICODE_NO_STATE_CHANGE,
+        };
+
+        ToUnicodeEx(wparam as _, scan_code(lparam), &state, &mut buf, flags, layout)
+    };
+
+    match len {
+        ..=-1 => Some(KeyText::Dead(Box::new(state))),
+        0 => None,
+        len => {
+            let text = String::from_utf16_lossy(&buf[..len as usize]);
+            (!text.chars().any(char::is_control)).then_some(KeyText::Text(text))
+        }
+    }
+}
+
+fn is_modifier_key(wparam: usize) -> bool {
+    matches!(
+        VIRTUAL_KEY(wparam as _),
+        VK_SHIFT | VK_CONTROL | VK_MENU | VK_LSHIFT | VK_RSHIFT | VK_LCONTROL | VK_RCONTROL
+            | VK_LMENU | VK_RMENU | VK_CAPITAL
+    )
+}
+
+/// Paths of the files of a `WM_DROPFILES`, the handle is left open.
+fn get_dropped_files(hdrop: HDROP) -> Vec<DroppedFile> {
+    let count = unsafe { DragQueryFileW(hdrop, u32::MAX, None) };
+    (0..count)
+        .map(|i| {
+            let len = unsafe { DragQueryFileW(hdrop, i, None) } as usize;
+            let mut buf = vec![0; len + 1];
+            let len = unsafe { DragQueryFileW(hdrop, i, Some(&mut buf)) } as usize;
+
+            let path = PathBuf::from(OsString::from_wide(&buf[..len]));
+            DroppedFile {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                path: Some(path),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+fn get_clipboard_text(hwnd: HWND) -> Option<String> {
+    clipboard::get_text(hwnd).ok().flatten()
+}
+
+#[test]
+fn test_scroll_config() {
+    let mut collector = InputCollector::new(HWND(0));
+    collector.set_scroll_config(ScrollConfig {
+        unit: ScrollUnit::Lines(2.),
+        multiplier: 1.5,
+    });
+    collector.process(WM_MOUSEWHEEL, (-120i16 as u16 as usize) << 16, 0);
+    collector.set_scroll_config(ScrollConfig {
+        unit: ScrollUnit::Points(50.),
+        multiplier: 1.,
+    });
+    collector.process(WM_MOUSEHWHEEL, 240 << 16, 0);
+
+    let scrolls: Vec<_> = collector
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Scroll(delta) => Some(*delta),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        scrolls,
+        [Vec2::new(0., -3. * SCROLL_LINE_HEIGHT), Vec2::new(100., 0.)]
+    );
+}
+
+#[test]
+fn test_wheel_zoom() {
+    let ctx = Context::default();
+    let mut collector = InputCollector::new(HWND(0));
+    let ctrl_wheel = |delta: i16| (delta as u16 as usize) << 16 | MK_CONTROL.0 as usize;
+
+    collector.process(WM_MOUSEWHEEL, ctrl_wheel(120), 0);
+    assert_eq!(collector.events, [Event::Zoom(1.5)]);
+
+    // Whole notches are applied, the rest waits for the next frame.
+    collector.set_wheel_zoom(true);
+    collector.process(WM_MOUSEWHEEL, ctrl_wheel(240), 0);
+    collector.process(WM_MOUSEWHEEL, ctrl_wheel(60), 0);
+    collector.apply_wheel_zoom(&ctx);
+    assert_eq!(collector.zoom_notches, 0.5);
+    assert_eq!(collector.events.len(), 1);
+}
+
+#[test]
+fn test_has_input() {
+    let ctx = Context::default();
+    let mut collector = InputCollector::new(HWND(0));
+    assert!(!collector.has_input());
+
+    collector.process(WM_MOUSEMOVE, 0, 0);
+    assert!(collector.has_input());
+    collector.collect_input(&ctx);
+    assert!(!collector.has_input());
+
+    collector.lose_focus();
+    assert!(collector.has_input());
 }
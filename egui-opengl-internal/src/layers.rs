@@ -0,0 +1,178 @@
+use egui::Context;
+
+#[cfg(feature = "parking-lot")]
+use parking_lot::Mutex;
+#[cfg(feature = "spin-lock")]
+use spin::lock_api::Mutex;
+
+type DrawFn<T> = Box<dyn FnMut(&Context, &mut T) + 'static>;
+
+struct Layer<T> {
+    /// Tells a layer apart from one registered later under the same name.
+    id: u64,
+    name: String,
+    z_order: i32,
+    visible: bool,
+    /// Taken out while the layer is drawn, so layers can be changed from within one.
+    draw: Option<DrawFn<T>>,
+}
+
+/// Named UI layers drawn after the UI closure, in z-order. Lives outside of the app lock,
+/// so layers can be registered before init and from within the UI.
+pub struct Layers<T> {
+    layers: Mutex<Vec<Layer<T>>>,
+    next_id: Mutex<u64>,
+}
+
+impl<T> Layers<T> {
+    pub const fn new() -> Self {
+        Self {
+            layers: Mutex::new(vec![]),
+            next_id: Mutex::new(0),
+        }
+    }
+
+    /// Registers a visible layer at z-order `0`, replacing the layer called `name` if there is one.
+    pub fn add(&self, name: String, draw: DrawFn<T>) {
+        let id = {
+            let mut next_id = self.next_id.lock();
+            *next_id += 1;
+            *next_id
+        };
+
+        let mut layers = self.layers.lock();
+        layers.retain(|layer| layer.name != name);
+        layers.push(Layer {
+            id,
+            name,
+            z_order: 0,
+            visible: true,
+            draw: Some(draw),
+        });
+    }
+
+    pub fn remove(&self, name: &str) -> bool {
+        let mut layers = self.layers.lock();
+        let len = layers.len();
+        layers.retain(|layer| layer.name != name);
+        layers.len() != len
+    }
+
+    pub fn clear(&self) {
+        self.layers.lock().clear();
+    }
+
+    /// Returns `false` if there is no layer called `name`.
+    pub fn set_visible(&self, name: &str, visible: bool) -> bool {
+        self.with_layer(name, |layer| layer.visible = visible)
+    }
+
+    pub fn is_visible(&self, name: &str) -> Option<bool> {
+        self.layers
+            .lock()
+            .iter()
+            .find(|layer| layer.name == name)
+            .map(|layer| layer.visible)
+    }
+
+    /// Returns `false` if there is no layer called `name`.
+    pub fn set_z_order(&self, name: &str, z_order: i32) -> bool {
+        self.with_layer(name, |layer| layer.z_order = z_order)
+    }
+
+    /// Names of the layers in the order they are drawn.
+    pub fn names(&self) -> Vec<String> {
+        let mut layers: Vec<_> = self
+            .layers
+            .lock()
+            .iter()
+            .map(|layer| (layer.z_order, layer.name.clone()))
+            .collect();
+        layers.sort_by_key(|(z_order, _)| *z_order);
+        layers.into_iter().map(|(_, name)| name).collect()
+    }
+
+    fn with_layer(&self, name: &str, f: impl FnOnce(&mut Layer<T>)) -> bool {
+        match self
+            .layers
+            .lock()
+            .iter_mut()
+            .find(|layer| layer.name == name)
+        {
+            Some(layer) => {
+                f(layer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Draws the visible layers from the lowest z-order up, layers with the same z-order
+    /// in the order they were added.
+    pub fn draw(&self, ctx: &Context, state: &mut T) {
+        let mut drawing: Vec<_> = self
+            .layers
+            .lock()
+            .iter_mut()
+            .filter(|layer| layer.visible)
+            .filter_map(|layer| Some((layer.z_order, layer.id, layer.draw.take()?)))
+            .collect();
+        drawing.sort_by_key(|(z_order, ..)| *z_order);
+
+        for (_, _, draw) in &mut drawing {
+            draw(ctx, state);
+        }
+
+        // Layers removed or replaced while drawing are dropped.
+        let mut layers = self.layers.lock();
+        for (_, id, draw) in drawing {
+            if let Some(layer) = layers.iter_mut().find(|layer| layer.id == id) {
+                layer.draw = Some(draw);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_layers() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let layers = Layers::<Vec<&str>>::new();
+    let ctx = Context::default();
+    let mut drawn = vec![];
+
+    layers.add("b".into(), Box::new(|_, drawn| drawn.push("b")));
+    layers.add("a".into(), Box::new(|_, drawn| drawn.push("a")));
+    layers.add("c".into(), Box::new(|_, drawn| drawn.push("c")));
+    assert!(layers.set_z_order("a", -1));
+    assert!(layers.set_visible("c", false));
+    assert!(!layers.set_visible("d", false));
+    assert_eq!(layers.is_visible("c"), Some(false));
+    assert_eq!(layers.names(), ["a", "b", "c"]);
+
+    layers.draw(&ctx, &mut drawn);
+    assert_eq!(drawn, ["a", "b"]);
+
+    // A layer replacing itself while drawn keeps the new closure.
+    let layers = Rc::new(layers);
+    let inner = Rc::downgrade(&layers);
+    let replaced = Rc::new(RefCell::new(false));
+    let flag = replaced.clone();
+    layers.add(
+        "b".into(),
+        Box::new(move |_, _| {
+            let flag = flag.clone();
+            if let Some(layers) = inner.upgrade() {
+                layers.add("b".into(), Box::new(move |_, _| *flag.borrow_mut() = true));
+            }
+        }),
+    );
+    assert!(layers.remove("a"));
+    assert!(!layers.remove("a"));
+
+    drawn.clear();
+    layers.draw(&ctx, &mut drawn);
+    layers.draw(&ctx, &mut drawn);
+    assert!(*replaced.borrow());
+    assert!(drawn.is_empty());
+}
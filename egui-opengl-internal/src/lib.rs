@@ -19,6 +19,16 @@ macro_rules! panic_msg {
     };
 }
 
+/// Writes a diagnostic message to stderr and the file logger, if one was set up with
+/// [`utils::init_file_logger`]. Compiled out when feature `no-msgs` is present.
+macro_rules! log {
+    ($($t:tt)*) => {
+        if !cfg!(feature = "no-msgs") {
+            $crate::logger::write(format_args!($($t)*))
+        }
+    };
+}
+
 #[cfg(all(feature = "parking-lot", feature = "spin-lock"))]
 compile_error!("Only one of features `parking-lot`, `spin-lock` must be enabled.");
 
@@ -26,9 +36,99 @@ compile_error!("Only one of features `parking-lot`, `spin-lock` must be enabled.
 compile_error!("One of the features `parking-lot`, `spin-lock` must be enabled.");
 
 mod app;
-pub use app::OpenGLApp;
+pub use app::{
+    ContextMode, InputBlocking, MessageCapture, OpenGLApp, ThreadPolicy, WindowPolicy, WndProcResult,
+};
+
+mod builder;
+pub use builder::OpenGLAppBuilder;
+
+mod error;
+pub use error::Error;
 
-mod input;
+mod event;
+pub use event::AppEvent;
+
+mod backoff;
+mod buffer;
+mod clipboard;
+mod compositor;
+#[cfg(feature = "gl-debug")]
+mod debug;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod gl_state;
+mod gl_version;
+pub use gl_version::GlVersion;
+mod offscreen;
+
+mod config;
+pub use config::{streamer_mode, Config};
+
+#[cfg(feature = "overlay")]
+pub mod overlay;
+#[cfg(feature = "overlay")]
+pub use overlay::{Overlay, OverlayConfig};
+#[cfg(feature = "macros")]
+pub use egui_opengl_internal_macros::overlay_main;
+
+/// Used by the code [`overlay_main`] generates.
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod __private {
+    pub use windows::Win32::{
+        Foundation::{BOOL, HMODULE},
+        System::SystemServices::DLL_PROCESS_ATTACH,
+    };
+}
+
+#[cfg(feature = "mouse-unlock")]
+pub mod mouse_unlock;
+
+mod layers;
+mod logger;
+mod panic_guard;
+#[cfg(feature = "persistence")]
+mod memory;
+#[cfg(feature = "persistence")]
+mod state;
+
+mod notifications;
+pub use notifications::{Notification, NotificationLevel, Notifier};
+
+#[cfg(feature = "update-check")]
+mod update;
+#[cfg(feature = "update-check")]
+pub use update::UpdateManifest;
+
+mod tasks;
+pub use tasks::TaskSender;
+
+mod subclass;
+
+mod stats;
+pub use stats::{FrameTiming, RenderStats};
+
+mod surface;
+pub use surface::{BlendMode, PixelFormatInfo, SurfaceConfig};
+
+#[cfg(feature = "golden")]
+pub mod golden;
+#[cfg(feature = "golden")]
+pub mod workload;
+
+mod compressed;
+pub use compressed::CompressedFormat;
+#[cfg(feature = "dds")]
+mod dds;
+#[cfg(feature = "dds")]
+pub use dds::{DdsError, DdsImage};
+
+pub mod input;
+pub use input::{KeyCode, MouseMotion, ScrollConfig, ScrollUnit, TextInputSource, ZoomConfig};
 mod painter;
+pub use painter::{CallbackFn, Painter, PreparedFrame};
+
 mod shader;
+pub use shader::{CustomShader, ProgramLocations, ShaderError};
 pub mod utils;
\ No newline at end of file
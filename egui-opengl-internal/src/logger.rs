@@ -0,0 +1,161 @@
+//! Writes the crate's log messages and panics to a file, for builds without a console.
+//! See [`crate::utils::init_file_logger`].
+
+use std::{
+    fmt::Arguments,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Once,
+};
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+#[cfg(feature = "parking-lot")]
+use parking_lot::Mutex;
+#[cfg(feature = "spin-lock")]
+use spin::lock_api::Mutex;
+
+/// Size at which the log is moved to `<path>.1`, replacing the one moved before.
+const MAX_SIZE: u64 = 1024 * 1024;
+const FILE_NAME: &str = "egui-opengl-internal.log";
+
+static LOGGER: Mutex<Option<FileLogger>> = Mutex::new(None);
+
+struct FileLogger {
+    path: PathBuf,
+    /// `None` if reopening it after rotating failed.
+    file: Option<File>,
+    size: u64,
+    max_size: u64,
+}
+
+impl FileLogger {
+    fn open(path: PathBuf, max_size: u64) -> std::io::Result<Self> {
+        let file = open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file: Some(file),
+            size,
+            max_size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(line.as_bytes())?;
+            self.size += line.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+
+        // Windows can't rename open files.
+        self.file = None;
+        let _ = std::fs::remove_file(&rotated);
+        let renamed = std::fs::rename(&self.path, &rotated);
+
+        self.file = Some(open(&self.path)?);
+        self.size = 0;
+        renamed
+    }
+}
+
+fn open(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Appends the crate's log messages and panics to the file at `path`, in addition to stderr.
+/// The file is moved to `<path>.1` once it reaches 1 MiB, so at most two files are kept.
+pub fn init_file_logger(path: impl Into<PathBuf>) -> std::io::Result<()> {
+    let logger = FileLogger::open(path.into(), MAX_SIZE)?;
+    *LOGGER.lock() = Some(logger);
+    install_panic_hook();
+    Ok(())
+}
+
+/// Like [`init_file_logger`], with `egui-opengl-internal.log` next to the game's executable,
+/// or in `%TEMP%` if that directory isn't writable. Returns the path logged to.
+pub fn init_default_file_logger() -> std::io::Result<PathBuf> {
+    let next_to_exe = std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(FILE_NAME)));
+
+    if let Some(path) = next_to_exe {
+        if init_file_logger(&path).is_ok() {
+            return Ok(path);
+        }
+    }
+
+    let path = std::env::temp_dir().join(FILE_NAME);
+    init_file_logger(&path)?;
+    Ok(path)
+}
+
+/// Writes a message of the `log!` macro to stderr and the log file.
+pub fn write(args: Arguments) {
+    eprintln!("{args}");
+    write_file(args);
+}
+
+fn write_file(args: Arguments) {
+    let mut logger = LOGGER.lock();
+    if let Some(logger) = logger.as_mut() {
+        let line = format!("{} {args}\n", timestamp());
+        // Nowhere left to report failures to.
+        let _ = logger.write_line(&line);
+    }
+}
+
+fn timestamp() -> String {
+    let time = unsafe { GetLocalTime() };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        time.wYear,
+        time.wMonth,
+        time.wDay,
+        time.wHour,
+        time.wMinute,
+        time.wSecond,
+        time.wMilliseconds
+    )
+}
+
+/// Logs panics before the previously installed hook handles them.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            write_file(format_args!("{info}"));
+            previous(info);
+        }));
+    });
+}
+
+#[test]
+fn test_rotation() {
+    let path = std::env::temp_dir().join("egui-opengl-internal-test.log");
+    let mut rotated = path.clone().into_os_string();
+    rotated.push(".1");
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&rotated);
+
+    let mut logger = FileLogger::open(path.clone(), 16).unwrap();
+    logger.write_line("0123456789\n").unwrap();
+    logger.write_line("abcdefghij\n").unwrap();
+    drop(logger);
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "abcdefghij\n");
+    assert_eq!(std::fs::read_to_string(&rotated).unwrap(), "0123456789\n");
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&rotated);
+}
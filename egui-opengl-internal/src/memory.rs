@@ -0,0 +1,99 @@
+//! Keeps egui's memory, like window positions, collapsed headers and scroll offsets, between launches.
+//! See [`crate::OpenGLApp::set_memory_path`].
+
+use crate::Error;
+use egui::Context;
+use std::{
+    io::ErrorKind,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// How often the memory is saved while rendering, like eframe does. The game may exit without
+/// the app being destroyed.
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Replaces the memory of `ctx` with the one saved at `path`, a missing file keeps it.
+/// The options, like the style and zoom factor, stay as configured.
+pub fn load(ctx: &Context, path: &Path) -> Result<(), Error> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(Error::Memory(e)),
+    };
+
+    let mut memory: egui::Memory =
+        ron::from_str(&text).map_err(|e| Error::Memory(invalid_data(e)))?;
+    ctx.memory_mut(|mem| {
+        memory.options = std::mem::take(&mut mem.options);
+        *mem = memory;
+    });
+    Ok(())
+}
+
+/// Saves the memory of `ctx` at `path` through [`write_file`].
+pub fn save(ctx: &Context, path: &Path) -> Result<(), Error> {
+    let text = ctx
+        .memory(ron::to_string)
+        .map_err(|e| Error::Memory(invalid_data(e)))?;
+    write_file(path, &text).map_err(Error::Memory)
+}
+
+/// Writes to a temporary file first and moves it over `path`, so a crash while saving
+/// doesn't lose what was saved before. Shared with the persisted state.
+pub(crate) fn write_file(path: &Path, text: &str) -> std::io::Result<()> {
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, text)?;
+    std::fs::rename(&temp, path)
+}
+
+/// Error of files that exist but don't deserialize.
+pub(crate) fn invalid_data(e: impl std::error::Error + Send + Sync + 'static) -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidData, e)
+}
+
+/// Tracks when the memory was saved last.
+pub struct Autosave {
+    last: Instant,
+}
+
+impl Autosave {
+    pub fn new() -> Self {
+        Self {
+            last: Instant::now(),
+        }
+    }
+
+    /// Returns whether the memory should be saved now and restarts the interval if so.
+    pub fn due(&mut self) -> bool {
+        let due = self.last.elapsed() >= AUTOSAVE_INTERVAL;
+        if due {
+            self.last = Instant::now();
+        }
+        due
+    }
+}
+
+#[test]
+fn test_memory_roundtrip() {
+    let path = std::env::temp_dir().join("egui-opengl-internal-test-memory.ron");
+    let _ = std::fs::remove_file(&path);
+
+    let ctx = Context::default();
+    load(&ctx, &path).unwrap();
+
+    let id = egui::Id::new("header");
+    ctx.memory_mut(|mem| mem.data.insert_persisted(id, 42u32));
+    save(&ctx, &path).unwrap();
+
+    let restored = Context::default();
+    load(&restored, &path).unwrap();
+    assert_eq!(
+        restored.memory_mut(|mem| mem.data.get_persisted::<u32>(id)),
+        Some(42)
+    );
+
+    std::fs::write(&path, "not ron").unwrap();
+    assert!(matches!(load(&restored, &path), Err(Error::Memory(_))));
+    let _ = std::fs::remove_file(&path);
+}
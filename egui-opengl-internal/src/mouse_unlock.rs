@@ -0,0 +1,190 @@
+//! Frees the mouse of games that confine, hide and recenter it, enabled with the `mouse-unlock` feature.
+//! While unlocked, the game's `ClipCursor`, `ShowCursor` and `SetCursorPos` calls are recorded instead of
+//! applied, and the clip rect and cursor visibility the game asked for are restored when locking again.
+//! Usually driven by [`crate::OpenGLApp::set_mouse_unlock`].
+
+use crate::utils;
+use once_cell::sync::OnceCell;
+use retour::GenericDetour;
+use std::ffi::CString;
+use windows::{
+    core::PCSTR,
+    Win32::{
+        Foundation::{BOOL, RECT},
+        System::LibraryLoader::GetProcAddress,
+        UI::WindowsAndMessaging::GetClipCursor,
+    },
+};
+
+#[cfg(feature = "parking-lot")]
+use parking_lot::Mutex;
+#[cfg(feature = "spin-lock")]
+use spin::lock_api::Mutex;
+
+type FnSetCursorPos = unsafe extern "system" fn(i32, i32) -> BOOL;
+type FnClipCursor = unsafe extern "system" fn(*const RECT) -> BOOL;
+type FnShowCursor = unsafe extern "system" fn(BOOL) -> i32;
+
+struct Hooks {
+    set_cursor_pos: GenericDetour<FnSetCursorPos>,
+    clip_cursor: GenericDetour<FnClipCursor>,
+    show_cursor: GenericDetour<FnShowCursor>,
+}
+
+struct State {
+    unlocked: bool,
+    /// Clip rect the game asked for, reapplied when locking.
+    clip: Option<RECT>,
+    /// Cursor display counter of the game when unlocking.
+    show_count: i32,
+    /// `ShowCursor` calls the game made while unlocked, replayed when locking.
+    show_delta: i32,
+    /// `ShowCursor(TRUE)` calls it took to show the cursor, undone when locking.
+    forced_shows: i32,
+}
+
+static HOOKS: OnceCell<Hooks> = OnceCell::new();
+static STATE: Mutex<State> = Mutex::new(State {
+    unlocked: false,
+    clip: None,
+    show_count: 0,
+    show_delta: 0,
+    forced_shows: 0,
+});
+
+/// Hooks the cursor functions of `user32.dll`. Does nothing if they're hooked already.
+pub fn install() -> Result<(), retour::Error> {
+    let hooks = HOOKS.get_or_try_init(|| unsafe {
+        Ok::<_, retour::Error>(Hooks {
+            set_cursor_pos: GenericDetour::new(
+                std::mem::transmute::<*const (), FnSetCursorPos>(user32_proc("SetCursorPos")?),
+                hk_set_cursor_pos,
+            )?,
+            clip_cursor: GenericDetour::new(
+                std::mem::transmute::<*const (), FnClipCursor>(user32_proc("ClipCursor")?),
+                hk_clip_cursor,
+            )?,
+            show_cursor: GenericDetour::new(
+                std::mem::transmute::<*const (), FnShowCursor>(user32_proc("ShowCursor")?),
+                hk_show_cursor,
+            )?,
+        })
+    })?;
+
+    unsafe {
+        hooks.set_cursor_pos.enable()?;
+        hooks.clip_cursor.enable()?;
+        hooks.show_cursor.enable()
+    }
+}
+
+/// Locks the mouse again and removes the hooks.
+pub fn uninstall() {
+    set_unlocked(false);
+
+    if let Some(hooks) = HOOKS.get() {
+        let result = unsafe {
+            hooks
+                .set_cursor_pos
+                .disable()
+                .and(hooks.clip_cursor.disable())
+                .and(hooks.show_cursor.disable())
+        };
+        if let Err(e) = result {
+            log!("Failed to unhook the cursor functions: {e}");
+        }
+    }
+}
+
+pub fn is_unlocked() -> bool {
+    STATE.lock().unlocked
+}
+
+/// Frees or confines the mouse. The cursor display counter belongs to the calling thread's input queue,
+/// so call this from the window's thread, e.g. from its window procedure. Requires [`install`].
+pub fn set_unlocked(unlocked: bool) {
+    let Some(hooks) = HOOKS.get() else {
+        return;
+    };
+
+    let mut state = STATE.lock();
+    if state.unlocked == unlocked {
+        return;
+    }
+    state.unlocked = unlocked;
+
+    unsafe {
+        if unlocked {
+            let mut clip = RECT::default();
+            state.clip = GetClipCursor(&mut clip).ok().map(|_| clip);
+            hooks.clip_cursor.call(std::ptr::null());
+
+            let mut count = hooks.show_cursor.call(BOOL(1));
+            state.show_count = count - 1;
+            state.show_delta = 0;
+            state.forced_shows = 1;
+            while count < 0 {
+                count = hooks.show_cursor.call(BOOL(1));
+                state.forced_shows += 1;
+            }
+        } else {
+            for _ in 0..state.forced_shows {
+                hooks.show_cursor.call(BOOL(0));
+            }
+            for _ in 0..state.show_delta.abs() {
+                hooks.show_cursor.call(BOOL((state.show_delta > 0) as _));
+            }
+
+            let clip = state
+                .clip
+                .as_ref()
+                .map_or(std::ptr::null(), |clip| clip as *const _);
+            hooks.clip_cursor.call(clip);
+        }
+    }
+}
+
+unsafe fn user32_proc(name: &str) -> Result<*const (), retour::Error> {
+    let name = CString::new(name).expect("Function names have no nul bytes");
+    GetProcAddress(
+        utils::get_module("user32.dll"),
+        PCSTR::from_raw(name.as_ptr() as *const u8),
+    )
+    .map(|proc| proc as *const ())
+    .ok_or(retour::Error::NotExecutable)
+}
+
+fn hooks() -> &'static Hooks {
+    expect!(HOOKS.get(), "Cursor hooks are missing")
+}
+
+unsafe extern "system" fn hk_set_cursor_pos(x: i32, y: i32) -> BOOL {
+    // Recentering would keep yanking the cursor away from the menu.
+    if STATE.lock().unlocked {
+        return BOOL(1);
+    }
+
+    hooks().set_cursor_pos.call(x, y)
+}
+
+unsafe extern "system" fn hk_clip_cursor(rect: *const RECT) -> BOOL {
+    let mut state = STATE.lock();
+    if state.unlocked {
+        state.clip = rect.as_ref().copied();
+        return BOOL(1);
+    }
+    drop(state);
+
+    hooks().clip_cursor.call(rect)
+}
+
+unsafe extern "system" fn hk_show_cursor(show: BOOL) -> i32 {
+    let mut state = STATE.lock();
+    if state.unlocked {
+        state.show_delta += if show.as_bool() { 1 } else { -1 };
+        return state.show_count + state.show_delta;
+    }
+    drop(state);
+
+    hooks().show_cursor.call(show)
+}
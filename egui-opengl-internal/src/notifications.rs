@@ -0,0 +1,255 @@
+use egui::{Align2, Area, Color32, Context, Frame, Id, Order, RichText};
+use once_cell::sync::OnceCell;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "parking-lot")]
+use parking_lot::Mutex;
+#[cfg(feature = "spin-lock")]
+use spin::lock_api::Mutex;
+
+/// How long a notification stays on screen unless configured otherwise.
+const DEFAULT_DURATION: Duration = Duration::from_secs(5);
+const TOAST_WIDTH: f32 = 280.;
+const SCREEN_MARGIN: f32 = 10.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NotificationLevel {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+impl NotificationLevel {
+    fn color(self) -> Color32 {
+        match self {
+            Self::Info => Color32::from_rgb(90, 160, 255),
+            Self::Warning => Color32::from_rgb(255, 190, 60),
+            Self::Error => Color32::from_rgb(255, 90, 90),
+        }
+    }
+}
+
+/// Toast shown in the bottom right corner of the overlay.
+/// ```ignore
+/// APP.notify(Notification::new("Config saved").text("Written to config.toml"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Notification {
+    title: String,
+    text: Option<String>,
+    level: NotificationLevel,
+    duration: Option<Duration>,
+    link: Option<(String, String)>,
+    sensitive: bool,
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            text: None,
+            level: NotificationLevel::default(),
+            duration: Some(DEFAULT_DURATION),
+            link: None,
+            sensitive: false,
+        }
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub fn level(mut self, level: NotificationLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets how long the notification is shown, `None` keeps it until it's dismissed.
+    pub fn duration(mut self, duration: Option<Duration>) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Adds a button that opens `url` in the default browser.
+    pub fn link(mut self, label: impl Into<String>, url: impl Into<String>) -> Self {
+        self.link = Some((label.into(), url.into()));
+        self
+    }
+
+    /// Hides the text while streamer mode is on, see [`crate::OpenGLApp::set_streamer_mode`].
+    pub fn sensitive(mut self) -> Self {
+        self.sensitive = true;
+        self
+    }
+}
+
+struct Active {
+    id: u64,
+    notification: Notification,
+    /// Set when the notification is first drawn, so ones pushed while the overlay is hidden don't expire unseen.
+    shown_at: Option<Instant>,
+}
+
+struct Shared {
+    active: Mutex<Vec<Active>>,
+    next_id: Mutex<u64>,
+    /// Replaced when the app is initialized again.
+    ctx: Mutex<Option<Context>>,
+}
+
+impl Shared {
+    fn push(&self, notification: Notification) {
+        let id = {
+            let mut next_id = self.next_id.lock();
+            *next_id += 1;
+            *next_id
+        };
+
+        self.active.lock().push(Active {
+            id,
+            notification,
+            shown_at: None,
+        });
+
+        if let Some(ctx) = self.ctx.lock().as_ref() {
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// Notifications waiting to be drawn. Lives outside of the app lock, like [`crate::tasks::Tasks`].
+pub struct Notifications {
+    shared: OnceCell<Arc<Shared>>,
+}
+
+impl Notifications {
+    pub const fn new() -> Self {
+        Self {
+            shared: OnceCell::new(),
+        }
+    }
+
+    fn shared(&self) -> &Arc<Shared> {
+        self.shared.get_or_init(|| {
+            Arc::new(Shared {
+                active: Mutex::new(vec![]),
+                next_id: Mutex::new(0),
+                ctx: Mutex::new(None),
+            })
+        })
+    }
+
+    /// Sets the context that gets a repaint requested whenever a notification is pushed.
+    pub fn set_context(&self, ctx: Context) {
+        *self.shared().ctx.lock() = Some(ctx);
+    }
+
+    pub fn push(&self, notification: Notification) {
+        self.shared().push(notification);
+    }
+
+    pub fn notifier(&self) -> Notifier {
+        Notifier {
+            shared: self.shared().clone(),
+        }
+    }
+
+    /// Draws active notifications on top of everything else and drops expired or dismissed ones.
+    pub fn show(&self, ctx: &Context, streamer_mode: bool) {
+        let Some(shared) = self.shared.get() else {
+            return;
+        };
+
+        let mut active = shared.active.lock();
+        if active.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut offset = -SCREEN_MARGIN;
+        let mut next_expiry: Option<Duration> = None;
+
+        active.retain_mut(|entry| {
+            let shown_at = *entry.shown_at.get_or_insert(now);
+            if let Some(duration) = entry.notification.duration {
+                let elapsed = now - shown_at;
+                if elapsed >= duration {
+                    return false;
+                }
+
+                let remaining = duration - elapsed;
+                next_expiry = Some(next_expiry.map_or(remaining, |next| next.min(remaining)));
+            }
+
+            let mut dismissed = false;
+            let response = Area::new(Id::new("egui-opengl-internal-notification").with(entry.id))
+                .order(Order::Foreground)
+                .anchor(Align2::RIGHT_BOTTOM, [-SCREEN_MARGIN, offset])
+                .interactable(true)
+                .show(ctx, |ui| {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_width(TOAST_WIDTH);
+                        dismissed = show_notification(ui, &entry.notification, streamer_mode);
+                    });
+                })
+                .response;
+
+            offset -= response.rect.height() + SCREEN_MARGIN / 2.;
+            !dismissed
+        });
+
+        if let Some(next_expiry) = next_expiry {
+            ctx.request_repaint_after(next_expiry);
+        }
+    }
+}
+
+/// Draws the contents of a single notification, returns `true` if it was dismissed.
+fn show_notification(ui: &mut egui::Ui, notification: &Notification, streamer_mode: bool) -> bool {
+    let mut dismissed = false;
+
+    ui.horizontal(|ui| {
+        ui.label(
+            RichText::new(&notification.title)
+                .strong()
+                .color(notification.level.color()),
+        );
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            dismissed = ui.small_button("x").clicked();
+        });
+    });
+
+    if let Some(text) = notification.text.as_ref() {
+        if notification.sensitive && streamer_mode {
+            ui.weak("Hidden in streamer mode");
+        } else {
+            ui.label(text);
+        }
+    }
+
+    if let Some((label, url)) = notification.link.as_ref() {
+        if ui.button(label).clicked() {
+            ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+            dismissed = true;
+        }
+    }
+
+    dismissed
+}
+
+/// Handle for pushing notifications from threads you manage yourself.
+#[derive(Clone)]
+pub struct Notifier {
+    shared: Arc<Shared>,
+}
+
+impl Notifier {
+    pub fn notify(&self, notification: Notification) {
+        self.shared.push(notification);
+    }
+}
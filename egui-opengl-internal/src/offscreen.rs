@@ -0,0 +1,146 @@
+use gl::types::*;
+
+/// Framebuffer object with a single color texture, for rendering somewhere other than the game's backbuffer.
+pub struct Offscreen {
+    fbo: GLuint,
+    color: GLuint,
+    width: u32,
+    height: u32,
+}
+
+impl Offscreen {
+    /// Creates the framebuffer. `srgb` selects an `SRGB8_ALPHA8` color attachment instead of `RGBA8`.
+    /// Returns `None` if the driver reports it as incomplete.
+    pub unsafe fn new(width: u32, height: u32, srgb: bool) -> Option<Self> {
+        let mut previous = 0;
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous);
+        let mut previous_texture = 0;
+        gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut previous_texture);
+
+        let mut color = 0;
+        gl::GenTextures(1, &mut color);
+        gl::BindTexture(gl::TEXTURE_2D, color);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as _);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as _);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            if srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 } as _,
+            width as _,
+            height as _,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            std::ptr::null(),
+        );
+
+        let mut fbo = 0;
+        gl::GenFramebuffers(1, &mut fbo);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            color,
+            0,
+        );
+        let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, previous as _);
+        gl::BindTexture(gl::TEXTURE_2D, previous_texture as _);
+
+        let offscreen = Self {
+            fbo,
+            color,
+            width,
+            height,
+        };
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            log!("Offscreen framebuffer is incomplete: {status:#x}");
+            return None;
+        }
+
+        Some(offscreen)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Color attachment, for sampling what was rendered.
+    pub fn texture(&self) -> GLuint {
+        self.color
+    }
+
+    /// Binds the framebuffer for drawing and reading, returns the previous binding for [`Self::unbind`].
+    pub unsafe fn bind(&self) -> GLuint {
+        let mut previous = 0;
+        gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+
+        previous as _
+    }
+
+    pub unsafe fn unbind(&self, previous: GLuint) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+    }
+
+    pub unsafe fn clear(&self, color: [f32; 4]) {
+        let previous = self.bind();
+        gl::ClearColor(color[0], color[1], color[2], color[3]);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        self.unbind(previous);
+    }
+
+    /// Reads the color attachment as tightly packed RGBA rows, top row first.
+    #[cfg(feature = "golden")]
+    pub unsafe fn read_pixels(&self) -> Vec<u8> {
+        let previous = self.bind();
+        let pixels = read_rgba(self.width, self.height);
+        self.unbind(previous);
+        pixels
+    }
+}
+
+/// Reads the bottom left corner of the bound read framebuffer as tightly packed RGBA rows, top row first.
+pub(crate) unsafe fn read_rgba(width: u32, height: u32) -> Vec<u8> {
+    let row = width as usize * 4;
+    let mut pixels = vec![0u8; row * height as usize];
+
+    let mut pack_alignment = 0;
+    gl::GetIntegerv(gl::PACK_ALIGNMENT, &mut pack_alignment);
+    gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+    gl::ReadPixels(
+        0,
+        0,
+        width as _,
+        height as _,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        pixels.as_mut_ptr() as _,
+    );
+    gl::PixelStorei(gl::PACK_ALIGNMENT, pack_alignment);
+
+    // OpenGL returns the bottom row first.
+    let (mut top, mut bottom) = (0, height as usize);
+    while top + 1 < bottom {
+        bottom -= 1;
+        let (upper, lower) = pixels.split_at_mut(bottom * row);
+        upper[top * row..(top + 1) * row].swap_with_slice(&mut lower[..row]);
+        top += 1;
+    }
+
+    pixels
+}
+
+impl Drop for Offscreen {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.color);
+        }
+    }
+}
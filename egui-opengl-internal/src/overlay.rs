@@ -0,0 +1,555 @@
+//! Turn-key setup: hooks the present functions, subclasses the game's window and handles the menu hotkey and ejecting.
+//! ```ignore
+//! #[no_mangle]
+//! extern "system" fn DllMain(module: HMODULE, reason: u32, _: *mut c_void) -> BOOL {
+//!     if reason == DLL_PROCESS_ATTACH {
+//!         Overlay::<State>::spawn(OverlayConfig::new(module), ui);
+//!     }
+//!     BOOL(1)
+//! }
+//! ```
+//! Only one overlay can exist per process. To hook the game for an [`OpenGLApp`] of your own, see
+//! [`OpenGLApp::attach`].
+
+use crate::{utils, Error, OpenGLApp};
+use egui::Context;
+use once_cell::sync::OnceCell;
+use retour::GenericDetour;
+use std::{
+    sync::atomic::{AtomicBool, AtomicIsize, Ordering},
+    time::Duration,
+};
+use windows::Win32::{
+    Foundation::{BOOL, HMODULE, HWND, LPARAM, LRESULT, WPARAM},
+    Graphics::Gdi::{WindowFromDC, HDC},
+    System::LibraryLoader::FreeLibraryAndExitThread,
+    UI::{
+        Input::KeyboardAndMouse::{VIRTUAL_KEY, VK_END, VK_INSERT},
+        WindowsAndMessaging::{CallWindowProcW, IsWindow, GWLP_WNDPROC, WM_KEYDOWN, WNDPROC},
+    },
+};
+
+#[cfg(target_pointer_width = "64")]
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetWindowLongPtrW as GetWindowLong, SetWindowLongPtrW as SetWindowLong,
+};
+#[cfg(target_pointer_width = "32")]
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetWindowLongW as GetWindowLong, SetWindowLongW as SetWindowLong,
+};
+
+#[cfg(feature = "parking-lot")]
+use parking_lot::Mutex;
+#[cfg(feature = "spin-lock")]
+use spin::lock_api::Mutex;
+
+type FnSwapBuffers = unsafe extern "system" fn(HDC) -> BOOL;
+type FnSwapLayerBuffers = unsafe extern "system" fn(HDC, u32) -> BOOL;
+#[allow(clippy::type_complexity)]
+type PendingInit<T> = Box<dyn FnOnce(&OpenGLApp<T>, HDC, HWND)>;
+
+/// Time given to threads still running inside the hooks after they're removed, before the module is unloaded.
+const EJECT_GRACE_PERIOD: Duration = Duration::from_millis(500);
+const EJECT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Plane of `wglSwapLayerBuffers` holding the frame the overlay draws on.
+const WGL_SWAP_MAIN_PLANE: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverlayConfig {
+    /// Module unloaded on eject, usually the one passed to `DllMain`.
+    /// `None` only removes the hooks and keeps the module loaded.
+    pub module: Option<HMODULE>,
+    /// Key that shows and hides the UI. `None` keeps the UI always shown.
+    pub menu_key: Option<VIRTUAL_KEY>,
+    /// Key that removes the overlay and unloads the module.
+    pub eject_key: Option<VIRTUAL_KEY>,
+    /// Whether the UI is shown right after injecting.
+    pub menu_open: bool,
+    /// Opens a console window for log output, closed on eject.
+    pub console: bool,
+}
+
+impl OverlayConfig {
+    pub fn new(module: HMODULE) -> Self {
+        Self {
+            module: Some(module),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            module: None,
+            menu_key: Some(VK_INSERT),
+            eject_key: Some(VK_END),
+            menu_open: true,
+            console: false,
+        }
+    }
+}
+
+/// Type erased overlay, so the hooks can reach it without knowing `T`.
+trait Hooks {
+    unsafe fn present(&self, hdc: HDC);
+    /// Shuts the app down before ejecting.
+    unsafe fn release(&self, hdc: HDC);
+    unsafe fn wnd_proc(&self, msg: u32, wparam: WPARAM, lparam: LPARAM) -> Option<LRESULT>;
+    /// Address of the app, tells the app attached with [`OpenGLApp::attach`] apart.
+    fn app_address(&self) -> usize;
+}
+
+#[derive(Clone, Copy)]
+struct Active(&'static dyn Hooks);
+
+// The hooks are called from the game's threads, `OpenGLApp` serializes them with its own lock.
+unsafe impl Send for Active {}
+unsafe impl Sync for Active {}
+
+static ACTIVE: Mutex<Option<Active>> = Mutex::new(None);
+static SWAP_BUFFERS: OnceCell<GenericDetour<FnSwapBuffers>> = OnceCell::new();
+static GDI_SWAP_BUFFERS: OnceCell<GenericDetour<FnSwapBuffers>> = OnceCell::new();
+static SWAP_LAYER_BUFFERS: OnceCell<GenericDetour<FnSwapLayerBuffers>> = OnceCell::new();
+static ORIGINAL_WND_PROC: AtomicIsize = AtomicIsize::new(0);
+static SUBCLASSED_WINDOW: AtomicIsize = AtomicIsize::new(0);
+static EJECTING: AtomicBool = AtomicBool::new(false);
+/// Set once the GL objects were deleted from within a present after [`EJECTING`] was set.
+static RELEASED: AtomicBool = AtomicBool::new(false);
+
+/// [`OpenGLApp`] wired into the game by hooking `wglSwapBuffers`, gdi32's `SwapBuffers`, `wglSwapLayerBuffers`
+/// and the window procedure.
+pub struct Overlay<T: 'static> {
+    app: OpenGLApp<T>,
+    config: OverlayConfig,
+    init: Mutex<Option<PendingInit<T>>>,
+}
+
+impl<T: Default + 'static> Overlay<T> {
+    /// Installs the overlay from a background thread and returns immediately, safe to call from `DllMain`.
+    pub fn spawn(
+        config: OverlayConfig,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+    ) -> &'static Self {
+        Self::spawn_with_state(config, ui, T::default())
+    }
+}
+
+impl<T: 'static> Overlay<T> {
+    /// Like [`Self::spawn`], with an explicit initial state.
+    pub fn spawn_with_state(
+        config: OverlayConfig,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+    ) -> &'static Self {
+        let init: PendingInit<T> = Box::new(move |app, hdc, window| {
+            if let Err(e) = app.init_with_state(hdc, window, ui, state) {
+                log!("Failed to initialize the overlay: {e}");
+            }
+        });
+
+        let app = OpenGLApp::new();
+        app.set_toggle_key(config.menu_key);
+        app.set_menu_open(config.menu_open);
+        app.set_run_ui_when_closed(false);
+
+        let Some(overlay) = activate(Self {
+            app,
+            config,
+            init: Mutex::new(Some(init)),
+        }) else {
+            panic_msg!("Only one overlay can be spawned");
+        };
+
+        std::thread::spawn(move || unsafe { run(config) });
+        overlay
+    }
+
+    pub fn app(&self) -> &OpenGLApp<T> {
+        &self.app
+    }
+
+    pub fn is_menu_open(&self) -> bool {
+        self.app.is_menu_open()
+    }
+
+    pub fn set_menu_open(&self, open: bool) {
+        self.app.set_menu_open(open);
+    }
+
+    /// Removes the hooks and unloads the module, see [`eject`].
+    pub fn eject(&self) {
+        eject();
+    }
+
+    fn is_eject_key(&self, wparam: WPARAM, lparam: LPARAM) -> bool {
+        // Bit 30 is set for auto-repeated key presses.
+        self.config
+            .eject_key
+            .is_some_and(|key| wparam.0 == key.0 as usize && lparam.0 & (1 << 30) == 0)
+    }
+}
+
+impl<T: 'static> Hooks for Overlay<T> {
+    unsafe fn present(&self, hdc: HDC) {
+        let window = WindowFromDC(hdc);
+
+        if let Some(init) = self.init.lock().take() {
+            init(&self.app, hdc, window);
+        }
+
+        subclass(window);
+        let _ = self.app.render(hdc);
+    }
+
+    unsafe fn release(&self, hdc: HDC) {
+        self.app.shutdown(hdc);
+    }
+
+    unsafe fn wnd_proc(&self, msg: u32, wparam: WPARAM, lparam: LPARAM) -> Option<LRESULT> {
+        if !self.app.is_ready() {
+            return None;
+        }
+
+        if msg == WM_KEYDOWN && self.is_eject_key(wparam, lparam) {
+            eject();
+            return Some(LRESULT(0));
+        }
+
+        self.app.wnd_proc_result(msg, wparam, lparam).into()
+    }
+
+    fn app_address(&self) -> usize {
+        &self.app as *const _ as usize
+    }
+}
+
+/// App hooked into the game with [`OpenGLApp::attach`].
+struct Attachment<T: 'static> {
+    app: &'static OpenGLApp<T>,
+    init: Mutex<Option<PendingInit<T>>>,
+    /// Window [`OpenGLApp::attach_wnd_proc`] failed for, so it isn't retried on every present.
+    failed_window: AtomicIsize,
+}
+
+impl<T: 'static> Hooks for Attachment<T> {
+    unsafe fn present(&self, hdc: HDC) {
+        let window = WindowFromDC(hdc);
+
+        if let Some(init) = self.init.lock().take() {
+            init(self.app, hdc, window);
+        }
+
+        // Detached again by `shutdown`.
+        if self.app.is_ready() && self.failed_window.load(Ordering::Acquire) != window.0 {
+            if let Err(e) = self.app.attach_wnd_proc(window) {
+                log!("Failed to attach to the window's messages: {e}");
+                self.failed_window.store(window.0, Ordering::Release);
+            }
+        }
+        let _ = self.app.render(hdc);
+    }
+
+    unsafe fn release(&self, hdc: HDC) {
+        self.app.shutdown(hdc);
+    }
+
+    unsafe fn wnd_proc(&self, _: u32, _: WPARAM, _: LPARAM) -> Option<LRESULT> {
+        // The messages reach the app through the subclass of `attach_wnd_proc`.
+        None
+    }
+
+    fn app_address(&self) -> usize {
+        self.app as *const _ as usize
+    }
+}
+
+impl<T: 'static> OpenGLApp<T> {
+    /// Hooks the game's present functions and subclasses its window with [`Self::attach_wnd_proc`],
+    /// the boilerplate every injected DLL needs.
+    /// The app is initialized with `ui` and `state` on the first present and drawn on every present after.
+    /// Only one app or [`Overlay`] can be attached at a time. Hooking suspends the game's threads,
+    /// call it from a thread of your own rather than from `DllMain`:
+    /// ```ignore
+    /// static APP: OpenGLApp<i32> = OpenGLApp::new();
+    ///
+    /// #[no_mangle]
+    /// extern "system" fn DllMain(_: HMODULE, reason: u32, _: *mut c_void) -> BOOL {
+    ///     if reason == DLL_PROCESS_ATTACH {
+    ///         std::thread::spawn(|| APP.attach(ui, 0));
+    ///     }
+    ///     BOOL(1)
+    /// }
+    ///
+    /// fn ui(ctx: &Context, clicks: &mut i32) {
+    ///     egui::Window::new("Hello").show(ctx, |ui| {
+    ///         if ui.button(format!("Clicked {clicks} times")).clicked() {
+    ///             *clicks += 1;
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    pub fn attach(
+        &'static self,
+        ui: impl FnMut(&Context, &mut T) + 'static,
+        state: T,
+    ) -> Result<(), Error> {
+        if self.is_ready() {
+            return Err(Error::AlreadyInitialized);
+        }
+
+        let init: PendingInit<T> = Box::new(move |app, hdc, window| {
+            if let Err(e) = app.init_with_state(hdc, window, ui, state) {
+                log!("Failed to initialize the attached app: {e}");
+            }
+        });
+
+        let attachment = Attachment {
+            app: self,
+            init: Mutex::new(Some(init)),
+            failed_window: AtomicIsize::new(0),
+        };
+        if activate(attachment).is_none() {
+            return Err(Error::AlreadyAttached);
+        }
+
+        unsafe {
+            install().map_err(|e| {
+                uninstall();
+                deactivate();
+                Error::Hook(e)
+            })
+        }
+    }
+
+    /// Reverses [`Self::attach`]: shuts the app down from within the next present, then removes the hooks and
+    /// restores the window procedure. Blocks until the game presented once more, so it can't be called from within
+    /// the UI closure. Does nothing if the app isn't attached.
+    ///
+    /// Fails with [`Error::DetachTimeout`] if the game didn't present in time, e.g. while it's minimized or loading.
+    /// The app stays attached and running then, call it again later.
+    pub fn detach(&self) -> Result<(), Error> {
+        let attached = ACTIVE
+            .lock()
+            .is_some_and(|active| active.0.app_address() == self as *const _ as usize);
+        if !attached {
+            return Ok(());
+        }
+
+        EJECTING.store(true, Ordering::Release);
+        if !wait_for_release() && !RELEASED.swap(true, Ordering::AcqRel) {
+            // Claimed the release, so no present shuts the app down while ejecting is called off.
+            EJECTING.store(false, Ordering::Release);
+            RELEASED.store(false, Ordering::Release);
+            return Err(Error::DetachTimeout);
+        }
+
+        unsafe { uninstall() };
+        deactivate();
+        Ok(())
+    }
+}
+
+/// Makes `hooks` the target of the hooks, `None` if another overlay or app is attached already.
+fn activate<H: Hooks + 'static>(hooks: H) -> Option<&'static H> {
+    let mut active = ACTIVE.lock();
+    if active.is_some() {
+        return None;
+    }
+
+    // Leaked only once claimed, threads may still be inside the hooks after they're removed.
+    let hooks: &'static H = Box::leak(Box::new(hooks));
+    EJECTING.store(false, Ordering::Release);
+    RELEASED.store(false, Ordering::Release);
+    *active = Some(Active(hooks));
+    Some(hooks)
+}
+
+fn deactivate() {
+    ACTIVE.lock().take();
+}
+
+fn active() -> Option<&'static dyn Hooks> {
+    ACTIVE.lock().map(|active| active.0)
+}
+
+/// Removes the overlay and unloads the module it lives in. Safe to call from within the UI closure.
+/// Apps attached with [`OpenGLApp::attach`] are removed with [`OpenGLApp::detach`] instead.
+pub fn eject() {
+    EJECTING.store(true, Ordering::Release);
+}
+
+unsafe fn run(config: OverlayConfig) {
+    if config.console {
+        utils::alloc_console();
+    }
+
+    if let Err(e) = install() {
+        log!("Failed to hook wglSwapBuffers: {e}");
+    } else {
+        while !EJECTING.load(Ordering::Acquire) {
+            std::thread::sleep(EJECT_POLL_INTERVAL);
+        }
+
+        wait_for_release();
+        uninstall();
+        std::thread::sleep(EJECT_GRACE_PERIOD);
+    }
+
+    if config.console {
+        utils::free_console();
+    }
+
+    if let Some(module) = config.module {
+        FreeLibraryAndExitThread(module, 0);
+    }
+}
+
+/// GL objects can only be deleted from a present, gives the game a chance to present once more.
+/// Returns whether the app was released.
+fn wait_for_release() -> bool {
+    let mut waited = Duration::ZERO;
+    while !RELEASED.load(Ordering::Acquire) && waited < EJECT_GRACE_PERIOD {
+        std::thread::sleep(EJECT_POLL_INTERVAL);
+        waited += EJECT_POLL_INTERVAL;
+    }
+    RELEASED.load(Ordering::Acquire)
+}
+
+unsafe fn install() -> Result<(), retour::Error> {
+    let target = utils::get_proc_address("wglSwapBuffers");
+    if target.is_null() {
+        return Err(retour::Error::NotExecutable);
+    }
+
+    let target: FnSwapBuffers = std::mem::transmute(target);
+    let detour = SWAP_BUFFERS.get_or_try_init(|| GenericDetour::new(target, hk_swap_buffers))?;
+    detour.enable()?;
+
+    // Games presenting through these may never reach the `wglSwapBuffers` of opengl32.dll.
+    if let Err(e) = install_gdi_swap_buffers() {
+        log!("Failed to hook SwapBuffers: {e}");
+    }
+    if let Err(e) = install_swap_layer_buffers() {
+        log!("Failed to hook wglSwapLayerBuffers: {e}");
+    }
+    Ok(())
+}
+
+unsafe fn install_gdi_swap_buffers() -> Result<(), retour::Error> {
+    let target = utils::get_gdi_swap_buffers();
+    if target.is_null() {
+        return Err(retour::Error::NotExecutable);
+    }
+
+    let target: FnSwapBuffers = std::mem::transmute(target);
+    let detour =
+        GDI_SWAP_BUFFERS.get_or_try_init(|| GenericDetour::new(target, hk_gdi_swap_buffers))?;
+    detour.enable()
+}
+
+unsafe fn install_swap_layer_buffers() -> Result<(), retour::Error> {
+    let target = utils::get_swap_layer_buffers();
+    if target.is_null() {
+        return Err(retour::Error::NotExecutable);
+    }
+
+    let target: FnSwapLayerBuffers = std::mem::transmute(target);
+    let detour =
+        SWAP_LAYER_BUFFERS.get_or_try_init(|| GenericDetour::new(target, hk_swap_layer_buffers))?;
+    detour.enable()
+}
+
+unsafe fn uninstall() {
+    if let Some(detour) = SWAP_BUFFERS.get() {
+        if let Err(e) = detour.disable() {
+            log!("Failed to unhook wglSwapBuffers: {e}");
+        }
+    }
+    if let Some(detour) = GDI_SWAP_BUFFERS.get() {
+        if let Err(e) = detour.disable() {
+            log!("Failed to unhook SwapBuffers: {e}");
+        }
+    }
+    if let Some(detour) = SWAP_LAYER_BUFFERS.get() {
+        if let Err(e) = detour.disable() {
+            log!("Failed to unhook wglSwapLayerBuffers: {e}");
+        }
+    }
+
+    unsubclass();
+}
+
+/// Draws the overlay, or releases it once ejecting. Returns the guard to hold until the game's present
+/// function returned, `None` if a hook further up the stack drew the frame already.
+unsafe fn present(hdc: HDC) -> Option<utils::PresentGuard> {
+    let guard = utils::PresentGuard::enter()?;
+    if let Some(active) = active() {
+        if !EJECTING.load(Ordering::Acquire) {
+            active.present(hdc);
+        } else if !RELEASED.swap(true, Ordering::AcqRel) {
+            active.release(hdc);
+        }
+    }
+    Some(guard)
+}
+
+unsafe extern "system" fn hk_swap_buffers(hdc: HDC) -> BOOL {
+    let _guard = present(hdc);
+    expect!(SWAP_BUFFERS.get(), "wglSwapBuffers hook is missing").call(hdc)
+}
+
+unsafe extern "system" fn hk_gdi_swap_buffers(hdc: HDC) -> BOOL {
+    let _guard = present(hdc);
+    expect!(GDI_SWAP_BUFFERS.get(), "SwapBuffers hook is missing").call(hdc)
+}
+
+unsafe extern "system" fn hk_swap_layer_buffers(hdc: HDC, planes: u32) -> BOOL {
+    let _guard = (planes & WGL_SWAP_MAIN_PLANE != 0).then(|| present(hdc));
+    expect!(
+        SWAP_LAYER_BUFFERS.get(),
+        "wglSwapLayerBuffers hook is missing"
+    )
+    .call(hdc, planes)
+}
+
+unsafe extern "system" fn hk_wnd_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if !EJECTING.load(Ordering::Acquire) {
+        if let Some(result) = active().and_then(|active| active.wnd_proc(msg, wparam, lparam)) {
+            return result;
+        }
+    }
+
+    let original: WNDPROC = std::mem::transmute(ORIGINAL_WND_PROC.load(Ordering::Acquire));
+    CallWindowProcW(original, hwnd, msg, wparam, lparam)
+}
+
+/// Routes the messages of `window` through [`hk_wnd_proc`], restoring the previously subclassed window.
+unsafe fn subclass(window: HWND) {
+    if SUBCLASSED_WINDOW.load(Ordering::Acquire) == window.0 {
+        return;
+    }
+
+    unsubclass();
+
+    // Stored before subclassing, messages may arrive as soon as the window procedure is replaced.
+    ORIGINAL_WND_PROC.store(GetWindowLong(window, GWLP_WNDPROC) as _, Ordering::Release);
+    SetWindowLong(window, GWLP_WNDPROC, hk_wnd_proc as *const () as usize as _);
+    SUBCLASSED_WINDOW.store(window.0, Ordering::Release);
+}
+
+unsafe fn unsubclass() {
+    let window = HWND(SUBCLASSED_WINDOW.swap(0, Ordering::AcqRel));
+    if window.0 != 0 && IsWindow(window).as_bool() {
+        SetWindowLong(
+            window,
+            GWLP_WNDPROC,
+            ORIGINAL_WND_PROC.load(Ordering::Acquire) as _,
+        );
+    }
+}
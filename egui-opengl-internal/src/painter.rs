@@ -1,11 +1,28 @@
-use crate::shader;
+use crate::{
+    buffer::{BufferKind, MeshBuffer, PixelUpload},
+    compositor::Compositor,
+    compressed,
+    gl_state::{GlState, GlStateGuard},
+    gl_version, offscreen,
+    shader::{self, ShaderError, ShaderVersion},
+    stats::{FrameCounters, GpuTimer},
+    BlendMode, CompressedFormat, CustomShader, GlVersion, ProgramLocations, RenderStats,
+    SurfaceConfig,
+};
 use egui::{
     emath::Rect,
-    epaint::{Mesh, Primitive},
-    Color32, TextureFilter,
+    epaint::{Mesh, PaintCallback, PaintCallbackInfo, Primitive, Vertex},
+    Color32, ColorImage, TextureFilter, TextureOptions, TextureWrapMode,
 };
 use gl::types::*;
-use std::ffi::{c_void, CString};
+use std::{ffi::c_void, mem::offset_of, time::Instant};
+
+// From EXT_texture_filter_anisotropic, core since OpenGL 4.6.
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+/// Uncompressed RGBA uploads of user textures at least this large go through a pixel buffer object.
+const PBO_UPLOAD_MIN_BYTES: usize = 256 * 1024;
 
 pub struct UserTexture {
     size: (usize, usize),
@@ -16,9 +33,32 @@ pub struct UserTexture {
     /// Lazily uploaded
     gl_texture_id: Option<GLuint>,
 
-    /// For user textures there is a choice between
-    /// Linear (default) and Nearest.
-    filtering: TextureFilter,
+    /// Size of the storage allocated for `gl_texture_id`, `0` for textures not owned by the painter.
+    gpu_bytes: usize,
+
+    /// Filtering and wrapping, applied whenever the texture is uploaded.
+    options: TextureOptions,
+
+    /// Set when `pixels` holds block compressed data instead of RGBA.
+    compressed: Option<CompressedFormat>,
+
+    /// Number of mip levels stored in `pixels`, largest first.
+    mip_levels: u32,
+
+    /// Maximum anisotropy used for sampling, `1.0` disables anisotropic filtering.
+    anisotropy: f32,
+
+    /// Set when `pixels` holds a single byte of coverage per texel, stored as `GL_R8`
+    /// and swizzled to premultiplied white when sampled.
+    coverage: bool,
+
+    /// `false` for textures created elsewhere and registered with [`Painter::new_opengl_texture`],
+    /// which are never deleted by the painter.
+    owned: bool,
+
+    /// Upload through a pixel buffer object that may still be in flight, meshes using the texture
+    /// are skipped until it completes.
+    pending_upload: Option<PixelUpload>,
 
     /// User textures can be modified and this flag
     /// is used to indicate if pixel data for the
@@ -27,6 +67,8 @@ pub struct UserTexture {
 }
 
 impl UserTexture {
+    /// Replaces the texels of the `width` x `height` rect at `x_offset`, `y_offset`. `bytes` starts with the
+    /// rect's first texel and holds `row_length` texels per row, so rects can be copied out of larger images.
     pub fn update_texture_part(
         &mut self,
         x_offset: i32,
@@ -34,13 +76,46 @@ impl UserTexture {
         width: i32,
         height: i32,
         bytes: &[u8],
+        row_length: i32,
     ) {
         assert!(x_offset + width <= self.size.0 as _);
         assert!(y_offset + height <= self.size.1 as _);
+        assert!(row_length >= width);
+        assert!(
+            self.compressed.is_none(),
+            "Compressed textures can't be partially updated"
+        );
+
+        let texel_len = if self.coverage { 1 } else { 4 };
+        if width == 0 || height == 0 {
+            return;
+        }
+        assert!(
+            bytes.len() >= ((height - 1) * row_length + width) as usize * texel_len,
+            "Mismatch between rect size and byte count"
+        );
+
+        // Not uploaded yet, patch the pending pixels instead.
+        if !self.pixels.is_empty() {
+            let row_len = width as usize * texel_len;
+            for row in 0..height as usize {
+                let src = row * row_length as usize * texel_len;
+                let dst = ((y_offset as usize + row) * self.size.0 + x_offset as usize) * texel_len;
+                self.pixels[dst..dst + row_len].copy_from_slice(&bytes[src..src + row_len]);
+            }
+            return;
+        }
+
+        let Some(texture) = self.gl_texture_id else {
+            return;
+        };
 
         unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture);
             gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_A, gl::RED as _);
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, row_length);
+            gl::PixelStorei(gl::UNPACK_SKIP_PIXELS, 0);
+            gl::PixelStorei(gl::UNPACK_SKIP_ROWS, 0);
 
             gl::TexSubImage2D(
                 gl::TEXTURE_2D,
@@ -49,27 +124,102 @@ impl UserTexture {
                 y_offset as _,
                 width as _,
                 height as _,
-                gl::RGBA,
+                if self.coverage { gl::RED } else { gl::RGBA },
                 gl::UNSIGNED_BYTE,
                 bytes.as_ptr() as *const _,
             );
+
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
         }
 
         self.dirty = true;
     }
 
+    /// Sets the sampling parameters of the texture bound to `GL_TEXTURE_2D` from `options`.
+    /// `max_anisotropy` is the limit of the context, `1.0` if anisotropic filtering is unsupported.
+    /// Textures not owned by the painter keep the game's sampling, only their anisotropy is set.
+    unsafe fn apply_options(&self, max_anisotropy: f32) {
+        if self.owned {
+            self.apply_sampling();
+        }
+
+        if max_anisotropy > 1. {
+            gl::TexParameterf(
+                gl::TEXTURE_2D,
+                TEXTURE_MAX_ANISOTROPY,
+                self.anisotropy.clamp(1., max_anisotropy),
+            );
+        }
+    }
+
+    unsafe fn apply_sampling(&self) {
+        let filter = |filter| match filter {
+            TextureFilter::Nearest => gl::NEAREST,
+            TextureFilter::Linear => gl::LINEAR,
+        } as i32;
+        let min_filter = match (self.options.minification, self.mip_levels > 1) {
+            (TextureFilter::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST as i32,
+            (TextureFilter::Linear, true) => gl::LINEAR_MIPMAP_LINEAR as i32,
+            (filter_mode, false) => filter(filter_mode),
+        };
+        let wrap = match self.options.wrap_mode {
+            TextureWrapMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+            TextureWrapMode::Repeat => gl::REPEAT,
+            TextureWrapMode::MirroredRepeat => gl::MIRRORED_REPEAT,
+        } as i32;
+
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter);
+        gl::TexParameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MAG_FILTER,
+            filter(self.options.magnification),
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap);
+
+        if self.mip_levels > 1 {
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAX_LEVEL,
+                self.mip_levels as i32 - 1,
+            );
+        }
+
+        if self.coverage {
+            for swizzle in [
+                gl::TEXTURE_SWIZZLE_R,
+                gl::TEXTURE_SWIZZLE_G,
+                gl::TEXTURE_SWIZZLE_B,
+                gl::TEXTURE_SWIZZLE_A,
+            ] {
+                gl::TexParameteri(gl::TEXTURE_2D, swizzle, gl::RED as _);
+            }
+        }
+    }
+
     pub fn from_raw(id: u32) -> Self {
         Self {
             size: (0, 0),
             gl_texture_id: Some(id),
-            filtering: TextureFilter::Linear,
+            gpu_bytes: 0,
+            options: TextureOptions::default(),
+            compressed: None,
+            mip_levels: 1,
+            anisotropy: 1.,
+            coverage: false,
+            owned: false,
+            pending_upload: None,
             dirty: false,
             pixels: Vec::with_capacity(0),
         }
     }
 
     pub fn delete(&self) {
-        if let Some(id) = &self.gl_texture_id {
+        if let Some(upload) = &self.pending_upload {
+            unsafe { upload.delete() };
+        }
+
+        if let (Some(id), true) = (&self.gl_texture_id, self.owned) {
             unsafe {
                 gl::DeleteTextures(1, id as *const _);
             }
@@ -77,52 +227,477 @@ impl UserTexture {
     }
 }
 
+/// Custom OpenGL painting inside an egui [`PaintCallback`], e.g. a 3D preview.
+/// Runs with the viewport set to the callback's rect and the scissor box set to its clip rect.
+/// Any other state may be changed freely, the painter sets its own state up again afterwards.
+/// ```ignore
+/// ui.painter().add(PaintCallback {
+///     rect,
+///     callback: Arc::new(CallbackFn::new(|info, painter| unsafe {
+///         gl::ClearColor(1., 0., 0., 1.);
+///         gl::Clear(gl::COLOR_BUFFER_BIT);
+///     })),
+/// });
+/// ```
+pub struct CallbackFn {
+    f: Box<CallbackFnInner>,
+}
+
+type CallbackFnInner = dyn Fn(PaintCallbackInfo, &Painter) + Sync + Send;
+
+impl CallbackFn {
+    pub fn new(callback: impl Fn(PaintCallbackInfo, &Painter) + Sync + Send + 'static) -> Self {
+        Self {
+            f: Box::new(callback),
+        }
+    }
+}
+
+/// Output of an egui frame, ready to be painted with [`Painter::paint_frame`].
+#[derive(Clone)]
+pub struct PreparedFrame {
+    pub primitives: Vec<egui::ClippedPrimitive>,
+    pub textures_delta: egui::TexturesDelta,
+    pub pixels_per_point: f32,
+    /// Size of the target framebuffer in pixels.
+    pub size: (u32, u32),
+}
+
+impl PreparedFrame {
+    /// Runs `ui` on `ctx` with fixed input and tessellates the result.
+    /// `ui` runs twice, so layouts that settle over a frame (e.g. window sizing) are stable
+    /// and the frame only depends on `ui`, `size` and `pixels_per_point`.
+    pub fn run(
+        ctx: &egui::Context,
+        size: (u32, u32),
+        pixels_per_point: f32,
+        mut ui: impl FnMut(&egui::Context),
+    ) -> Self {
+        let input = || egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Default::default(),
+                egui::vec2(size.0 as f32, size.1 as f32) / pixels_per_point,
+            )),
+            time: Some(0.),
+            ..Default::default()
+        };
+
+        ctx.set_pixels_per_point(pixels_per_point);
+        let first = ctx.run(input(), &mut ui);
+        let output = ctx.run(input(), &mut ui);
+
+        let mut textures_delta = first.textures_delta;
+        textures_delta.append(output.textures_delta);
+
+        Self {
+            primitives: ctx.tessellate(output.shapes, pixels_per_point),
+            textures_delta,
+            pixels_per_point,
+            size,
+        }
+    }
+}
+
+/// Hands out [`egui::TextureId::User`]s, never reusing one so stale IDs can't alias new textures.
+#[derive(Default)]
+struct UserTextureIds {
+    next: u64,
+}
+
+impl UserTextureIds {
+    fn next_id(&mut self) -> egui::TextureId {
+        let id = egui::TextureId::User(self.next);
+        self.next += 1;
+        id
+    }
+
+    fn continue_from(&mut self, next: u64) {
+        self.next = self.next.max(next);
+    }
+}
+
+/// Locations in the egui shader program, resolved once when the painter is created.
+struct Locations {
+    a_pos: GLuint,
+    a_tc: GLuint,
+    a_srgba: GLuint,
+    u_screen_size: GLint,
+    u_sampler: GLint,
+    u_srgb_framebuffer: GLint,
+}
+
+impl Locations {
+    /// Fails if the program lacks an input the painter can't do without.
+    fn resolve(program: GLuint) -> Result<Self, ShaderError> {
+        let locations = ProgramLocations::new(program);
+        let attrib = |name| {
+            locations
+                .attrib(name)
+                .ok_or(ShaderError::MissingInput(name))
+        };
+        let uniform = |name| match locations.uniform(name) {
+            -1 => Err(ShaderError::MissingInput(name)),
+            location => Ok(location),
+        };
+
+        Ok(Self {
+            a_pos: attrib("a_pos")?,
+            a_tc: attrib("a_tc")?,
+            a_srgba: attrib("a_srgba")?,
+            u_screen_size: uniform("u_screen_size")?,
+            u_sampler: uniform("u_sampler")?,
+            // Only used to pick the color space, custom shaders may ignore it.
+            u_srgb_framebuffer: locations.uniform("u_srgb_framebuffer"),
+        })
+    }
+}
+
+/// Builds the painting program from the built-in sources, replacing the stages `custom` provides.
+fn build_program(
+    shader_version: ShaderVersion,
+    custom: Option<&CustomShader>,
+) -> Result<(GLuint, Locations), ShaderError> {
+    let vertex = custom.and_then(|custom| custom.vertex.as_deref());
+    let fragment = custom.and_then(|custom| custom.fragment.as_deref());
+
+    let program = shader::Shader::build(
+        &shader_version.source(vertex.unwrap_or(include_str!("shader/vertex.vert"))),
+        &shader_version.source(fragment.unwrap_or(include_str!("shader/fragment.frag"))),
+    )?;
+
+    match Locations::resolve(program) {
+        Ok(locations) => Ok((program, locations)),
+        Err(e) => {
+            unsafe { gl::DeleteProgram(program) };
+            Err(e)
+        }
+    }
+}
+
+/// Converts glyph coverage to premultiplied white RGBA, like [`egui::FontImage::srgba_pixels`]
+/// with an extra `weight` multiplier. With `single_channel` only the coverage bytes are written.
+/// `out` is cleared first, so its allocation can be reused.
+fn font_pixels(
+    image: &egui::FontImage,
+    gamma: f32,
+    weight: f32,
+    single_channel: bool,
+    out: &mut Vec<u8>,
+) {
+    let coverage = image
+        .pixels
+        .iter()
+        .map(|coverage| ((coverage.powf(gamma) * weight).min(1.) * 255.).round() as u8);
+
+    out.clear();
+    if single_channel {
+        out.extend(coverage);
+    } else {
+        out.extend(coverage.flat_map(|alpha| [alpha; 4]));
+    }
+}
+
+/// OpenGL painter for egui output. All methods must be called with the same OpenGL context current.
 pub struct Painter {
+    gl_version: GlVersion,
+    shader_version: ShaderVersion,
     program: GLuint,
-    vertex_array: GLuint,
-    index_buffer: GLuint,
-    pos_buffer: GLuint,
-    tc_buffer: GLuint,
-    color_buffer: GLuint,
+    locations: Locations,
+    /// `None` on contexts without vertex array objects.
+    vertex_array: Option<GLuint>,
+    index_buffer: MeshBuffer,
+    /// Interleaved [`Vertex`]es, uploaded as egui produces them.
+    vertex_buffer: MeshBuffer,
     textures: std::collections::HashMap<egui::TextureId, UserTexture>,
+    user_texture_ids: UserTextureIds,
+    /// Texels of partial texture updates, kept so egui's frequent small atlas updates don't allocate.
+    scratch: Vec<u8>,
+    surface: SurfaceConfig,
+    check_state: bool,
+    /// Last reported state divergence, so the same one isn't logged every frame.
+    state_divergence: Vec<String>,
+    freed_texture_bytes: u64,
+    atlas_rebuilds: u32,
+    /// Created once compositing is first needed.
+    compositor: Option<Compositor>,
+    compositing: bool,
+    opacity: f32,
+    /// Set while the target framebuffer is multisampled. Blending straight into it fringes text edges,
+    /// so the UI is resolved through the single-sample compositing framebuffer instead.
+    multisampled: bool,
+    /// `GL_MAX_TEXTURE_MAX_ANISOTROPY`, `1.0` if anisotropic filtering is unsupported.
+    max_anisotropy: f32,
+    /// Set once the compositing shaders failed to build, the UI is then always painted directly.
+    compositor_failed: bool,
+    font_gamma: f32,
+    font_weight: f32,
+    /// Font atlases are stored as `GL_R8` where texture swizzling is available, a quarter of the RGBA size.
+    r8_fonts: bool,
+    /// Set where large uploads can be staged in pixel buffer objects, see [`PixelUpload`].
+    pixel_buffers: bool,
+    /// Counters of the frame being painted and of the last completed one.
+    frame: FrameCounters,
+    last_frame: FrameCounters,
+    /// `None` on contexts without timer queries.
+    gpu_timer: Option<GpuTimer>,
+    debug_overlay: bool,
+    /// Fixed size the UI is painted at before it's stretched onto the target, see [`Self::set_render_resolution`].
+    render_resolution: Option<(u32, u32)>,
+    /// Meshes painted with every texture in the last frame, only counted with the debug overlay.
+    mesh_counts: Vec<(egui::TextureId, u32)>,
 }
 
 impl Painter {
-    pub fn new() -> Painter {
-        let vs =
-            shader::Shader::compile_shader(include_str!("shader/vertex.vert"), gl::VERTEX_SHADER);
-        let fs = shader::Shader::compile_shader(
-            include_str!("shader/fragment.frag"),
-            gl::FRAGMENT_SHADER,
-        );
-
-        let program = shader::Shader::link_program(vs, fs);
+    /// Compiles the shaders and creates buffers in the current context.
+    pub fn new() -> Result<Painter, ShaderError> {
+        let gl_version = GlVersion::detect().unwrap_or(GlVersion::new(2, 0));
+        let shader_version = ShaderVersion::for_gl(gl_version);
+        log!("Painting with {gl_version}, {shader_version:?} shaders");
+
+        let (program, locations) = build_program(shader_version, None)?;
+
+        // OpenGL 2.1 contexts may lack VAOs, the attributes are then set up on every draw anyway.
+        let vertex_array =
+            (gl_version.at_least(3, 0) && gl::GenVertexArrays::is_loaded()).then(|| {
+                let mut vertex_array = 0;
+                unsafe { gl::GenVertexArrays(1, &mut vertex_array) };
+                vertex_array
+            });
+        if vertex_array.is_none() {
+            log!("Vertex array objects are not supported, using the legacy paint path");
+        }
 
-        let mut vertex_array = 0;
-        let mut index_buffer = 0;
-        let mut pos_buffer = 0;
-        let mut tc_buffer = 0;
-        let mut color_buffer = 0;
-        unsafe {
-            gl::GenVertexArrays(1, &mut vertex_array);
-            gl::BindVertexArray(vertex_array);
-            gl::GenBuffers(1, &mut index_buffer);
-            gl::GenBuffers(1, &mut pos_buffer);
-            gl::GenBuffers(1, &mut tc_buffer);
-            gl::GenBuffers(1, &mut color_buffer);
+        let buffer_kind = BufferKind::detect(gl_version);
+        match buffer_kind {
+            BufferKind::Persistent => {
+                log!("Streaming vertices through persistently mapped buffers")
+            }
+            BufferKind::Mapped => log!("Streaming vertices through glMapBufferRange"),
+            BufferKind::Stream => log!("Streaming vertices through orphaned buffers"),
         }
 
-        Painter {
+        let max_anisotropy = if gl_version.at_least(4, 6)
+            || gl_version::has_extension("GL_EXT_texture_filter_anisotropic")
+            || gl_version::has_extension("GL_ARB_texture_filter_anisotropic")
+        {
+            let mut max_anisotropy = 1.;
+            unsafe { gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy) };
+            max_anisotropy
+        } else {
+            1.
+        };
+
+        let r8_fonts = if gl_version.es {
+            gl_version.at_least(3, 0)
+        } else {
+            gl_version.at_least(3, 3)
+                || (gl_version.at_least(3, 0)
+                    && gl_version::has_extension("GL_ARB_texture_swizzle"))
+        };
+
+        let gpu_timer = unsafe {
+            GpuTimer::new(
+                !gl_version.es
+                    && (gl_version.at_least(3, 3)
+                        || gl_version::has_extension("GL_ARB_timer_query")),
+            )
+        };
+
+        Ok(Painter {
+            gl_version,
+            shader_version,
             program,
+            locations,
             vertex_array,
-            index_buffer,
-            pos_buffer,
-            tc_buffer,
-            color_buffer,
+            index_buffer: MeshBuffer::new(gl::ELEMENT_ARRAY_BUFFER, buffer_kind),
+            vertex_buffer: MeshBuffer::new(gl::ARRAY_BUFFER, buffer_kind),
             textures: Default::default(),
+            user_texture_ids: UserTextureIds::default(),
+            scratch: vec![],
+            surface: SurfaceConfig::from_pixel_format(None),
+            check_state: cfg!(debug_assertions),
+            state_divergence: vec![],
+            freed_texture_bytes: 0,
+            atlas_rebuilds: 0,
+            compositor: None,
+            compositing: false,
+            opacity: 1.,
+            multisampled: false,
+            max_anisotropy,
+            compositor_failed: false,
+            font_gamma: 1.,
+            font_weight: 1.,
+            r8_fonts,
+            pixel_buffers: PixelUpload::is_supported(gl_version),
+            frame: FrameCounters::default(),
+            last_frame: FrameCounters::default(),
+            gpu_timer,
+            debug_overlay: false,
+            render_resolution: None,
+            mesh_counts: vec![],
+        })
+    }
+
+    /// Deletes every GL object created by the painter, textures registered with [`Self::new_opengl_texture`]
+    /// are left alone. Must be called with the painter's context current.
+    pub fn destroy(mut self) {
+        for (_, texture) in self.textures.drain() {
+            texture.delete();
+        }
+
+        unsafe {
+            gl::DeleteProgram(self.program);
+            if let Some(vertex_array) = self.vertex_array {
+                gl::DeleteVertexArrays(1, &vertex_array);
+            }
+            if let Some(gpu_timer) = self.gpu_timer.take() {
+                gpu_timer.delete();
+            }
+        }
+
+        // The buffers and the compositor delete their objects when dropped.
+    }
+
+    /// Version of the context the painter was created in.
+    pub fn gl_version(&self) -> GlVersion {
+        self.gl_version
+    }
+
+    /// Whether vertices are streamed through persistently mapped buffers instead of orphaned ones.
+    pub fn uses_persistent_buffers(&self) -> bool {
+        self.vertex_buffer.is_persistent() && self.index_buffer.is_persistent()
+    }
+
+    /// Reads the bottom left `size` pixels of the default framebuffer's back buffer as an opaque image.
+    pub fn read_backbuffer(&self, size: [usize; 2]) -> ColorImage {
+        let [width, height] = size;
+        let rgba = unsafe {
+            let mut read_framebuffer = 0;
+            let mut read_buffer = 0;
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut read_framebuffer);
+            gl::GetIntegerv(gl::READ_BUFFER, &mut read_buffer);
+
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl::ReadBuffer(gl::BACK);
+            let rgba = offscreen::read_rgba(width as _, height as _);
+
+            gl::ReadBuffer(read_buffer as _);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read_framebuffer as _);
+            rgba
+        };
+
+        // The back buffer's alpha is whatever the game left in it, screenshots are opaque.
+        ColorImage {
+            size,
+            pixels: rgba
+                .chunks_exact(4)
+                .map(|p| Color32::from_rgb(p[0], p[1], p[2]))
+                .collect(),
+        }
+    }
+
+    /// Replaces the painting shaders, `None` restores the built-in ones. If the new program fails to build
+    /// or lacks a required input, the current one is kept and the error returned.
+    pub fn set_custom_shader(&mut self, custom: Option<&CustomShader>) -> Result<(), ShaderError> {
+        let (program, locations) = build_program(self.shader_version, custom)?;
+
+        unsafe { gl::DeleteProgram(self.program) };
+        self.program = program;
+        self.locations = locations;
+
+        Ok(())
+    }
+
+    /// Paints the UI into an offscreen framebuffer and blends it onto the target framebuffer
+    /// in a final pass, instead of painting every mesh onto the target directly.
+    pub fn set_compositing(&mut self, enabled: bool) {
+        self.compositing = enabled;
+    }
+
+    /// Fades the whole UI, `1.0` is fully opaque. Values below `1.0` enable compositing.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0., 1.);
+    }
+
+    /// Outlines the clip rect of every primitive in red and the bounds of every mesh in green,
+    /// and counts the meshes painted with every texture, see [`Self::mesh_counts`].
+    pub fn set_debug_overlay(&mut self, enabled: bool) {
+        self.debug_overlay = enabled;
+        self.mesh_counts.clear();
+    }
+
+    /// Meshes painted with every texture in the last frame, empty unless the debug overlay is enabled.
+    pub fn mesh_counts(&self) -> &[(egui::TextureId, u32)] {
+        &self.mesh_counts
+    }
+
+    /// Next [`egui::TextureId::User`] the painter hands out.
+    pub fn next_user_texture(&self) -> u64 {
+        self.user_texture_ids.next
+    }
+
+    /// Continues the IDs of a previous painter, so IDs still held from it don't alias new textures.
+    pub fn set_next_user_texture(&mut self, next: u64) {
+        self.user_texture_ids.continue_from(next);
+    }
+
+    /// Paints the UI at a fixed size in pixels and stretches it onto the target, so layouts look the same
+    /// at every window size. `client_rect` passed to the paint methods stays the target's size. Enables compositing.
+    /// Dropped when compositing is unavailable, see [`Self::render_resolution`].
+    pub fn set_render_resolution(&mut self, resolution: Option<(u32, u32)>) {
+        self.render_resolution = resolution
+            .filter(|&(width, height)| width > 0 && height > 0 && !self.compositor_failed);
+    }
+
+    /// Resolution set with [`Self::set_render_resolution`], `None` after the compositor or its framebuffer
+    /// couldn't be created. The UI is then painted at the target's size and has to be laid out for it.
+    pub fn render_resolution(&self) -> Option<(u32, u32)> {
+        self.render_resolution
+    }
+
+    fn composites(&self) -> bool {
+        !self.compositor_failed
+            && (self.compositing
+                || self.opacity < 1.
+                || self.multisampled
+                || self.render_resolution.is_some())
+    }
+
+    /// Checks whether the bound draw framebuffer is multisampled.
+    unsafe fn detect_multisampling(&mut self) {
+        let mut samples = 0;
+        gl::GetIntegerv(gl::SAMPLES, &mut samples);
+
+        let multisampled = samples > 1;
+        if multisampled != self.multisampled {
+            log!("Target framebuffer has {samples} samples per pixel");
+            self.multisampled = multisampled;
         }
     }
 
+    /// Gamma applied to the coverage of font glyphs, values below `1.0` make text bolder.
+    /// Only affects glyphs uploaded afterwards, rebuild the font atlas to apply it to all text.
+    pub fn set_font_gamma(&mut self, gamma: f32) {
+        self.font_gamma = gamma.max(0.);
+    }
+
+    /// Multiplier of the coverage of font glyphs after [`Self::set_font_gamma`], values above `1.0` make
+    /// text heavier. Only affects glyphs uploaded afterwards, like the gamma.
+    pub fn set_font_weight(&mut self, weight: f32) {
+        self.font_weight = weight.max(0.);
+    }
+
+    /// Enables comparing the GL state before and after each frame and logging any divergence.
+    /// Enabled by default in debug builds.
+    pub fn set_state_check(&mut self, enabled: bool) {
+        self.check_state = enabled;
+    }
+
+    /// Selects the sRGB and blending strategy used for painting.
+    pub fn set_surface(&mut self, surface: SurfaceConfig) {
+        self.surface = surface;
+    }
+
     pub fn paint_and_update_textures(
         &mut self,
         pixels_per_point: f32,
@@ -130,89 +705,325 @@ impl Painter {
         textures_delta: &egui::TexturesDelta,
         client_rect: &(u32, u32),
     ) {
+        let started = Instant::now();
+        self.frame = FrameCounters::default();
+        let guard = unsafe { GlStateGuard::new() };
+        #[cfg(feature = "gl-debug")]
+        let debug_group = unsafe { crate::debug::DebugGroup::push() };
+        let timed = unsafe { self.gpu_timer.as_mut().is_some_and(|timer| timer.begin()) };
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            self.detect_multisampling();
+            if self.pixel_buffers {
+                // A bound buffer would turn the pixel pointers of partial updates into offsets.
+                gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+            }
+        }
+
         for (id, image_delta) in &textures_delta.set {
             self.set_texture(*id, image_delta);
         }
 
-        self.paint_primitives(pixels_per_point, clipped_primitives, client_rect);
+        if is_empty(client_rect) {
+            // Nothing to paint on, e.g. while the window is minimized. The textures are kept up to date,
+            // egui only sends their changes once.
+            self.upload_user_textures();
+            self.poll_pending_uploads();
+        } else if self.composites() {
+            self.paint_composited(pixels_per_point, clipped_primitives, client_rect);
+        } else {
+            self.paint_primitives_unguarded(pixels_per_point, clipped_primitives, client_rect);
+        }
 
         for &id in &textures_delta.free {
             self.free_texture(id);
         }
+
+        if let (true, Some(timer)) = (timed, self.gpu_timer.as_mut()) {
+            unsafe { timer.end() };
+        }
+        #[cfg(feature = "gl-debug")]
+        drop(debug_group);
+
+        self.frame.cpu_time = started.elapsed();
+        self.last_frame = self.frame;
+
+        let before = self.check_state.then(|| guard.state().clone());
+        drop(guard);
+        if let Some(before) = before {
+            self.check_state_divergence(&before);
+        }
+    }
+
+    /// Uploads the texture changes of `frame` and paints it onto the bound framebuffer.
+    pub fn paint_frame(&mut self, frame: &PreparedFrame) {
+        self.paint_and_update_textures(
+            frame.pixels_per_point,
+            &frame.primitives,
+            &frame.textures_delta,
+            &frame.size,
+        );
     }
 
-    /// Main entry-point for painting a frame.
+    fn check_state_divergence(&mut self, before: &GlState) {
+        let divergence = before.diff(unsafe { &GlState::capture() });
+        if divergence != self.state_divergence {
+            if !divergence.is_empty() {
+                log!("Failed to restore GL state: {}", divergence.join(", "));
+            }
+            self.state_divergence = divergence;
+        }
+    }
+
+    /// Main entry-point for painting a frame. The GL state is restored afterwards.
     pub fn paint_primitives(
         &mut self,
         pixels_per_point: f32,
         clipped_primitives: &[egui::ClippedPrimitive],
         client_rect: &(u32, u32),
+    ) {
+        if is_empty(client_rect) {
+            return;
+        }
+
+        let _guard = unsafe { GlStateGuard::new() };
+        unsafe { gl::ActiveTexture(gl::TEXTURE0) };
+        self.paint_primitives_unguarded(pixels_per_point, clipped_primitives, client_rect);
+    }
+
+    fn paint_composited(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        client_rect: &(u32, u32),
+    ) {
+        let compositor = match self.compositor.take() {
+            Some(compositor) => Ok(compositor),
+            None => Compositor::new(self.shader_version),
+        };
+        let mut compositor = match compositor {
+            Ok(compositor) => compositor,
+            Err(e) => {
+                log!("Failed to create the compositor, painting directly: {e}");
+                self.compositor_failed = true;
+                self.render_resolution = None;
+                self.paint_primitives_unguarded(pixels_per_point, clipped_primitives, client_rect);
+                return;
+            }
+        };
+
+        let render_size = self.render_resolution.unwrap_or(*client_rect);
+        if unsafe { compositor.begin(render_size, self.surface) } {
+            // The offscreen framebuffer starts out transparent, its alpha has to accumulate coverage
+            // whatever the surface's blend mode is. That one is used for compositing instead.
+            let surface = self.surface;
+            self.surface.blend = BlendMode::Premultiplied;
+            self.paint_primitives_unguarded(pixels_per_point, clipped_primitives, &render_size);
+            self.surface = surface;
+
+            unsafe {
+                compositor.finish(self.vertex_array, self.surface, self.opacity, *client_rect)
+            };
+            self.frame.draw_calls += 1;
+        } else {
+            log!("Failed to create the compositing framebuffer, painting directly");
+            self.render_resolution = None;
+            self.paint_primitives_unguarded(pixels_per_point, clipped_primitives, client_rect);
+        }
+
+        self.compositor = Some(compositor);
+    }
+
+    fn paint_primitives_unguarded(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        client_rect: &(u32, u32),
     ) {
         self.upload_user_textures();
+        self.poll_pending_uploads();
+        self.prepare_painting(pixels_per_point, client_rect);
+        unsafe {
+            self.vertex_buffer.begin_frame();
+            self.index_buffer.begin_frame();
+        }
+
+        for egui::ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in clipped_primitives
+        {
+            match primitive {
+                Primitive::Mesh(mesh) => {
+                    self.paint_mesh(mesh, clip_rect, pixels_per_point, client_rect);
+                }
+
+                Primitive::Callback(callback) => {
+                    self.paint_callback(callback, clip_rect, pixels_per_point, client_rect);
+                }
+            }
+        }
+
+        if self.debug_overlay {
+            self.paint_debug_overlay(pixels_per_point, clipped_primitives, client_rect);
+        }
+
+        unsafe {
+            self.vertex_buffer.end_frame();
+            self.index_buffer.end_frame();
+        }
+    }
+
+    fn paint_debug_overlay(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[egui::ClippedPrimitive],
+        client_rect: &(u32, u32),
+    ) {
+        const CLIP_COLOR: Color32 = Color32::from_rgb(255, 0, 0);
+        const BOUNDS_COLOR: Color32 = Color32::from_rgb(0, 255, 0);
+
+        // One physical pixel wide, drawn with the white texel of the font atlas.
+        let width = 1. / pixels_per_point;
+        let mut outlines = Mesh::default();
+        let mut outline = |rect: Rect, color| {
+            let Rect { min, max } = rect;
+            for side in [
+                Rect::from_min_max(min, egui::pos2(max.x, min.y + width)),
+                Rect::from_min_max(egui::pos2(min.x, max.y - width), max),
+                Rect::from_min_max(min, egui::pos2(min.x + width, max.y)),
+                Rect::from_min_max(egui::pos2(max.x - width, min.y), max),
+            ] {
+                outlines.add_colored_rect(side, color);
+            }
+        };
+
+        self.mesh_counts.clear();
+        for egui::ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in clipped_primitives
+        {
+            outline(*clip_rect, CLIP_COLOR);
+
+            if let Primitive::Mesh(mesh) = primitive {
+                outline(mesh.calc_bounds(), BOUNDS_COLOR);
+
+                match self
+                    .mesh_counts
+                    .iter_mut()
+                    .find(|(id, _)| *id == mesh.texture_id)
+                {
+                    Some((_, count)) => *count += 1,
+                    None => self.mesh_counts.push((mesh.texture_id, 1)),
+                }
+            }
+        }
+
+        self.paint_mesh(&outlines, &Rect::EVERYTHING, pixels_per_point, client_rect);
+    }
 
+    /// Sets up the state for painting meshes, again after every paint callback.
+    fn prepare_painting(&self, pixels_per_point: f32, client_rect: &(u32, u32)) {
         unsafe {
+            // Left enabled by some games, would hide or cull parts of the UI.
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::STENCIL_TEST);
+            gl::Disable(gl::CULL_FACE);
+
             //Let OpenGL know we are dealing with SRGB colors so that it
             //can do the blending correctly. Not setting the framebuffer
             //leads to darkened, oversaturated colors.
-            gl::Enable(gl::FRAMEBUFFER_SRGB);
+            if self.surface.framebuffer_srgb {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            }
 
+            // Stays enabled for the whole pass, every mesh sets its own scissor box.
             gl::Enable(gl::SCISSOR_TEST);
             gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA); // premultiplied alpha
+            gl::BlendEquation(gl::FUNC_ADD);
+            match self.surface.blend {
+                BlendMode::Premultiplied => gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+                BlendMode::PremultipliedKeepAlpha => gl::BlendFuncSeparate(
+                    gl::ONE,
+                    gl::ONE_MINUS_SRC_ALPHA,
+                    gl::ONE_MINUS_DST_ALPHA,
+                    gl::ONE,
+                ),
+            }
             gl::UseProgram(self.program);
             gl::ActiveTexture(gl::TEXTURE0);
         }
 
-        let u_screen_size = CString::new("u_screen_size").unwrap();
-        let u_screen_size_ptr = u_screen_size.as_ptr();
-        let u_screen_size_loc = unsafe { gl::GetUniformLocation(self.program, u_screen_size_ptr) };
         let screen_size_pixels = egui::vec2(client_rect.0 as f32, client_rect.1 as f32);
         let screen_size_points = screen_size_pixels / pixels_per_point;
 
         unsafe {
+            gl::Uniform1i(
+                self.locations.u_srgb_framebuffer,
+                self.surface.framebuffer_srgb as _,
+            );
             gl::Uniform2f(
-                u_screen_size_loc,
+                self.locations.u_screen_size,
                 screen_size_points.x,
                 screen_size_points.y,
             );
+            gl::Uniform1i(self.locations.u_sampler, 0);
+            gl::Viewport(0, 0, client_rect.0 as i32, client_rect.1 as i32);
         }
+    }
 
-        let u_sampler = CString::new("u_sampler").unwrap();
-        let u_sampler_ptr = u_sampler.as_ptr();
-        let u_sampler_loc = unsafe { gl::GetUniformLocation(self.program, u_sampler_ptr) };
+    fn paint_callback(
+        &self,
+        callback: &PaintCallback,
+        clip_rect: &Rect,
+        pixels_per_point: f32,
+        client_rect: &(u32, u32),
+    ) {
+        let Some(callback_fn) = callback.callback.downcast_ref::<CallbackFn>() else {
+            log!("Unsupported paint callback, expected an egui_opengl_internal::CallbackFn");
+            return;
+        };
+
+        let info = PaintCallbackInfo {
+            viewport: callback.rect,
+            clip_rect: *clip_rect,
+            pixels_per_point,
+            screen_size_px: [client_rect.0, client_rect.1],
+        };
+
+        let viewport = info.viewport_in_pixels();
+        let clip = info.clip_rect_in_pixels();
         unsafe {
-            gl::Uniform1i(u_sampler_loc, 0);
-            gl::Viewport(0, 0, client_rect.0 as i32, client_rect.1 as i32);
+            gl::Viewport(
+                viewport.left_px,
+                viewport.from_bottom_px,
+                viewport.width_px,
+                viewport.height_px,
+            );
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Scissor(
+                clip.left_px,
+                clip.from_bottom_px,
+                clip.width_px,
+                clip.height_px,
+            );
         }
 
-        for egui::ClippedPrimitive {
-            clip_rect,
-            primitive,
-        } in clipped_primitives
-        {
-            match primitive {
-                Primitive::Mesh(mesh) => {
-                    self.paint_mesh(mesh, clip_rect, pixels_per_point, client_rect);
-                    unsafe {
-                        gl::Disable(gl::SCISSOR_TEST);
-                    }
-                }
+        (callback_fn.f)(info, self);
 
-                Primitive::Callback(_) => {
-                    panic!("Custom rendering callbacks are not implemented in egui_glium");
-                }
-            }
-        }
+        // The callback may have changed anything.
+        self.prepare_painting(pixels_per_point, client_rect);
+    }
 
-        unsafe {
-            gl::Disable(gl::FRAMEBUFFER_SRGB);
-        }
+    fn next_user_texture_id(&mut self) -> egui::TextureId {
+        self.user_texture_ids.next_id()
     }
 
     pub fn new_opengl_texture(&mut self, openl_id: u32) -> egui::TextureId {
-        let id = egui::TextureId::User(self.textures.len() as u64);
+        let id = self.next_user_texture_id();
 
-        self.textures.insert(id, UserTexture::from_raw(openl_id));
+        self.insert_texture(id, UserTexture::from_raw(openl_id));
 
         id
     }
@@ -221,20 +1032,140 @@ impl Painter {
         &mut self,
         size: (usize, usize),
         srgba_pixels: &[Color32],
-        filtering: TextureFilter,
+        options: TextureOptions,
     ) -> egui::TextureId {
         assert_eq!(size.0 * size.1, srgba_pixels.len());
 
         let pixels: Vec<u8> = srgba_pixels.iter().flat_map(|a| a.to_array()).collect();
-        let id = egui::TextureId::User(self.textures.len() as u64);
+        let id = self.next_user_texture_id();
 
-        self.textures.insert(
+        self.insert_texture(
             id,
             UserTexture {
                 size,
                 pixels,
                 gl_texture_id: None,
-                filtering,
+                gpu_bytes: 0,
+                options,
+                compressed: None,
+                mip_levels: 1,
+                anisotropy: 1.,
+                coverage: false,
+                owned: true,
+                pending_upload: None,
+                dirty: true,
+            },
+        );
+
+        id
+    }
+
+    /// Decodes a PNG or JPEG image and creates a user texture from it.
+    #[cfg(feature = "image")]
+    pub fn texture_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        options: TextureOptions,
+    ) -> Result<egui::TextureId, crate::Error> {
+        let image = image::load_from_memory(bytes)
+            .map_err(crate::Error::Image)?
+            .into_rgba8();
+        let size = (image.width() as usize, image.height() as usize);
+        let pixels: Vec<Color32> = image
+            .pixels()
+            .map(|p| Color32::from_rgba_unmultiplied(p[0], p[1], p[2], p[3]))
+            .collect();
+
+        Ok(self.new_user_texture(size, &pixels, options))
+    }
+
+    /// Like [`Self::texture_from_bytes`], reading the image from `path`.
+    #[cfg(feature = "image")]
+    pub fn texture_from_file(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        options: TextureOptions,
+    ) -> Result<egui::TextureId, crate::Error> {
+        let bytes =
+            std::fs::read(path).map_err(|e| crate::Error::Image(image::ImageError::IoError(e)))?;
+        self.texture_from_bytes(&bytes, options)
+    }
+
+    /// Creates a texture from the contents of a `.dds` file, keeping its mip chain.
+    /// Block compressed formats need to be supported by the context, see [`CompressedFormat::is_supported`].
+    #[cfg(feature = "dds")]
+    pub fn texture_from_dds(
+        &mut self,
+        bytes: &[u8],
+        options: TextureOptions,
+    ) -> Result<egui::TextureId, crate::Error> {
+        let image = crate::DdsImage::parse(bytes).map_err(crate::Error::Dds)?;
+        let id = self.next_user_texture_id();
+
+        self.insert_texture(
+            id,
+            UserTexture {
+                size: image.size,
+                pixels: image.data,
+                gl_texture_id: None,
+                gpu_bytes: 0,
+                options,
+                compressed: image.format,
+                mip_levels: image.mip_levels,
+                anisotropy: 1.,
+                coverage: false,
+                owned: true,
+                pending_upload: None,
+                dirty: true,
+            },
+        );
+
+        Ok(id)
+    }
+
+    /// Like [`Self::texture_from_dds`], reading the file at `path`.
+    #[cfg(feature = "dds")]
+    pub fn texture_from_dds_file(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        options: TextureOptions,
+    ) -> Result<egui::TextureId, crate::Error> {
+        let bytes = std::fs::read(path).map_err(|e| crate::Error::Dds(crate::DdsError::Io(e)))?;
+        self.texture_from_dds(&bytes, options)
+    }
+
+    /// Creates a texture from block compressed data, e.g. the contents of a DDS file without its header.
+    /// Only the first mip level is used. Check [`CompressedFormat::is_supported`] first,
+    /// unsupported formats fail to upload and render black.
+    pub fn new_compressed_texture(
+        &mut self,
+        size: (usize, usize),
+        format: CompressedFormat,
+        data: &[u8],
+        options: TextureOptions,
+    ) -> egui::TextureId {
+        assert_eq!(
+            format.data_len(size.0, size.1),
+            data.len(),
+            "Mismatch between texture size and compressed data length"
+        );
+
+        let id = self.next_user_texture_id();
+
+        self.insert_texture(
+            id,
+            UserTexture {
+                size,
+                pixels: data.to_vec(),
+                gl_texture_id: None,
+                gpu_bytes: 0,
+                options,
+                compressed: Some(format),
+                mip_levels: 1,
+                anisotropy: 1.,
+                coverage: false,
+                owned: true,
+                pending_upload: None,
                 dirty: true,
             },
         );
@@ -242,18 +1173,48 @@ impl Painter {
         id
     }
 
-    pub fn update_user_texture_data(&mut self, texture_id: &egui::TextureId, pixels: &[Color32]) {
+    /// Enables anisotropic filtering of up to `level` samples for a user texture, keeping images
+    /// drawn rotated or at an angle sharp. `1.0` disables it, levels are clamped to [`Self::max_anisotropy`].
+    /// Textures of [`Self::new_opengl_texture`] only get their anisotropy changed, not their other sampling.
+    pub fn set_texture_anisotropy(&mut self, texture_id: egui::TextureId, level: f32) {
+        if let Some(texture) = self.textures.get_mut(&texture_id) {
+            texture.anisotropy = level;
+            texture.dirty = true;
+        }
+    }
+
+    /// Highest anisotropy level supported by the context, `1.0` if anisotropic filtering is unsupported.
+    pub fn max_anisotropy(&self) -> f32 {
+        self.max_anisotropy
+    }
+
+    pub fn update_user_texture_data(
+        &mut self,
+        texture_id: &egui::TextureId,
+        pixels: &[Color32],
+    ) -> Result<(), crate::Error> {
         let texture = self
             .textures
             .get_mut(texture_id)
-            .expect("Texture with id has not been created");
+            .ok_or(crate::Error::UnknownTexture(*texture_id))?;
+
+        let expected = texture.size.0 * texture.size.1;
+        if expected != pixels.len() {
+            return Err(crate::Error::TextureSize {
+                expected,
+                actual: pixels.len(),
+            });
+        }
 
         texture.pixels = pixels.iter().flat_map(|a| a.to_array()).collect();
+        texture.compressed = None;
+        texture.mip_levels = 1;
         texture.dirty = true;
+        Ok(())
     }
 
     fn paint_mesh(
-        &self,
+        &mut self,
         mesh: &Mesh,
         clip_rect: &Rect,
         pixels_per_point: f32,
@@ -261,13 +1222,14 @@ impl Painter {
     ) {
         debug_assert!(mesh.is_valid());
 
-        if let Some(it) = self.textures.get(&mesh.texture_id) {
+        if let Some(gl_texture_id) = self
+            .textures
+            .get(&mesh.texture_id)
+            .filter(|it| it.pending_upload.is_none())
+            .and_then(|it| it.gl_texture_id)
+        {
             unsafe {
-                gl::BindTexture(
-                    gl::TEXTURE_2D,
-                    it.gl_texture_id
-                        .expect("Texture should have a valid OpenGL id now"),
-                );
+                gl::BindTexture(gl::TEXTURE_2D, gl_texture_id);
             }
 
             let screen_size_pixels = egui::vec2(client_rect.0 as f32, client_rect.1 as f32);
@@ -295,133 +1257,50 @@ impl Painter {
                 );
             }
 
-            let indices: Vec<u16> = mesh.indices.iter().map(move |idx| *idx as u16).collect();
-            let indices_len = indices.len();
-            let vertices_len = mesh.vertices.len();
-
-            unsafe {
-                gl::BindVertexArray(self.vertex_array);
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.index_buffer);
-                gl::BufferData(
-                    gl::ELEMENT_ARRAY_BUFFER,
-                    (indices_len * core::mem::size_of::<u16>()) as GLsizeiptr,
-                    //mem::transmute(&indices.as_ptr()),
-                    indices.as_ptr() as *const gl::types::GLvoid,
-                    gl::STREAM_DRAW,
-                );
-            }
-
-            let mut positions: Vec<f32> = Vec::with_capacity(2 * vertices_len);
-            let mut tex_coords: Vec<f32> = Vec::with_capacity(2 * vertices_len);
-            let mut colors: Vec<u8> = Vec::with_capacity(4 * vertices_len);
-            for v in &mesh.vertices {
-                positions.push(v.pos.x);
-                positions.push(v.pos.y);
-
-                tex_coords.push(v.uv.x);
-                tex_coords.push(v.uv.y);
-
-                colors.push(v.color[0]);
-                colors.push(v.color[1]);
-                colors.push(v.color[2]);
-                colors.push(v.color[3]);
-            }
-
-            unsafe {
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.pos_buffer);
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,
-                    (positions.len() * core::mem::size_of::<f32>()) as GLsizeiptr,
-                    //mem::transmute(&positions.as_ptr()),
-                    positions.as_ptr() as *const gl::types::GLvoid,
-                    gl::STREAM_DRAW,
-                );
-            }
-
-            let a_pos = CString::new("a_pos").unwrap();
-            let a_pos_ptr = a_pos.as_ptr();
-            let a_pos_loc = unsafe { gl::GetAttribLocation(self.program, a_pos_ptr) };
-            assert!(a_pos_loc >= 0);
-            let a_pos_loc = a_pos_loc as u32;
+            // egui meshes may exceed 65535 vertices (large scroll areas, plots), so the
+            // indices are uploaded as they are instead of being narrowed to u16.
+            let indices_len = mesh.indices.len();
 
-            let stride = 0;
-            unsafe {
-                gl::VertexAttribPointer(
-                    a_pos_loc,
-                    2,
-                    gl::FLOAT,
-                    gl::FALSE,
-                    stride,
-                    core::ptr::null(),
-                );
-                gl::EnableVertexAttribArray(a_pos_loc);
-
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.tc_buffer);
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,
-                    (tex_coords.len() * core::mem::size_of::<f32>()) as GLsizeiptr,
-                    //mem::transmute(&tex_coords.as_ptr()),
-                    tex_coords.as_ptr() as *const gl::types::GLvoid,
-                    gl::STREAM_DRAW,
-                );
-            }
+            let index_offset = unsafe {
+                if let Some(vertex_array) = self.vertex_array {
+                    gl::BindVertexArray(vertex_array);
+                }
+                self.index_buffer.upload(&mesh.indices)
+            };
 
-            let a_tc = CString::new("a_tc").unwrap();
-            let a_tc_ptr = a_tc.as_ptr();
-            let a_tc_loc = unsafe { gl::GetAttribLocation(self.program, a_tc_ptr) };
-            assert!(a_tc_loc >= 0);
-            let a_tc_loc = a_tc_loc as u32;
+            let stride = core::mem::size_of::<Vertex>() as GLsizei;
+            let attribs = [
+                (self.locations.a_pos, 2, gl::FLOAT, offset_of!(Vertex, pos)),
+                (self.locations.a_tc, 2, gl::FLOAT, offset_of!(Vertex, uv)),
+                (
+                    self.locations.a_srgba,
+                    4,
+                    gl::UNSIGNED_BYTE,
+                    offset_of!(Vertex, color),
+                ),
+            ];
 
-            let stride = 0;
             unsafe {
-                gl::VertexAttribPointer(
-                    a_tc_loc,
-                    2,
-                    gl::FLOAT,
-                    gl::FALSE,
-                    stride,
-                    core::ptr::null(),
-                );
-                gl::EnableVertexAttribArray(a_tc_loc);
-
-                gl::BindBuffer(gl::ARRAY_BUFFER, self.color_buffer);
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,
-                    (colors.len() * core::mem::size_of::<u8>()) as GLsizeiptr,
-                    //mem::transmute(&colors.as_ptr()),
-                    colors.as_ptr() as *const gl::types::GLvoid,
-                    gl::STREAM_DRAW,
-                );
-            }
-
-            let a_srgba = CString::new("a_srgba").unwrap();
-            let a_srgba_ptr = a_srgba.as_ptr();
-            let a_srgba_loc = unsafe { gl::GetAttribLocation(self.program, a_srgba_ptr) };
-            assert!(a_srgba_loc >= 0);
-            let a_srgba_loc = a_srgba_loc as u32;
+                let vertex_offset = self.vertex_buffer.upload(&mesh.vertices);
 
-            let stride = 0;
-            unsafe {
-                gl::VertexAttribPointer(
-                    a_srgba_loc,
-                    4,
-                    gl::UNSIGNED_BYTE,
-                    gl::FALSE,
-                    stride,
-                    core::ptr::null(),
-                );
-                gl::EnableVertexAttribArray(a_srgba_loc);
+                for (location, size, ty, offset) in attribs {
+                    let offset = vertex_offset + offset;
+                    gl::VertexAttribPointer(location, size, ty, gl::FALSE, stride, offset as _);
+                    gl::EnableVertexAttribArray(location);
+                }
 
                 gl::DrawElements(
                     gl::TRIANGLES,
                     indices_len as i32,
-                    gl::UNSIGNED_SHORT,
-                    core::ptr::null(),
+                    gl::UNSIGNED_INT,
+                    index_offset as *const _,
                 );
-                gl::DisableVertexAttribArray(a_pos_loc);
-                gl::DisableVertexAttribArray(a_tc_loc);
-                gl::DisableVertexAttribArray(a_srgba_loc);
+                for (location, ..) in attribs {
+                    gl::DisableVertexAttribArray(location);
+                }
             }
+
+            self.frame.count_mesh(mesh.vertices.len(), indices_len);
         }
     }
 
@@ -430,6 +1309,9 @@ impl Painter {
 
         if let Some([x, y]) = delta.pos {
             if let Some(texture) = self.textures.get_mut(&tex_id) {
+                // Applied with the next upload.
+                texture.options = delta.options;
+
                 match &delta.image {
                     egui::ImageData::Color(image) => {
                         assert_eq!(
@@ -438,10 +1320,9 @@ impl Painter {
                             "Mismatch between texture size and texel count"
                         );
 
-                        let data: Vec<u8> =
-                            image.pixels.iter().flat_map(|a| a.to_array()).collect();
-
-                        texture.update_texture_part(x as _, y as _, w as _, h as _, &data);
+                        self.scratch.clear();
+                        self.scratch
+                            .extend(image.pixels.iter().flat_map(|a| a.to_array()));
                     }
 
                     egui::ImageData::Font(image) => {
@@ -451,15 +1332,18 @@ impl Painter {
                             "Mismatch between texture size and texel count"
                         );
 
-                        let gamma = 1.0;
-                        let data: Vec<u8> = image
-                            .srgba_pixels(Some(gamma))
-                            .flat_map(|a| a.to_array())
-                            .collect();
-
-                        texture.update_texture_part(x as _, y as _, w as _, h as _, &data);
+                        font_pixels(
+                            image,
+                            self.font_gamma,
+                            self.font_weight,
+                            texture.coverage,
+                            &mut self.scratch,
+                        );
                     }
                 }
+
+                texture.update_texture_part(x as _, y as _, w as _, h as _, &self.scratch, w as _);
+                self.frame.texture_uploads += 1;
             } else {
                 eprintln!("Failed to find egui texture {:?}", tex_id);
             }
@@ -478,7 +1362,14 @@ impl Painter {
                         size: (w, h),
                         pixels,
                         gl_texture_id: None,
-                        filtering: TextureFilter::Linear,
+                        gpu_bytes: 0,
+                        options: delta.options,
+                        compressed: None,
+                        mip_levels: 1,
+                        anisotropy: 1.,
+                        coverage: false,
+                        owned: true,
+                        pending_upload: None,
                         dirty: true,
                     }
                 }
@@ -489,34 +1380,60 @@ impl Painter {
                         "Mismatch between texture size and texel count"
                     );
 
-                    let gamma = 1.0;
-                    let pixels = image
-                        .srgba_pixels(Some(gamma))
-                        .flat_map(|a| a.to_array())
-                        .collect();
+                    let mut pixels = vec![];
+                    font_pixels(
+                        image,
+                        self.font_gamma,
+                        self.font_weight,
+                        self.r8_fonts,
+                        &mut pixels,
+                    );
 
                     UserTexture {
                         size: (w, h),
                         pixels,
                         gl_texture_id: None,
-                        filtering: TextureFilter::Linear,
+                        gpu_bytes: 0,
+                        options: delta.options,
+                        compressed: None,
+                        mip_levels: 1,
+                        anisotropy: 1.,
+                        coverage: self.r8_fonts,
+                        owned: true,
+                        pending_upload: None,
                         dirty: true,
                     }
                 }
             };
 
-            let previous = self.textures.insert(tex_id, texture);
-            if let Some(previous) = previous {
-                previous.delete();
+            if self.insert_texture(tex_id, texture) && tex_id == egui::TextureId::default() {
+                self.atlas_rebuilds += 1;
+            }
+        }
+    }
+
+    /// Forgets the pixel buffer objects of completed uploads, their textures are drawn from now on.
+    fn poll_pending_uploads(&mut self) {
+        for texture in self.textures.values_mut() {
+            if let Some(upload) = texture
+                .pending_upload
+                .take_if(|upload| unsafe { upload.is_complete() })
+            {
+                unsafe { upload.delete() };
             }
         }
     }
 
     fn upload_user_textures(&mut self) {
+        let max_anisotropy = self.max_anisotropy;
+        let pixel_buffers = self.pixel_buffers;
+        let mut uploads = 0;
         self.textures
-            .values_mut()
-            .filter(|user_texture| user_texture.gl_texture_id.is_none() || user_texture.dirty)
-            .for_each(|user_texture| {
+            .iter_mut()
+            .filter(|(_, user_texture)| user_texture.gl_texture_id.is_none() || user_texture.dirty)
+            .for_each(|(id, user_texture)| {
+                // egui's own textures, like the font atlas, are needed right away.
+                let stage_upload = pixel_buffers && matches!(id, egui::TextureId::User(_));
                 let pixels = std::mem::take(&mut user_texture.pixels);
 
                 match user_texture.gl_texture_id {
@@ -529,77 +1446,195 @@ impl Painter {
                         unsafe {
                             gl::GenTextures(1, &mut gl_texture);
                             gl::BindTexture(gl::TEXTURE_2D, gl_texture);
-                            gl::TexParameteri(
-                                gl::TEXTURE_2D,
-                                gl::TEXTURE_WRAP_S,
-                                gl::CLAMP_TO_EDGE as i32,
-                            );
-                            gl::TexParameteri(
-                                gl::TEXTURE_2D,
-                                gl::TEXTURE_WRAP_T,
-                                gl::CLAMP_TO_EDGE as i32,
-                            );
-                        }
-
-                        match user_texture.filtering {
-                            TextureFilter::Nearest => unsafe {
-                                gl::TexParameteri(
-                                    gl::TEXTURE_2D,
-                                    gl::TEXTURE_MIN_FILTER,
-                                    gl::LINEAR as i32,
-                                );
-                                gl::TexParameteri(
-                                    gl::TEXTURE_2D,
-                                    gl::TEXTURE_MAG_FILTER,
-                                    gl::LINEAR as i32,
-                                );
-                            },
-
-                            TextureFilter::Linear => unsafe {
-                                gl::TexParameteri(
-                                    gl::TEXTURE_2D,
-                                    gl::TEXTURE_MIN_FILTER,
-                                    gl::NEAREST as i32,
-                                );
-                                gl::TexParameteri(
-                                    gl::TEXTURE_2D,
-                                    gl::TEXTURE_MAG_FILTER,
-                                    gl::NEAREST as i32,
-                                );
-                            },
                         }
                         user_texture.gl_texture_id = Some(gl_texture);
                     }
                 }
 
+                unsafe { user_texture.apply_options(max_anisotropy) };
+
                 if !pixels.is_empty() {
-                    let level = 0;
-                    let internal_format = gl::RGBA;
-                    let border = 0;
-                    let src_format = gl::RGBA;
-                    let src_type = gl::UNSIGNED_BYTE;
                     unsafe {
-                        gl::TexImage2D(
-                            gl::TEXTURE_2D,
-                            level,
-                            internal_format as i32,
-                            user_texture.size.0 as i32,
-                            user_texture.size.1 as i32,
-                            border,
-                            src_format,
-                            src_type,
-                            pixels.as_ptr() as *const c_void,
-                        );
+                        // Left over by partial updates or by the game when painting in its context.
+                        gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+                        gl::PixelStorei(gl::UNPACK_SKIP_PIXELS, 0);
+                        gl::PixelStorei(gl::UNPACK_SKIP_ROWS, 0);
+                        if pixel_buffers {
+                            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+                        }
                     }
+
+                    let mut offset = 0;
+                    for level in 0..user_texture.mip_levels {
+                        let (width, height) = compressed::level_size(user_texture.size, level);
+                        let len = match user_texture.coverage {
+                            true => width * height,
+                            false => compressed::level_len(user_texture.compressed, width, height),
+                        };
+                        let data = pixels[offset..offset + len].as_ptr() as *const c_void;
+                        offset += len;
+
+                        unsafe {
+                            match user_texture.compressed {
+                                Some(format) => gl::CompressedTexImage2D(
+                                    gl::TEXTURE_2D,
+                                    level as i32,
+                                    format.gl_format(),
+                                    width as i32,
+                                    height as i32,
+                                    0,
+                                    len as i32,
+                                    data,
+                                ),
+                                None if user_texture.coverage => {
+                                    // Rows of single byte texels aren't 4 byte aligned.
+                                    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                                    gl::TexImage2D(
+                                        gl::TEXTURE_2D,
+                                        level as i32,
+                                        gl::R8 as i32,
+                                        width as i32,
+                                        height as i32,
+                                        0,
+                                        gl::RED,
+                                        gl::UNSIGNED_BYTE,
+                                        data,
+                                    )
+                                }
+                                None if stage_upload
+                                    && user_texture.mip_levels == 1
+                                    && len >= PBO_UPLOAD_MIN_BYTES =>
+                                {
+                                    // Replaces an upload that's still in flight.
+                                    if let Some(upload) = user_texture.pending_upload.take() {
+                                        upload.delete();
+                                    }
+
+                                    let mut upload = PixelUpload::stage(&pixels[..len]);
+                                    gl::TexImage2D(
+                                        gl::TEXTURE_2D,
+                                        0,
+                                        gl::RGBA as i32,
+                                        width as i32,
+                                        height as i32,
+                                        0,
+                                        gl::RGBA,
+                                        gl::UNSIGNED_BYTE,
+                                        std::ptr::null(),
+                                    );
+                                    upload.submit();
+                                    user_texture.pending_upload = Some(upload);
+                                }
+                                None => gl::TexImage2D(
+                                    gl::TEXTURE_2D,
+                                    level as i32,
+                                    gl::RGBA as i32,
+                                    width as i32,
+                                    height as i32,
+                                    0,
+                                    gl::RGBA,
+                                    gl::UNSIGNED_BYTE,
+                                    data,
+                                ),
+                            }
+                        }
+                    }
+                    user_texture.gpu_bytes = offset;
+                    uploads += 1;
                 }
 
                 user_texture.dirty = false;
             });
+        self.frame.texture_uploads += uploads;
     }
 
     pub fn free_texture(&mut self, tex_id: egui::TextureId) {
         if let Some(old_tex) = self.textures.remove(&tex_id) {
-            old_tex.delete();
+            self.delete_texture(old_tex);
+        }
+    }
+
+    /// Inserts `texture`, deleting the one it replaces. Returns `true` if one was replaced.
+    fn insert_texture(&mut self, tex_id: egui::TextureId, texture: UserTexture) -> bool {
+        match self.textures.insert(tex_id, texture) {
+            Some(previous) => {
+                self.delete_texture(previous);
+                true
+            }
+            None => false,
         }
     }
+
+    fn delete_texture(&mut self, texture: UserTexture) {
+        texture.delete();
+        self.freed_texture_bytes += texture.gpu_bytes as u64;
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        let owned = self.textures.values().filter(|t| t.gpu_bytes > 0);
+
+        RenderStats {
+            textures: owned.clone().count(),
+            texture_bytes: owned.map(|t| t.gpu_bytes).sum(),
+            freed_texture_bytes: self.freed_texture_bytes,
+            buffer_bytes: self.vertex_buffer.capacity() + self.index_buffer.capacity(),
+            atlas_rebuilds: self.atlas_rebuilds,
+            draw_calls: self.last_frame.draw_calls,
+            meshes: self.last_frame.meshes,
+            vertices: self.last_frame.vertices,
+            indices: self.last_frame.indices,
+            texture_uploads: self.last_frame.texture_uploads,
+            cpu_time: self.last_frame.cpu_time,
+            gpu_time: self.gpu_timer.as_ref().and_then(GpuTimer::last),
+            // Counted by `OpenGLApp`, which decides when the UI runs.
+            ..Default::default()
+        }
+    }
+
+    /// OpenGL name of an uploaded texture, e.g. for sampling it in a [`CallbackFn`].
+    pub fn gl_texture(&self, texture_id: egui::TextureId) -> Option<GLuint> {
+        self.textures.get(&texture_id)?.gl_texture_id
+    }
+
+    /// Largest texture width and height supported by the current context.
+    pub fn max_texture_side(&self) -> usize {
+        let mut side = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut side) };
+        side.max(0) as usize
+    }
+}
+
+/// Whether `client_rect` has no area, e.g. the client area of a minimized window.
+fn is_empty(client_rect: &(u32, u32)) -> bool {
+    client_rect.0 == 0 || client_rect.1 == 0
+}
+
+#[test]
+fn test_update_pending_texture_part() {
+    let mut texture = UserTexture {
+        pixels: vec![0; 4 * 3],
+        size: (4, 3),
+        coverage: true,
+        owned: true,
+        gl_texture_id: None,
+        ..UserTexture::from_raw(0)
+    };
+
+    // A 2x2 rect out of a source image that's 3 texels wide.
+    texture.update_texture_part(1, 1, 2, 2, &[1, 2, 0, 3, 4], 3);
+    assert_eq!(texture.pixels, [0, 0, 0, 0, 0, 1, 2, 0, 0, 3, 4, 0]);
+}
+
+#[test]
+fn test_user_texture_ids() {
+    let mut ids = UserTextureIds::default();
+    assert_eq!(ids.next_id(), egui::TextureId::User(0));
+    assert_eq!(ids.next_id(), egui::TextureId::User(1));
+
+    // A rebuilt painter continues after the IDs of the previous one, never going back.
+    let mut rebuilt = UserTextureIds::default();
+    rebuilt.continue_from(ids.next);
+    assert_eq!(rebuilt.next_id(), egui::TextureId::User(2));
+    rebuilt.continue_from(1);
+    assert_eq!(rebuilt.next_id(), egui::TextureId::User(3));
 }
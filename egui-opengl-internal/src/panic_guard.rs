@@ -0,0 +1,96 @@
+//! Keeps panics of the UI closure from unwinding into the game.
+
+use egui::Context;
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    panic::AssertUnwindSafe,
+    sync::Once,
+};
+
+thread_local! {
+    /// Set while the UI runs, so the panic hook only records backtraces of UI panics.
+    static IN_UI: Cell<bool> = const { Cell::new(false) };
+    static BACKTRACE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// A panic caught while running the UI closure.
+pub struct UiPanic {
+    pub message: String,
+    pub backtrace: String,
+}
+
+/// Runs `f`, catching a panic instead of letting it unwind further. Builds with `panic = "abort"`
+/// still abort.
+pub fn catch(f: impl FnOnce()) -> Result<(), UiPanic> {
+    install_hook();
+
+    IN_UI.with(|in_ui| in_ui.set(true));
+    let result = std::panic::catch_unwind(AssertUnwindSafe(f));
+    IN_UI.with(|in_ui| in_ui.set(false));
+
+    result.map_err(|payload| UiPanic {
+        message: panic_message(payload.as_ref()),
+        backtrace: BACKTRACE
+            .with(|backtrace| backtrace.borrow_mut().take())
+            .unwrap_or_default(),
+    })
+}
+
+/// The backtrace is gone once `catch_unwind` returns, the hook captures it while the stack still exists.
+/// Other panics go to the hook that was installed before.
+fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if IN_UI.with(Cell::get) {
+                let backtrace = Backtrace::force_capture().to_string();
+                BACKTRACE.with(|slot| *slot.borrow_mut() = Some(backtrace));
+            }
+            previous(info);
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_owned()
+    }
+}
+
+/// Shows the panic in place of the UI. Returns `true` once the user asked to run the UI again.
+pub fn show_window(ctx: &Context, panic: &UiPanic) -> bool {
+    let mut resume = false;
+    egui::Window::new("UI panicked")
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label("The UI stopped running after a panic:");
+            ui.monospace(&panic.message);
+            ui.horizontal(|ui| {
+                if ui.button("Copy backtrace").clicked() {
+                    ui.output_mut(|output| {
+                        output.copied_text = format!("{}\n\n{}", panic.message, panic.backtrace)
+                    });
+                }
+                resume = ui.button("Resume UI").clicked();
+            });
+        });
+    resume
+}
+
+#[test]
+fn test_catch() {
+    assert!(catch(|| {}).is_ok());
+
+    let panic = catch(|| panic!("boom {}", 42)).err().unwrap();
+    assert_eq!(panic.message, "boom 42");
+    assert!(!panic.backtrace.is_empty());
+    assert!(!IN_UI.with(Cell::get));
+}
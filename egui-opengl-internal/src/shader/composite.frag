@@ -0,0 +1,20 @@
+#ifdef GL_ES
+precision highp float;
+#endif
+
+uniform sampler2D u_sampler;
+uniform float u_opacity;
+
+#if NEW_SHADER_INTERFACE
+in vec2 v_tc;
+out vec4 f_color;
+#define gl_FragColor f_color
+#define texture2D texture
+#else
+varying vec2 v_tc;
+#endif
+
+void main() {
+    // The UI is premultiplied, so fading it scales every channel.
+    gl_FragColor = texture2D(u_sampler, v_tc) * u_opacity;
+}
@@ -0,0 +1,17 @@
+#if NEW_SHADER_INTERFACE
+#define I in
+#define O out
+#else
+#define I attribute
+#define O varying
+#endif
+
+// Corners of the screen in normalized device coordinates.
+I vec2 a_pos;
+
+O vec2 v_tc;
+
+void main() {
+    v_tc = a_pos * 0.5 + 0.5;
+    gl_Position = vec4(a_pos, 0.0, 1.0);
+}
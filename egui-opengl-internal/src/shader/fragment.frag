@@ -1,11 +1,19 @@
-#version 100
+#ifdef GL_ES
+precision highp float;
+#endif
 
 uniform sampler2D u_sampler;
 
-precision highp float;
-
+#if NEW_SHADER_INTERFACE
+in vec2 v_tc;
+in vec4 v_rgba;
+out vec4 f_color;
+#define gl_FragColor f_color
+#define texture2D texture
+#else
 varying vec2 v_tc;
 varying vec4 v_rgba;
+#endif
 
 // 0-1 linear  from  0-255 sRGB
 vec3 linear_from_srgb(vec3 srgb) {
@@ -1,69 +1,205 @@
-use gl::types::{GLenum, GLuint, GLint, GLchar};
+use crate::GlVersion;
+use gl::types::{GLenum, GLuint, GLint, GLchar, GLsizei};
+use std::ffi::CString;
+use thiserror::Error;
+
+/// Failure to build a shader program, with the driver's info log.
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("failed to compile {stage} shader: {log}")]
+    Compile { stage: &'static str, log: String },
+
+    #[error("failed to link shader program: {0}")]
+    Link(String),
+
+    #[error("shader program doesn't use {0}")]
+    MissingInput(&'static str),
+}
+
+/// Replacement sources for the painter's shaders, e.g. to tint the whole UI or add scanlines.
+/// `None` keeps the built-in stage. Sources must not start with `#version`, the painter prepends the
+/// version matching the context and defines `NEW_SHADER_INTERFACE` to `1` where `in`/`out` replace
+/// `attribute`/`varying`. See `shader/vertex.vert` and `shader/fragment.frag` for the inputs to keep:
+/// the attributes `a_pos`, `a_tc`, `a_srgba` and the uniforms `u_screen_size`, `u_sampler`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CustomShader {
+    pub vertex: Option<String>,
+    pub fragment: Option<String>,
+}
 
 pub struct Shader;
 
 impl Shader {
-    pub fn compile_shader(src: &str, ty: GLenum) -> GLuint {
+    /// Compiles, links and validates a program, deleting the intermediate shader objects.
+    pub fn build(vertex_src: &str, fragment_src: &str) -> Result<GLuint, ShaderError> {
+        let vs = Self::compile_shader(vertex_src, gl::VERTEX_SHADER)?;
+        let fs = match Self::compile_shader(fragment_src, gl::FRAGMENT_SHADER) {
+            Ok(fs) => fs,
+            Err(e) => {
+                unsafe { gl::DeleteShader(vs) };
+                return Err(e);
+            }
+        };
+
+        let program = Self::link_program(vs, fs);
+        unsafe {
+            gl::DeleteShader(vs);
+            gl::DeleteShader(fs);
+        }
+
+        program
+    }
+
+    pub fn compile_shader(src: &str, ty: GLenum) -> Result<GLuint, ShaderError> {
         let id = unsafe { gl::CreateShader(ty) };
+        let mut status = gl::FALSE as GLint;
         unsafe {
-            let ptr: *const u8 = src.as_bytes().as_ptr();
-            let ptr_i8: *const i8 = std::mem::transmute(ptr);
+            let ptr = src.as_ptr() as *const GLchar;
             let len = src.len() as GLint;
-            gl::ShaderSource(id, 1, &ptr_i8, &len);
-        }
-    
-        let successful = unsafe {
+            gl::ShaderSource(id, 1, &ptr, &len);
             gl::CompileShader(id);
-    
-            let mut result: GLint = 0;
-            gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut result);
-            result != 0
-        };
-    
-        if !successful {
-            panic!()
+            gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut status);
         }
-    
-        id
+
+        if status != gl::TRUE as GLint {
+            let log = unsafe { info_log(id, gl::GetShaderiv, gl::GetShaderInfoLog) };
+            unsafe { gl::DeleteShader(id) };
+
+            let stage = match ty {
+                gl::VERTEX_SHADER => "vertex",
+                gl::FRAGMENT_SHADER => "fragment",
+                _ => "unknown",
+            };
+            return Err(ShaderError::Compile { stage, log });
+        }
+
+        Ok(id)
     }
-    
-    pub fn link_program(vs: GLuint, fs: GLuint) -> GLuint {
+
+    /// Links `vs` and `fs` into a program. The shaders are left attached and owned by the caller.
+    pub fn link_program(vs: GLuint, fs: GLuint) -> Result<GLuint, ShaderError> {
         let program = unsafe { gl::CreateProgram() };
-    
+        let mut status = gl::FALSE as GLint;
         unsafe {
             gl::AttachShader(program, vs);
             gl::AttachShader(program, fs);
             gl::LinkProgram(program);
-        }
-    
-        let mut status = gl::FALSE as GLint;
-        unsafe {
             gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
         }
-    
-        if status != (gl::TRUE as GLint) {
-            let mut len: GLint = 0;
-            unsafe {
-                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
-            }
-    
-            let mut buf = vec![0; len as usize];
-    
-            unsafe {
-                gl::GetProgramInfoLog(
-                    program,
-                    len,
-                    core::ptr::null_mut(),
-                    buf.as_mut_ptr() as *mut GLchar,
-                );
+
+        if status != gl::TRUE as GLint {
+            let log = unsafe { info_log(program, gl::GetProgramiv, gl::GetProgramInfoLog) };
+            unsafe { gl::DeleteProgram(program) };
+            return Err(ShaderError::Link(log));
+        }
+
+        Ok(program)
+    }
+}
+
+/// Reads the info log of a shader or program with the matching pair of GL functions.
+unsafe fn info_log(
+    id: GLuint,
+    get_iv: unsafe fn(GLuint, GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    let mut len: GLint = 0;
+    get_iv(id, gl::INFO_LOG_LENGTH, &mut len);
+
+    let mut buf = vec![0u8; len.max(1) as usize];
+    let mut written: GLsizei = 0;
+    get_log(id, buf.len() as _, &mut written, buf.as_mut_ptr() as *mut GLchar);
+    buf.truncate(written.max(0) as usize);
+
+    String::from_utf8_lossy(&buf).trim_end().to_owned()
+}
+
+/// Looks up attribute and uniform locations of a linked program by name.
+/// Lookups are slow, resolve them once after linking and keep the result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramLocations {
+    program: GLuint,
+}
+
+impl ProgramLocations {
+    pub fn new(program: GLuint) -> Self {
+        Self { program }
+    }
+
+    /// Location of the vertex attribute `name`, `None` if the program has no active attribute with that name.
+    pub fn attrib(&self, name: &str) -> Option<GLuint> {
+        let name = CString::new(name).ok()?;
+        let location = unsafe { gl::GetAttribLocation(self.program, name.as_ptr()) };
+        (location >= 0).then_some(location as GLuint)
+    }
+
+    /// Location of the uniform `name`, `-1` if it doesn't exist or was optimized out.
+    /// Setting a uniform at `-1` is silently ignored by OpenGL.
+    pub fn uniform(&self, name: &str) -> GLint {
+        match CString::new(name) {
+            Ok(name) => unsafe { gl::GetUniformLocation(self.program, name.as_ptr()) },
+            Err(_) => -1,
+        }
+    }
+}
+
+/// GLSL dialect the built-in shaders are compiled as, picked from the context's version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderVersion {
+    /// GLSL ES 1.00 for OpenGL ES 2 contexts.
+    Es100,
+    /// GLSL 1.20 for OpenGL 2.1 and 3.0.
+    Gl120,
+    /// GLSL 1.40 for OpenGL 3.1.
+    Gl140,
+    /// GLSL 3.30 for OpenGL 3.3 and later, required by core-profile contexts.
+    Gl330,
+    /// GLSL ES 3.00 for OpenGL ES 3 contexts.
+    Es300,
+}
+
+impl ShaderVersion {
+    pub fn for_gl(version: GlVersion) -> Self {
+        if version.es {
+            if version.at_least(3, 0) {
+                Self::Es300
+            } else {
+                Self::Es100
             }
-    
-            panic!(
-                "{}",
-                core::str::from_utf8(&buf).expect("ProgramInfoLog not valid utf8")
-            );
+        } else if version.at_least(3, 3) {
+            Self::Gl330
+        } else if version.at_least(3, 1) || version.core_profile {
+            Self::Gl140
+        } else {
+            Self::Gl120
         }
-    
-        program
     }
+
+    /// Lines prepended to the shader sources, which use `NEW_SHADER_INTERFACE` to pick
+    /// `in`/`out` over `attribute`/`varying`.
+    fn header(self) -> &'static str {
+        match self {
+            Self::Es100 => "#version 100\n#define NEW_SHADER_INTERFACE 0\n",
+            Self::Gl120 => "#version 120\n#define NEW_SHADER_INTERFACE 0\n",
+            Self::Gl140 => "#version 140\n#define NEW_SHADER_INTERFACE 1\n",
+            Self::Gl330 => "#version 330 core\n#define NEW_SHADER_INTERFACE 1\n",
+            Self::Es300 => "#version 300 es\n#define NEW_SHADER_INTERFACE 1\n",
+        }
+    }
+
+    /// Prepends the version header to `src`.
+    pub fn source(self, src: &str) -> String {
+        format!("{}{src}", self.header())
+    }
+}
+
+#[test]
+fn test_shader_version() {
+    let core = GlVersion {
+        core_profile: true,
+        ..GlVersion::new(3, 2)
+    };
+    assert_eq!(ShaderVersion::for_gl(core), ShaderVersion::Gl140);
+    assert_eq!(ShaderVersion::for_gl(GlVersion::new(4, 6)), ShaderVersion::Gl330);
+    assert_eq!(ShaderVersion::for_gl(GlVersion::new(2, 1)), ShaderVersion::Gl120);
 }
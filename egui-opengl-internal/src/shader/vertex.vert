@@ -1,13 +1,21 @@
-#version 100
+#if NEW_SHADER_INTERFACE
+#define I in
+#define O out
+#else
+#define I attribute
+#define O varying
+#endif
 
 uniform vec2 u_screen_size;
+// When false the framebuffer can't encode linear colors, so they are output in gamma space.
+uniform bool u_srgb_framebuffer;
 
-attribute vec2 a_pos;
-attribute vec2 a_tc;
-attribute vec4 a_srgba;
+I vec2 a_pos;
+I vec2 a_tc;
+I vec4 a_srgba;
 
-varying vec2 v_tc;
-varying vec4 v_rgba;
+O vec2 v_tc;
+O vec4 v_rgba;
 
 // 0-1 linear  from  0-255 sRGB
 vec3 linear_from_srgb(vec3 srgb) {
@@ -44,6 +52,10 @@ void main() {
         0.0,
     1.0);
     v_tc = a_tc;
-    v_rgba = linear_from_srgba(a_srgba);
-    v_rgba.a = pow(v_rgba.a, 1.6);
+    if (u_srgb_framebuffer) {
+        v_rgba = linear_from_srgba(a_srgba);
+        v_rgba.a = pow(v_rgba.a, 1.6);
+    } else {
+        v_rgba = a_srgba / 255.0;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,84 @@
+//! Keeps the app state between launches, see [`crate::OpenGLApp::init_with_persisted_state`].
+
+use crate::{
+    memory::{self, Autosave},
+    Error,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Reads the state saved at `path`, a missing file yields `None`.
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, Error> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::State(e)),
+    };
+
+    ron::from_str(&text)
+        .map(Some)
+        .map_err(|e| Error::State(memory::invalid_data(e)))
+}
+
+fn serialize<T: Serialize>(state: &T) -> Result<String, Error> {
+    ron::ser::to_string_pretty(state, ron::ser::PrettyConfig::default())
+        .map_err(|e| Error::State(memory::invalid_data(e)))
+}
+
+/// Saves the state of an app initialized with a persisted state. Type-erased, so the app data
+/// doesn't need `T: Serialize`.
+pub struct StateSaver<T> {
+    path: PathBuf,
+    serialize: fn(&T) -> Result<String, Error>,
+    /// Last text written, unchanged states aren't written again.
+    saved: Option<String>,
+    pub autosave: Autosave,
+}
+
+impl<T: Serialize> StateSaver<T> {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            serialize: serialize::<T>,
+            saved: None,
+            autosave: Autosave::new(),
+        }
+    }
+}
+
+impl<T> StateSaver<T> {
+    /// Writes the state unless it's unchanged since the last save, see [`memory::write_file`].
+    pub fn save(&mut self, state: &T) -> Result<(), Error> {
+        let text = (self.serialize)(state)?;
+        if self.saved.as_ref() == Some(&text) {
+            return Ok(());
+        }
+
+        memory::write_file(&self.path, &text).map_err(Error::State)?;
+        self.saved = Some(text);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_state_roundtrip() {
+    let path = std::env::temp_dir().join("egui-opengl-internal-test-state.ron");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(load::<Vec<u32>>(&path).unwrap(), None);
+
+    let mut saver = StateSaver::new(path.clone());
+    saver.save(&vec![1u32, 2, 3]).unwrap();
+    assert_eq!(load::<Vec<u32>>(&path).unwrap(), Some(vec![1, 2, 3]));
+
+    // Unchanged states aren't written again.
+    std::fs::remove_file(&path).unwrap();
+    saver.save(&vec![1, 2, 3]).unwrap();
+    assert!(!path.exists());
+
+    std::fs::write(&path, "not ron").unwrap();
+    assert!(matches!(load::<Vec<u32>>(&path), Err(Error::State(_))));
+    let _ = std::fs::remove_file(&path);
+}
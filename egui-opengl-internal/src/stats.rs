@@ -0,0 +1,248 @@
+use gl::types::*;
+use std::{collections::VecDeque, time::Duration};
+use windows::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+/// Counters describing the GPU resources owned by the painter and the cost of the last painted frame.
+/// Read them with [`crate::OpenGLApp::last_frame_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderStats {
+    /// Textures currently allocated.
+    pub textures: usize,
+    /// Bytes of texture memory currently allocated.
+    pub texture_bytes: usize,
+    /// Bytes of texture memory freed since the painter was created.
+    pub freed_texture_bytes: u64,
+    /// Bytes allocated for the vertex and index buffers, which grow to fit the largest mesh.
+    pub buffer_bytes: usize,
+    /// Number of times egui replaced its font atlas.
+    pub atlas_rebuilds: u32,
+    /// Draw calls issued by the last frame, including the compositing pass.
+    pub draw_calls: u32,
+    /// Meshes painted by the last frame.
+    pub meshes: u32,
+    /// Vertices uploaded by the last frame.
+    pub vertices: usize,
+    /// Indices uploaded by the last frame.
+    pub indices: usize,
+    /// Whole and partial texture uploads of the last frame.
+    pub texture_uploads: u32,
+    /// Time spent on the CPU uploading and painting the last frame.
+    pub cpu_time: Duration,
+    /// Time the GPU spent painting, `None` without timer queries. Read back
+    /// without stalling, so it usually lags a frame or two behind the other counters.
+    pub gpu_time: Option<Duration>,
+    /// Frames the UI closure ran and egui tessellated since init.
+    pub ui_runs: u64,
+    /// Frames that painted the primitives of an earlier frame again since init,
+    /// see [`crate::OpenGLApp::set_damage_tracking`] and [`crate::OpenGLApp::set_ui_rate`].
+    pub cached_frames: u64,
+}
+
+/// Per frame counters, kept by the painter while it paints.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct FrameCounters {
+    pub draw_calls: u32,
+    pub meshes: u32,
+    pub vertices: usize,
+    pub indices: usize,
+    pub texture_uploads: u32,
+    pub cpu_time: Duration,
+}
+
+impl FrameCounters {
+    /// Counts a mesh painted with a draw call of its own.
+    pub fn count_mesh(&mut self, vertices: usize, indices: usize) {
+        self.meshes += 1;
+        self.draw_calls += 1;
+        self.vertices += vertices;
+        self.indices += indices;
+    }
+}
+
+/// Measures the GPU time of a frame with a `GL_TIME_ELAPSED` query. While a result is still pending,
+/// frames aren't measured, so the game never waits on the query.
+pub(crate) struct GpuTimer {
+    query: GLuint,
+    pending: bool,
+    last: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// Creates the query, `None` if the context lacks timer queries.
+    pub unsafe fn new(supported: bool) -> Option<Self> {
+        if !supported || !gl::GenQueries::is_loaded() {
+            return None;
+        }
+
+        let mut query = 0;
+        gl::GenQueries(1, &mut query);
+        Some(Self {
+            query,
+            pending: false,
+            last: None,
+        })
+    }
+
+    /// Starts measuring unless the previous result hasn't arrived yet. Returns whether it did.
+    pub unsafe fn begin(&mut self) -> bool {
+        if self.pending {
+            let mut available = 0;
+            gl::GetQueryObjectiv(self.query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+            if available == 0 {
+                return false;
+            }
+
+            let mut nanos = 0;
+            gl::GetQueryObjectui64v(self.query, gl::QUERY_RESULT, &mut nanos);
+            self.last = Some(Duration::from_nanos(nanos));
+            self.pending = false;
+        }
+
+        gl::BeginQuery(gl::TIME_ELAPSED, self.query);
+        true
+    }
+
+    pub unsafe fn end(&mut self) {
+        gl::EndQuery(gl::TIME_ELAPSED);
+        self.pending = true;
+    }
+
+    /// Latest measured frame.
+    pub fn last(&self) -> Option<Duration> {
+        self.last
+    }
+
+    pub unsafe fn delete(self) {
+        gl::DeleteQueries(1, &self.query);
+    }
+}
+
+/// Frame rate of the game and what the overlay costs it. Read it with [`crate::OpenGLApp::frame_timing`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FrameTiming {
+    /// Presents per second over the last second.
+    pub fps: f32,
+    /// Time between the last two presents.
+    pub frame_time: Duration,
+    /// Time the last `render` call took, including frames it skipped.
+    pub overlay_time: Duration,
+}
+
+/// Measures [`FrameTiming`] from `QueryPerformanceCounter` timestamps taken in `render`.
+pub(crate) struct FrameClock {
+    /// Counts per second, `0` until the first frame.
+    frequency: i64,
+    last_present: Option<i64>,
+    /// Counts between presents within the last second, oldest first.
+    intervals: VecDeque<i64>,
+    timing: FrameTiming,
+}
+
+impl FrameClock {
+    pub const fn new() -> Self {
+        Self {
+            frequency: 0,
+            last_present: None,
+            intervals: VecDeque::new(),
+            timing: FrameTiming {
+                fps: 0.,
+                frame_time: Duration::ZERO,
+                overlay_time: Duration::ZERO,
+            },
+        }
+    }
+
+    pub fn now() -> i64 {
+        let mut counter = 0;
+        unsafe {
+            let _ = QueryPerformanceCounter(&mut counter);
+        }
+        counter
+    }
+
+    fn frequency() -> i64 {
+        let mut frequency = 0;
+        unsafe {
+            let _ = QueryPerformanceFrequency(&mut frequency);
+        }
+        frequency.max(1)
+    }
+
+    /// Records a present at counter `now`.
+    pub fn present(&mut self, now: i64) {
+        if self.frequency == 0 {
+            self.frequency = Self::frequency();
+        }
+        self.present_with(now, self.frequency);
+    }
+
+    fn present_with(&mut self, now: i64, frequency: i64) {
+        let Some(last) = self.last_present.replace(now) else {
+            return;
+        };
+
+        let interval = (now - last).max(0);
+        self.intervals.push_back(interval);
+        let mut total: i64 = self.intervals.iter().sum();
+        while total > frequency && self.intervals.len() > 1 {
+            total -= self.intervals.pop_front().unwrap_or_default();
+        }
+
+        self.timing.frame_time = ticks_to_duration(interval, frequency);
+        self.timing.fps = match total {
+            0 => 0.,
+            total => self.intervals.len() as f32 * frequency as f32 / total as f32,
+        };
+    }
+
+    /// Records that the `render` call started at counter `started` finished at `now`.
+    /// Calls whose present wasn't recorded, e.g. presents to another window, are ignored.
+    pub fn finish(&mut self, started: i64, now: i64) {
+        if self.last_present == Some(started) {
+            self.timing.overlay_time = ticks_to_duration(now - started, self.frequency.max(1));
+        }
+    }
+
+    pub fn timing(&self) -> FrameTiming {
+        self.timing
+    }
+}
+
+fn ticks_to_duration(ticks: i64, frequency: i64) -> Duration {
+    Duration::from_secs_f64(ticks.max(0) as f64 / frequency as f64)
+}
+
+#[test]
+fn test_frame_clock() {
+    let mut clock = FrameClock::new();
+    clock.present_with(0, 1000);
+    assert_eq!(clock.timing().fps, 0.);
+
+    for frame in 1..=200 {
+        clock.present_with(frame * 10, 1000);
+    }
+    let timing = clock.timing();
+    assert_eq!(timing.frame_time, Duration::from_millis(10));
+    assert_eq!(timing.fps, 100.);
+
+    // A stall only counts for the second it was in.
+    clock.present_with(2000 + 500, 1000);
+    assert_eq!(clock.timing().frame_time, Duration::from_millis(500));
+    assert_eq!(clock.intervals.iter().sum::<i64>(), 1000);
+
+    clock.finish(2500, 2600);
+    let overlay_time = clock.timing().overlay_time;
+    assert_eq!(overlay_time, Duration::from_millis(100));
+    clock.finish(2700, 2701);
+    assert_eq!(clock.timing().overlay_time, overlay_time);
+}
+
+#[test]
+fn test_frame_counters() {
+    let mut frame = FrameCounters::default();
+    frame.count_mesh(4, 6);
+    frame.count_mesh(3, 3);
+    assert_eq!(frame.meshes, 2);
+    assert_eq!(frame.draw_calls, frame.meshes);
+    assert_eq!((frame.vertices, frame.indices), (7, 9));
+}
@@ -0,0 +1,249 @@
+//! Window subclassing and message hooks for [`crate::OpenGLApp::attach_wnd_proc`].
+
+use crate::{OpenGLApp, WndProcResult};
+use once_cell::sync::OnceCell;
+use windows::{
+    core::w,
+    Win32::{
+        Foundation::{HMODULE, HWND, LPARAM, LRESULT, WPARAM},
+        System::Threading::GetCurrentThreadId,
+        UI::{
+            Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass},
+            WindowsAndMessaging::{
+                CallNextHookEx, GetWindowThreadProcessId, RegisterWindowMessageW, SendMessageW,
+                SetWindowsHookExW, TranslateMessage, UnhookWindowsHookEx, CWPSTRUCT, HC_ACTION,
+                HHOOK, MSG, PM_REMOVE, WH_CALLWNDPROC, WH_GETMESSAGE, WM_KEYDOWN, WM_NCDESTROY,
+                WM_NULL, WM_SYSKEYDOWN,
+            },
+        },
+    },
+};
+
+#[cfg(feature = "parking-lot")]
+use parking_lot::Mutex;
+#[cfg(feature = "spin-lock")]
+use spin::lock_api::Mutex;
+
+/// Window whose thread is hooked with [`hook`]. The app is type-erased, the hooks can't be generic.
+#[derive(Clone, Copy)]
+struct Hooked {
+    window: HWND,
+    app: usize,
+    dispatch: unsafe fn(usize, HWND, u32, WPARAM, LPARAM) -> WndProcResult,
+    get_message: HHOOK,
+    call_wnd_proc: HHOOK,
+}
+
+static HOOKED: Mutex<Vec<Hooked>> = Mutex::new(vec![]);
+
+/// Subclasses `window` with `app`, from the window's thread as `SetWindowSubclass` requires.
+pub(crate) unsafe fn attach<T>(
+    app: &'static OpenGLApp<T>,
+    window: HWND,
+) -> windows::core::Result<()> {
+    let mut result = Ok(());
+    on_window_thread(window, &mut || {
+        let id = app as *const _ as usize;
+        result = SetWindowSubclass(window, Some(subclass_proc::<T>), id, id).ok();
+    })?;
+    result
+}
+
+/// Feeds the messages of `window` to `app` from hooks on the window's thread instead of a subclass,
+/// which games replacing their window procedure can't remove.
+pub(crate) unsafe fn hook<T>(
+    app: &'static OpenGLApp<T>,
+    window: HWND,
+) -> windows::core::Result<()> {
+    let thread = GetWindowThreadProcessId(window, None);
+    if thread == 0 {
+        return Err(windows::core::Error::from_win32());
+    }
+
+    let get_message = SetWindowsHookExW(WH_GETMESSAGE, Some(get_message_hook), HMODULE(0), thread)?;
+    let call_wnd_proc =
+        match SetWindowsHookExW(WH_CALLWNDPROC, Some(sent_message_hook), HMODULE(0), thread) {
+            Ok(hook) => hook,
+            Err(e) => {
+                let _ = UnhookWindowsHookEx(get_message);
+                return Err(e);
+            }
+        };
+
+    HOOKED.lock().push(Hooked {
+        window,
+        app: app as *const _ as usize,
+        dispatch: dispatch::<T>,
+        get_message,
+        call_wnd_proc,
+    });
+    Ok(())
+}
+
+/// Removes the hooks of [`hook`] or the subclass of [`attach`], whichever `window` has.
+pub(crate) unsafe fn detach<T>(app: &OpenGLApp<T>, window: HWND) -> windows::core::Result<()> {
+    if let Some(hooked) = take_hooked(app as *const _ as usize, window) {
+        return unhook(&hooked);
+    }
+
+    // `RemoveWindowSubclass` has to be called from the window's thread too.
+    on_window_thread(window, &mut || remove::<T>(app, window))
+}
+
+unsafe fn remove<T>(app: &OpenGLApp<T>, window: HWND) {
+    let _ = RemoveWindowSubclass(window, Some(subclass_proc::<T>), app as *const _ as usize);
+}
+
+unsafe fn dispatch<T>(
+    app: usize,
+    window: HWND,
+    umsg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> WndProcResult {
+    let app = &*(app as *const OpenGLApp<T>);
+
+    let result = if app.is_ready() {
+        app.wnd_proc_result(umsg, wparam, lparam)
+    } else {
+        WndProcResult::PassThrough
+    };
+
+    // The window is going away, the subclass or hooks have to be removed before it's gone.
+    if umsg == WM_NCDESTROY {
+        detached::<T>(app as *const _ as usize, window);
+    }
+
+    result
+}
+
+unsafe fn detached<T>(app: usize, window: HWND) {
+    let app = &*(app as *const OpenGLApp<T>);
+    app.wnd_proc_detached(window);
+
+    match take_hooked(app as *const _ as usize, window) {
+        Some(hooked) => {
+            let _ = unhook(&hooked);
+        }
+        None => remove::<T>(app, window),
+    }
+}
+
+unsafe extern "system" fn subclass_proc<T>(
+    window: HWND,
+    umsg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: usize,
+    app: usize,
+) -> LRESULT {
+    match dispatch::<T>(app, window, umsg, wparam, lparam) {
+        WndProcResult::Consumed(result) => result,
+        WndProcResult::PassThrough => DefSubclassProc(window, umsg, wparam, lparam),
+    }
+}
+
+/// Sees posted messages, like keyboard and mouse input, as the game takes them from its queue.
+/// Consumed ones are turned into `WM_NULL`.
+unsafe extern "system" fn get_message_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    // Messages peeked without removing them are seen again when they're removed.
+    if code == HC_ACTION as i32 && wparam.0 == PM_REMOVE.0 as usize {
+        let msg = &mut *(lparam.0 as *mut MSG);
+        if let Some(hooked) = find_hooked(msg.hwnd) {
+            let result =
+                (hooked.dispatch)(hooked.app, msg.hwnd, msg.message, msg.wParam, msg.lParam);
+            if result.is_consumed() {
+                // The game would translate it into `WM_CHAR` after taking it from the queue.
+                if matches!(msg.message, WM_KEYDOWN | WM_SYSKEYDOWN) {
+                    let _ = TranslateMessage(msg);
+                }
+                msg.message = WM_NULL;
+            }
+        }
+    }
+
+    CallNextHookEx(None, code, wparam, lparam)
+}
+
+/// Sees sent messages before the window procedure does. Their results can't be replaced,
+/// so egui only observes them.
+unsafe extern "system" fn sent_message_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let message = &*(lparam.0 as *const CWPSTRUCT);
+        if let Some(hooked) = find_hooked(message.hwnd) {
+            (hooked.dispatch)(
+                hooked.app,
+                message.hwnd,
+                message.message,
+                message.wParam,
+                message.lParam,
+            );
+        }
+    }
+
+    CallNextHookEx(None, code, wparam, lparam)
+}
+
+/// Copied out of the list, dispatching may send messages that run the hooks again.
+fn find_hooked(window: HWND) -> Option<Hooked> {
+    HOOKED
+        .lock()
+        .iter()
+        .find(|hooked| hooked.window == window)
+        .copied()
+}
+
+fn take_hooked(app: usize, window: HWND) -> Option<Hooked> {
+    let mut hooked = HOOKED.lock();
+    let index = hooked
+        .iter()
+        .position(|hooked| hooked.app == app && hooked.window == window)?;
+    Some(hooked.remove(index))
+}
+
+unsafe fn unhook(hooked: &Hooked) -> windows::core::Result<()> {
+    UnhookWindowsHookEx(hooked.get_message).and(UnhookWindowsHookEx(hooked.call_wnd_proc))
+}
+
+/// Runs `f` on the thread owning `window`. From other threads a `WH_CALLWNDPROC` hook catches
+/// a message sent to the window, so this blocks until the window's thread processes messages.
+unsafe fn on_window_thread(window: HWND, f: &mut dyn FnMut()) -> windows::core::Result<()> {
+    let thread = GetWindowThreadProcessId(window, None);
+    if thread == 0 {
+        return Err(windows::core::Error::from_win32());
+    }
+    if thread == GetCurrentThreadId() {
+        f();
+        return Ok(());
+    }
+
+    let hook = SetWindowsHookExW(WH_CALLWNDPROC, Some(run_hook), HMODULE(0), thread)?;
+    let mut f = f;
+    SendMessageW(
+        window,
+        run_message(),
+        WPARAM(0),
+        LPARAM(&mut f as *mut &mut dyn FnMut() as isize),
+    );
+    UnhookWindowsHookEx(hook)
+}
+
+unsafe extern "system" fn run_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let message = &*(lparam.0 as *const CWPSTRUCT);
+        if message.message == run_message() {
+            let f = &mut *(message.lParam.0 as *mut &mut dyn FnMut());
+            f();
+        }
+    }
+
+    CallNextHookEx(None, code, wparam, lparam)
+}
+
+/// Message sent to a window to run a closure on its thread, see [`on_window_thread`].
+fn run_message() -> u32 {
+    static MESSAGE: OnceCell<u32> = OnceCell::new();
+    *MESSAGE.get_or_init(|| unsafe {
+        RegisterWindowMessageW(w!("egui-opengl-internal run on window thread"))
+    })
+}
@@ -0,0 +1,132 @@
+use crate::utils;
+use std::mem::size_of;
+use windows::Win32::{
+    Foundation::BOOL,
+    Graphics::{
+        Gdi::HDC,
+        OpenGL::{DescribePixelFormat, GetPixelFormat, PFD_DOUBLEBUFFER, PIXELFORMATDESCRIPTOR},
+    },
+};
+
+const WGL_FRAMEBUFFER_SRGB_CAPABLE_ARB: i32 = 0x20A9;
+
+type FnWglGetPixelFormatAttribivARB =
+    unsafe extern "system" fn(HDC, i32, i32, u32, *const i32, *mut i32) -> BOOL;
+
+/// Description of the pixel format selected on the game's device context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelFormatInfo {
+    pub index: i32,
+    pub color_bits: u8,
+    pub alpha_bits: u8,
+    pub depth_bits: u8,
+    pub stencil_bits: u8,
+    pub double_buffered: bool,
+    /// Whether the driver reports `WGL_FRAMEBUFFER_SRGB_CAPABLE_ARB` for this format.
+    pub srgb_capable: bool,
+}
+
+impl PixelFormatInfo {
+    /// Describes the pixel format of `hdc`. sRGB capability can only be queried while a context is current.
+    ///
+    /// # Safety
+    /// `hdc` must be a valid device context, and a current OpenGL context must be usable with it.
+    pub unsafe fn query(hdc: HDC) -> Option<Self> {
+        let index = GetPixelFormat(hdc);
+        if index == 0 {
+            return None;
+        }
+
+        let mut pfd = PIXELFORMATDESCRIPTOR::default();
+        if DescribePixelFormat(
+            hdc,
+            index,
+            size_of::<PIXELFORMATDESCRIPTOR>() as _,
+            Some(&mut pfd),
+        ) == 0
+        {
+            return None;
+        }
+
+        Some(Self {
+            index,
+            color_bits: pfd.cColorBits,
+            alpha_bits: pfd.cAlphaBits,
+            depth_bits: pfd.cDepthBits,
+            stencil_bits: pfd.cStencilBits,
+            double_buffered: pfd.dwFlags.0 & PFD_DOUBLEBUFFER.0 != 0,
+            srgb_capable: query_srgb_capable(hdc, index),
+        })
+    }
+}
+
+unsafe fn query_srgb_capable(hdc: HDC, index: i32) -> bool {
+    let proc = utils::get_proc_address("wglGetPixelFormatAttribivARB");
+    if proc.is_null() {
+        return false;
+    }
+
+    let get_attribs: FnWglGetPixelFormatAttribivARB = std::mem::transmute(proc);
+    let attrib = WGL_FRAMEBUFFER_SRGB_CAPABLE_ARB;
+    let mut value = 0;
+
+    get_attribs(hdc, index, 0, 1, &attrib, &mut value).as_bool() && value != 0
+}
+
+/// How the painter blends the UI onto the game's framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Premultiplied alpha for both color and alpha channels.
+    Premultiplied,
+    /// Premultiplied color, while the destination alpha is only ever increased.
+    /// Used when the framebuffer has an alpha channel, so the overlay doesn't punch holes into it.
+    PremultipliedKeepAlpha,
+}
+
+/// Rendering strategy selected from the pixel format of the game's device context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceConfig {
+    pub pixel_format: Option<PixelFormatInfo>,
+    /// If `true`, `GL_FRAMEBUFFER_SRGB` is enabled and the shader outputs linear colors,
+    /// otherwise the shader outputs gamma space colors directly.
+    pub framebuffer_srgb: bool,
+    pub blend: BlendMode,
+}
+
+impl SurfaceConfig {
+    /// Picks a strategy for `hdc`. Should be called with the overlay context current.
+    ///
+    /// # Safety
+    /// Same as [`PixelFormatInfo::query`].
+    pub unsafe fn detect(hdc: HDC) -> Self {
+        let pixel_format = PixelFormatInfo::query(hdc);
+
+        match pixel_format {
+            Some(pf) => log!(
+                "Pixel format {}: {} color bits, {} alpha bits, {} depth bits, {} stencil bits, double buffered: {}, sRGB capable: {}",
+                pf.index,
+                pf.color_bits,
+                pf.alpha_bits,
+                pf.depth_bits,
+                pf.stencil_bits,
+                pf.double_buffered,
+                pf.srgb_capable
+            ),
+            None => log!("Failed to describe pixel format of the device context"),
+        }
+
+        Self::from_pixel_format(pixel_format)
+    }
+
+    /// Unknown pixel formats keep `GL_FRAMEBUFFER_SRGB` enabled, matching what the painter always did.
+    pub fn from_pixel_format(pixel_format: Option<PixelFormatInfo>) -> Self {
+        Self {
+            pixel_format,
+            framebuffer_srgb: pixel_format.is_none_or(|pf| pf.srgb_capable),
+            blend: match pixel_format {
+                Some(pf) if pf.alpha_bits > 0 => BlendMode::PremultipliedKeepAlpha,
+                _ => BlendMode::Premultiplied,
+            },
+        }
+    }
+}
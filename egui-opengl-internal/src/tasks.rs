@@ -0,0 +1,124 @@
+use egui::Context;
+use once_cell::sync::OnceCell;
+use std::sync::Arc;
+
+#[cfg(feature = "parking-lot")]
+use parking_lot::Mutex;
+#[cfg(feature = "spin-lock")]
+use spin::lock_api::Mutex;
+
+/// Update of the app state produced by a finished background job.
+type Completion<T> = Box<dyn FnOnce(&mut T) + Send + 'static>;
+
+struct Shared<T> {
+    completed: Mutex<Vec<Completion<T>>>,
+    /// Replaced when the app is initialized again.
+    ctx: Mutex<Option<Context>>,
+}
+
+impl<T> Shared<T> {
+    fn complete(&self, completion: Completion<T>) {
+        self.completed.lock().push(completion);
+
+        if let Some(ctx) = self.ctx.lock().as_ref() {
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// Queue of finished background jobs, applied to the app state right before the next frame.
+/// Lives outside of the app lock, so jobs can be spawned from within the UI closure.
+pub struct Tasks<T> {
+    shared: OnceCell<Arc<Shared<T>>>,
+}
+
+impl<T> Tasks<T> {
+    pub const fn new() -> Self {
+        Self {
+            shared: OnceCell::new(),
+        }
+    }
+
+    fn shared(&self) -> &Arc<Shared<T>> {
+        self.shared.get_or_init(|| {
+            Arc::new(Shared {
+                completed: Mutex::new(vec![]),
+                ctx: Mutex::new(None),
+            })
+        })
+    }
+
+    /// Sets the context that gets a repaint requested whenever a job finishes.
+    pub fn set_context(&self, ctx: Context) {
+        *self.shared().ctx.lock() = Some(ctx);
+    }
+
+    pub fn sender(&self) -> TaskSender<T> {
+        TaskSender {
+            shared: self.shared().clone(),
+        }
+    }
+
+    /// Applies all finished jobs to `state`.
+    pub fn apply(&self, state: &mut T) {
+        let Some(shared) = self.shared.get() else {
+            return;
+        };
+
+        let completed = std::mem::take(&mut *shared.completed.lock());
+        for completion in completed {
+            completion(state);
+        }
+    }
+}
+
+impl<T: 'static> Tasks<T> {
+    pub fn spawn<C>(&self, job: impl FnOnce() -> C + Send + 'static)
+    where
+        C: FnOnce(&mut T) + Send + 'static,
+    {
+        let sender = self.sender();
+        std::thread::spawn(move || sender.send(job()));
+    }
+
+    #[cfg(feature = "tokio")]
+    pub fn spawn_future<C>(&self, future: impl std::future::Future<Output = C> + Send + 'static)
+    where
+        C: FnOnce(&mut T) + Send + 'static,
+    {
+        static RUNTIME: OnceCell<tokio::runtime::Runtime> = OnceCell::new();
+
+        let runtime = RUNTIME.get_or_init(|| {
+            expect!(
+                tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(2)
+                    .enable_all()
+                    .build(),
+                "Failed to start tokio runtime"
+            )
+        });
+
+        let sender = self.sender();
+        runtime.spawn(async move { sender.send(future.await) });
+    }
+}
+
+/// Handle for delivering results into the app state from threads you manage yourself.
+/// Results are applied before the next frame and a repaint is requested.
+pub struct TaskSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for TaskSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> TaskSender<T> {
+    pub fn send(&self, completion: impl FnOnce(&mut T) + Send + 'static) {
+        self.shared.complete(Box::new(completion));
+    }
+}
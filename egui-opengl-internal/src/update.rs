@@ -0,0 +1,229 @@
+use crate::notifications::{Notification, Notifier};
+use windows::{
+    core::{HSTRING, PCWSTR},
+    Win32::Networking::WinInet::{
+        HttpQueryInfoW, InternetCloseHandle, InternetOpenUrlW, InternetOpenW, InternetReadFile,
+        HTTP_QUERY_FLAG_NUMBER, HTTP_QUERY_STATUS_CODE, INTERNET_FLAG_NO_CACHE_WRITE,
+        INTERNET_FLAG_RELOAD, INTERNET_OPEN_TYPE_PRECONFIG,
+    },
+};
+
+/// Manifests larger than this are not update manifests.
+const MAX_MANIFEST_SIZE: usize = 64 * 1024;
+
+/// Contents of an update manifest, a plain text file of `key = value` lines:
+/// ```text
+/// version = 1.4.0
+/// url = https://example.com/releases/1.4.0
+/// notes = Fixes the crash on startup.
+/// ```
+/// Only `version` is required.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateManifest {
+    pub version: String,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl UpdateManifest {
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut version = None;
+        let mut url = None;
+        let mut notes = None;
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+
+            match key.trim() {
+                "version" => version = Some(value.to_owned()),
+                "url" => url = Some(value.to_owned()),
+                "notes" => notes = Some(value.to_owned()),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            version: version?,
+            url,
+            notes,
+        })
+    }
+
+    /// Returns `true` if the manifest describes a newer build than `current`.
+    pub fn is_newer_than(&self, current: &str) -> bool {
+        is_newer(current, &self.version)
+    }
+}
+
+/// Fetches the manifest at `url` and pushes a notification if it announces a version newer than `current`.
+/// Failures are logged and otherwise ignored, an update check must never get in the way.
+pub fn check(url: &str, current: &str, notifier: &Notifier) {
+    let manifest = match fetch(url) {
+        Some(text) => UpdateManifest::parse(&text),
+        None => return,
+    };
+
+    let Some(manifest) = manifest else {
+        log!("Update manifest at {url} has no version");
+        return;
+    };
+
+    if !manifest.is_newer_than(current) {
+        return;
+    }
+
+    let mut notification = Notification::new("Update available")
+        .text(match manifest.notes.as_ref() {
+            Some(notes) => format!(
+                "Version {} is out (you have {current}).\n{notes}",
+                manifest.version
+            ),
+            None => format!("Version {} is out (you have {current}).", manifest.version),
+        })
+        .duration(None);
+    if let Some(url) = manifest.url {
+        notification = notification.link("Download", url);
+    }
+
+    notifier.notify(notification);
+}
+
+fn fetch(url: &str) -> Option<String> {
+    unsafe {
+        let internet = InternetOpenW(
+            &HSTRING::from("egui-opengl-internal"),
+            INTERNET_OPEN_TYPE_PRECONFIG.0,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            0,
+        );
+        if internet.is_null() {
+            log!(
+                "Update check failed: {}",
+                windows::core::Error::from_win32()
+            );
+            return None;
+        }
+
+        let request = InternetOpenUrlW(
+            internet,
+            &HSTRING::from(url),
+            None,
+            INTERNET_FLAG_RELOAD | INTERNET_FLAG_NO_CACHE_WRITE,
+            0,
+        );
+        let body = if request.is_null() {
+            log!(
+                "Update check failed: {}",
+                windows::core::Error::from_win32()
+            );
+            None
+        } else {
+            let body = read_body(request);
+            let _ = InternetCloseHandle(request);
+            body
+        };
+
+        let _ = InternetCloseHandle(internet);
+        body
+    }
+}
+
+unsafe fn read_body(request: *const std::ffi::c_void) -> Option<String> {
+    // Not every scheme has a status code, only bail on an explicit non-success one.
+    let mut status = 0u32;
+    let mut len = std::mem::size_of::<u32>() as u32;
+    if HttpQueryInfoW(
+        request,
+        HTTP_QUERY_STATUS_CODE | HTTP_QUERY_FLAG_NUMBER,
+        Some(&mut status as *mut u32 as _),
+        &mut len,
+        None,
+    )
+    .is_ok()
+        && !(200..300).contains(&status)
+    {
+        log!("Update check failed: HTTP {status}");
+        return None;
+    }
+
+    let mut body = vec![];
+    let mut buf = [0u8; 4096];
+    loop {
+        let mut read = 0;
+        if let Err(e) = InternetReadFile(request, buf.as_mut_ptr() as _, buf.len() as _, &mut read)
+        {
+            log!("Update check failed: {e}");
+            return None;
+        }
+
+        if read == 0 {
+            break;
+        }
+
+        body.extend_from_slice(&buf[..read as usize]);
+        if body.len() > MAX_MANIFEST_SIZE {
+            log!("Update check failed: manifest is too large");
+            return None;
+        }
+    }
+
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Compares dotted versions numerically, `1.10` is newer than `1.9`. A leading `v` and
+/// anything after `-` or `+` (pre-release, build metadata) is ignored.
+fn is_newer(current: &str, candidate: &str) -> bool {
+    fn parts(version: &str) -> Vec<u64> {
+        version
+            .trim()
+            .trim_start_matches(['v', 'V'])
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+
+    let current = parts(current);
+    let candidate = parts(candidate);
+    let len = current.len().max(candidate.len());
+
+    for i in 0..len {
+        let a = current.get(i).copied().unwrap_or(0);
+        let b = candidate.get(i).copied().unwrap_or(0);
+        if a != b {
+            return b > a;
+        }
+    }
+
+    false
+}
+
+#[test]
+fn test_is_newer() {
+    assert!(is_newer("1.9.0", "1.10.0"));
+    assert!(is_newer("v1.2", "1.2.1"));
+    assert!(!is_newer("1.2.0", "1.2"));
+    assert!(!is_newer("2.0.0", "1.99.99"));
+    assert!(!is_newer("1.3.0", "1.3.0-beta"));
+}
+
+#[test]
+fn test_parse_manifest() {
+    let manifest =
+        UpdateManifest::parse("# latest\nversion = 1.4.0\nurl=https://example.com\n").unwrap();
+    assert_eq!(manifest.version, "1.4.0");
+    assert_eq!(manifest.url.as_deref(), Some("https://example.com"));
+    assert_eq!(manifest.notes, None);
+
+    assert_eq!(UpdateManifest::parse("url = https://example.com"), None);
+}
@@ -1,7 +1,9 @@
-use std::ffi::CString;
+use std::{cell::Cell, ffi::CString};
+
+pub use crate::logger::{init_default_file_logger, init_file_logger};
 
 use windows::{
-    core::PCSTR,
+    core::{s, PCSTR},
     Win32::{
                 Graphics::OpenGL::wglGetProcAddress,
         System::{
@@ -31,6 +33,64 @@ pub unsafe fn get_proc_address(function_name: &str) -> *const usize {
     std::ptr::null()
 }
 
+/// Address of `SwapBuffers` in gdi32.dll, the present function most games call. It calls `wglSwapBuffers`
+/// of the opengl32.dll loaded in the process, so the `wglSwapBuffers` hook usually sees the present already.
+/// Hooking `SwapBuffers` too catches games where that call doesn't reach the hooked export, e.g. with a wrapper
+/// opengl32.dll shipped next to the game. [`PresentGuard`] keeps the UI from being drawn twice when both fire.
+/// Null if gdi32.dll isn't loaded.
+pub fn get_gdi_swap_buffers() -> *const usize {
+    let gdi32 = get_module("gdi32.dll");
+    if gdi32.0 == 0 {
+        return std::ptr::null();
+    }
+
+    match unsafe { GetProcAddress(gdi32, s!("SwapBuffers")) } {
+        Some(process_address) => process_address as _,
+        None => std::ptr::null(),
+    }
+}
+
+/// Address of `wglSwapLayerBuffers` in opengl32.dll, used by some older games to present. Only calls
+/// with `WGL_SWAP_MAIN_PLANE` set present the frame the overlay draws on.
+pub fn get_swap_layer_buffers() -> *const usize {
+    // Exported by opengl32.dll, so no context is needed to look it up.
+    unsafe { get_proc_address("wglSwapLayerBuffers") }
+}
+
+thread_local! {
+    static PRESENTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Keeps a frame from being drawn twice when several present functions are hooked and one calls the other,
+/// e.g. `SwapBuffers` calling `wglSwapBuffers`. Hold it until the game's present function returned:
+/// ```ignore
+/// unsafe extern "system" fn hk_swap_buffers(hdc: HDC) -> BOOL {
+///     let _guard = PresentGuard::enter().map(|guard| {
+///         let _ = APP.render(hdc);
+///         guard
+///     });
+///     ORIGINAL.call(hdc)
+/// }
+/// ```
+pub struct PresentGuard(());
+
+impl PresentGuard {
+    /// Returns `None` if a hook further up the stack of this thread is presenting already.
+    pub fn enter() -> Option<Self> {
+        if PRESENTING.with(|presenting| presenting.replace(true)) {
+            None
+        } else {
+            Some(Self(()))
+        }
+    }
+}
+
+impl Drop for PresentGuard {
+    fn drop(&mut self) {
+        PRESENTING.with(|presenting| presenting.set(false));
+    }
+}
+
 pub fn get_module(module_name: &str) -> HMODULE {
     unsafe {
         let o = CString::new(module_name).unwrap();
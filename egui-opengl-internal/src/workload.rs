@@ -0,0 +1,86 @@
+//! Synthetic egui workloads for measuring painter throughput, see `benches/painter.rs`.
+
+use egui::{Color32, Context, Pos2, Stroke};
+
+/// Describes a UI of configurable size. Rendering the same workload always produces the same frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Workload {
+    /// Number of windows, laid out in a grid.
+    pub windows: usize,
+    /// Number of labels in every window.
+    pub labels: usize,
+    /// Number of points of the line plot in every window, `0` for none.
+    pub plot_points: usize,
+}
+
+impl Workload {
+    pub const fn new(windows: usize, labels: usize, plot_points: usize) -> Self {
+        Self {
+            windows,
+            labels,
+            plot_points,
+        }
+    }
+
+    /// Short description for benchmark names, e.g. `"4w_100l_0p"`.
+    pub fn name(&self) -> String {
+        format!("{}w_{}l_{}p", self.windows, self.labels, self.plot_points)
+    }
+
+    pub fn ui(&self, ctx: &Context) {
+        let columns = (self.windows as f32).sqrt().ceil().max(1.) as usize;
+
+        for i in 0..self.windows {
+            let pos = Pos2::new((i % columns) as f32 * 220., (i / columns) as f32 * 260.);
+
+            egui::Window::new(format!("Window {i}"))
+                .id(egui::Id::new("workload").with(i))
+                .default_pos(pos)
+                .default_size([200., 240.])
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(f32::INFINITY)
+                        .show(ui, |ui| {
+                            for label in 0..self.labels {
+                                ui.label(format!("Label {label}: the quick brown fox"));
+                            }
+
+                            if self.plot_points > 0 {
+                                self.plot(ui);
+                            }
+                        });
+                });
+        }
+    }
+
+    fn plot(&self, ui: &mut egui::Ui) {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(180., 100.), egui::Sense::hover());
+        let step = rect.width() / self.plot_points as f32;
+
+        let points = (0..self.plot_points)
+            .map(|i| {
+                let x = i as f32 * step;
+                let y = (i as f32 * 0.05).sin() * 0.4 + (i as f32 * 0.31).sin() * 0.1;
+                Pos2::new(rect.left() + x, rect.center().y - y * rect.height())
+            })
+            .collect();
+
+        ui.painter().line_segment(
+            [rect.left_center(), rect.right_center()],
+            Stroke::new(1_f32, Color32::GRAY),
+        );
+        ui.painter().add(egui::Shape::line(
+            points,
+            Stroke::new(1.5_f32, Color32::LIGHT_BLUE),
+        ));
+    }
+}
+
+/// Workloads covering text-heavy, window-heavy and geometry-heavy frames.
+pub const WORKLOADS: &[Workload] = &[
+    Workload::new(1, 10, 0),
+    Workload::new(4, 100, 0),
+    Workload::new(16, 50, 0),
+    Workload::new(1, 0, 100_000),
+    Workload::new(4, 50, 10_000),
+];
@@ -0,0 +1,264 @@
+//! Renders known UIs offscreen and compares them to the references in `tests/golden`.
+//! Run with `cargo test --features golden`, set `UPDATE_GOLDEN=1` to accept intended changes.
+#![cfg(feature = "golden")]
+
+use egui::{Color32, Rounding, Stroke};
+use egui_opengl_internal::{
+    golden::{assert_golden, GoldenRenderer},
+    CallbackFn, CustomShader, ShaderError, SurfaceConfig,
+};
+use std::sync::Arc;
+
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+/// Allows for rounding differences between drivers.
+const TOLERANCE: u8 = 2;
+
+fn renderer(framebuffer_srgb: bool) -> GoldenRenderer {
+    let mut surface = SurfaceConfig::from_pixel_format(None);
+    surface.framebuffer_srgb = framebuffer_srgb;
+
+    GoldenRenderer::new(surface).expect("failed to create hidden OpenGL context")
+}
+
+fn text_ui(ctx: &egui::Context) {
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.label("The quick brown fox jumps over the lazy dog");
+        ui.heading("Heading 0123456789");
+        ui.small("small text, thin strokes");
+    });
+}
+
+fn blend_ui(ctx: &egui::Context) {
+    egui::Area::new("shapes").show(ctx, |ui| {
+        let painter = ui.painter();
+        painter.rect_filled(
+            egui::Rect::from_min_size([8., 8.].into(), [64., 64.].into()),
+            Rounding::same(8.),
+            Color32::from_rgba_unmultiplied(255, 0, 0, 128),
+        );
+        painter.rect_filled(
+            egui::Rect::from_min_size([40., 40.].into(), [64., 64.].into()),
+            Rounding::ZERO,
+            Color32::from_rgba_unmultiplied(0, 0, 255, 64),
+        );
+        painter.circle_stroke([96., 24.].into(), 16., Stroke::new(1.5_f32, Color32::WHITE));
+    });
+}
+
+#[test]
+fn golden_text_srgb() {
+    let image = renderer(true).render((320, 96), 1.0, text_ui);
+    assert_golden(GOLDEN_DIR, "text_srgb", &image, TOLERANCE);
+}
+
+#[test]
+fn golden_text_linear() {
+    let image = renderer(false).render((320, 96), 1.0, text_ui);
+    assert_golden(GOLDEN_DIR, "text_linear", &image, TOLERANCE);
+}
+
+#[test]
+fn golden_text_hidpi() {
+    let image = renderer(true).render((640, 192), 2.0, text_ui);
+    assert_golden(GOLDEN_DIR, "text_hidpi", &image, TOLERANCE);
+}
+
+#[test]
+fn golden_blend() {
+    let image = renderer(true).render((128, 128), 1.0, blend_ui);
+    assert_golden(GOLDEN_DIR, "blend", &image, TOLERANCE);
+}
+
+#[test]
+fn large_mesh_is_not_truncated() {
+    // 100x100 feathered rects tessellate into a single mesh with well over 65535 vertices.
+    let image = renderer(true).render((200, 200), 1.0, |ctx| {
+        egui::Area::new("grid").show(ctx, |ui| {
+            let painter = ui.painter();
+            for y in 0..100 {
+                for x in 0..100 {
+                    painter.rect_filled(
+                        egui::Rect::from_min_size(
+                            [x as f32 * 2., y as f32 * 2.].into(),
+                            [2., 2.].into(),
+                        ),
+                        Rounding::ZERO,
+                        Color32::WHITE,
+                    );
+                }
+            }
+        });
+    });
+
+    // The last rect ends up beyond the first 65535 vertices.
+    let last = ((199 * image.width + 199) * 4) as usize;
+    assert_eq!(image.pixels[last + 3], 255);
+}
+
+#[test]
+fn paint_callback_runs_in_its_rect() {
+    let image = renderer(true).render((64, 64), 1.0, |ctx| {
+        egui::Area::new("callback").show(ctx, |ui| {
+            let rect = egui::Rect::from_min_size([16., 16.].into(), [16., 16.].into());
+            // `glClear` ignores the viewport, only the scissor of the clip rect confines it.
+            ui.painter().with_clip_rect(rect).add(egui::PaintCallback {
+                rect,
+                callback: Arc::new(CallbackFn::new(|_, _| unsafe {
+                    gl::ClearColor(0., 1., 0., 1.);
+                    gl::Clear(gl::COLOR_BUFFER_BIT);
+                })),
+            });
+        });
+    });
+
+    let pixel = |x: u32, y: u32| {
+        let i = ((y * image.width + x) * 4) as usize;
+        &image.pixels[i..i + 4]
+    };
+    assert_eq!(pixel(20, 20), [0, 255, 0, 255]);
+    assert_eq!(pixel(40, 40)[3], 0);
+}
+
+#[test]
+fn scissor_applies_to_every_mesh() {
+    let image = renderer(true).render((64, 64), 1.0, |ctx| {
+        egui::Area::new("clipped").show(ctx, |ui| {
+            let rect =
+                |min: f32, size: f32| egui::Rect::from_min_size([min; 2].into(), [size; 2].into());
+            for clip in [rect(0., 16.), rect(32., 16.)] {
+                ui.painter().with_clip_rect(clip).rect_filled(
+                    rect(0., 64.),
+                    Rounding::ZERO,
+                    Color32::WHITE,
+                );
+            }
+        });
+    });
+
+    let alpha = |x: u32, y: u32| image.pixels[((y * image.width + x) * 4 + 3) as usize];
+    assert_eq!(alpha(8, 8), 255);
+    assert_eq!(alpha(40, 40), 255);
+    // Outside of both clip rects, the second mesh would cover it without a scissor.
+    assert_eq!(alpha(56, 56), 0);
+    assert_eq!(alpha(24, 24), 0);
+}
+
+#[test]
+fn compositing_matches_direct_painting() {
+    let direct = renderer(true).render((128, 128), 1.0, blend_ui);
+
+    let mut composited = renderer(true);
+    composited.painter().set_compositing(true);
+    let image = composited.render((128, 128), 1.0, blend_ui);
+    assert!(image.compare(&direct, TOLERANCE).is_match());
+
+    composited.painter().set_opacity(0.5);
+    let faded = composited.render((128, 128), 1.0, blend_ui);
+    let alpha = |image: &egui_opengl_internal::golden::Image| image.pixels[(20 * 128 + 20) * 4 + 3];
+    assert!(alpha(&faded).abs_diff(alpha(&direct) / 2) <= TOLERANCE);
+}
+
+/// Paints everything red, keeping the coverage of the built-in shader.
+const RED_FRAGMENT: &str = r#"
+#ifdef GL_ES
+precision highp float;
+#endif
+
+uniform sampler2D u_sampler;
+
+#if NEW_SHADER_INTERFACE
+in vec2 v_tc;
+in vec4 v_rgba;
+out vec4 f_color;
+#define gl_FragColor f_color
+#define texture2D texture
+#else
+varying vec2 v_tc;
+varying vec4 v_rgba;
+#endif
+
+void main() {
+    float a = v_rgba.a * texture2D(u_sampler, v_tc).a;
+    gl_FragColor = vec4(a, 0.0, 0.0, a);
+}
+"#;
+
+#[test]
+fn custom_shader_replaces_and_falls_back() {
+    let white_rect = |ctx: &egui::Context| {
+        egui::Area::new("rect").show(ctx, |ui| {
+            ui.painter().rect_filled(
+                egui::Rect::from_min_size([8., 8.].into(), [32., 32.].into()),
+                Rounding::ZERO,
+                Color32::WHITE,
+            );
+        });
+    };
+    let pixel = |image: &egui_opengl_internal::golden::Image| {
+        let i = (20 * image.width + 20) as usize * 4;
+        [image.pixels[i], image.pixels[i + 1], image.pixels[i + 2]]
+    };
+
+    let mut renderer = renderer(true);
+    let red = CustomShader {
+        fragment: Some(RED_FRAGMENT.into()),
+        ..Default::default()
+    };
+    renderer.painter().set_custom_shader(Some(&red)).unwrap();
+    assert_eq!(
+        pixel(&renderer.render((64, 64), 1.0, white_rect)),
+        [255, 0, 0]
+    );
+
+    // Ignores the texture, so `u_sampler` is optimized out. The red shader stays in use.
+    let broken = CustomShader {
+        fragment: Some("void main() { gl_FragColor = vec4(1.0); }".into()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        renderer.painter().set_custom_shader(Some(&broken)),
+        Err(ShaderError::Compile { .. } | ShaderError::MissingInput(_))
+    ));
+    assert_eq!(
+        pixel(&renderer.render((64, 64), 1.0, white_rect)),
+        [255, 0, 0]
+    );
+
+    renderer.painter().set_custom_shader(None).unwrap();
+    assert_eq!(
+        pixel(&renderer.render((64, 64), 1.0, white_rect)),
+        [255, 255, 255]
+    );
+}
+
+#[test]
+fn debug_overlay_outlines_clip_rects() {
+    let mut renderer = renderer(true);
+    renderer.painter().set_debug_overlay(true);
+    let image = renderer.render((64, 64), 1.0, blend_ui);
+
+    // The clip rect covers the whole screen, its outline runs along the left edge.
+    let left = ((32 * image.width) * 4) as usize;
+    assert_eq!(&image.pixels[left..left + 4], &[255, 0, 0, 255]);
+    assert!(!renderer.painter().mesh_counts().is_empty());
+}
+
+#[test]
+fn render_resolution_is_stretched_onto_the_target() {
+    let mut renderer = renderer(true);
+    renderer.painter().set_render_resolution(Some((128, 128)));
+    let image = renderer.render((64, 64), 1.0, |ctx| {
+        egui::Area::new("square").show(ctx, |ui| {
+            ui.painter().rect_filled(
+                egui::Rect::from_min_size(egui::Pos2::ZERO, [16., 16.].into()),
+                Rounding::ZERO,
+                Color32::WHITE,
+            );
+        });
+    });
+
+    // Painted at twice the target's size, the square ends up half as large.
+    let alpha = |x: u32, y: u32| image.pixels[((y * image.width + x) * 4 + 3) as usize];
+    assert_eq!(alpha(4, 4), 255);
+    assert_eq!(alpha(12, 12), 0);
+}